@@ -0,0 +1,89 @@
+// Bridges between Rust allocators from the `composable-allocators` crate
+// and C++ `std::pmr::memory_resource`, for use with its `pmr-ffi` feature.
+//
+// Define COMPOSABLE_ALLOCATORS_PMR_IMPLEMENTATION before including this
+// in exactly one source file, to define the functions used by Rust's
+// `FromMemoryResource`.
+#pragma once
+
+#include <cstddef>
+#include <memory_resource>
+#include <new>
+
+namespace composable_allocators {
+
+// Matches Rust's `MemoryResourceVTable`.
+struct vtable {
+  void *(*allocate)(const void *data, std::size_t bytes, std::size_t align);
+  void (*deallocate)(const void *data, void *ptr, std::size_t bytes,
+                     std::size_t align);
+};
+
+// Matches Rust's `ToMemoryResource`.
+struct raw_resource {
+  const void *data;
+  const composable_allocators::vtable *vtable;
+};
+
+// A memory resource which allocates from a Rust allocator.
+//
+// The Rust allocator must outlive this.
+class rust_memory_resource final : public std::pmr::memory_resource {
+public:
+  explicit rust_memory_resource(raw_resource raw) noexcept : raw_(raw) {}
+
+  raw_resource raw() const noexcept { return raw_; }
+
+private:
+  void *do_allocate(std::size_t bytes, std::size_t align) override {
+    void *ptr = raw_.vtable->allocate(raw_.data, bytes, align);
+    if (ptr == nullptr) {
+      throw std::bad_alloc();
+    }
+    return ptr;
+  }
+  void do_deallocate(void *ptr, std::size_t bytes,
+                     std::size_t align) override {
+    raw_.vtable->deallocate(raw_.data, ptr, bytes, align);
+  }
+  bool do_is_equal(const std::pmr::memory_resource &other) const
+      noexcept override {
+    auto *it = dynamic_cast<const rust_memory_resource *>(&other);
+    return it != nullptr && it->raw_.data == raw_.data &&
+           it->raw_.vtable == raw_.vtable;
+  }
+
+  raw_resource raw_;
+};
+
+} // namespace composable_allocators
+
+extern "C" {
+// Returns null if `resource` throws.
+void *composable_allocators_pmr_allocate(std::pmr::memory_resource *resource,
+                                         std::size_t bytes,
+                                         std::size_t align) noexcept;
+void composable_allocators_pmr_deallocate(std::pmr::memory_resource *resource,
+                                          void *ptr, std::size_t bytes,
+                                          std::size_t align) noexcept;
+}
+
+#ifdef COMPOSABLE_ALLOCATORS_PMR_IMPLEMENTATION
+extern "C" void *
+composable_allocators_pmr_allocate(std::pmr::memory_resource *resource,
+                                   std::size_t bytes,
+                                   std::size_t align) noexcept {
+  try {
+    return resource->allocate(bytes, align);
+  } catch (...) {
+    return nullptr;
+  }
+}
+
+extern "C" void
+composable_allocators_pmr_deallocate(std::pmr::memory_resource *resource,
+                                     void *ptr, std::size_t bytes,
+                                     std::size_t align) noexcept {
+  resource->deallocate(ptr, bytes, align);
+}
+#endif
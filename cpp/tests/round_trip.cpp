@@ -0,0 +1,28 @@
+// Built into a shared library by the `round_trip` test in `src/pmr_ffi.rs`,
+// which checks that `pmr.hpp` compiles and works in both directions.
+#define COMPOSABLE_ALLOCATORS_PMR_IMPLEMENTATION
+#include "composable_allocators/pmr.hpp"
+
+#include <vector>
+
+extern "C" std::pmr::memory_resource *
+round_trip_new(composable_allocators::raw_resource raw) {
+  return new composable_allocators::rust_memory_resource(raw);
+}
+
+extern "C" void round_trip_delete(std::pmr::memory_resource *resource) {
+  delete resource;
+}
+
+// The sum of `0..n`, pushed through a vector allocated from `resource`.
+extern "C" int round_trip_sum(std::pmr::memory_resource *resource, int n) {
+  std::pmr::vector<int> v(resource);
+  for (int i = 0; i < n; i++) {
+    v.push_back(i);
+  }
+  int sum = 0;
+  for (int it : v) {
+    sum += it;
+  }
+  return sum;
+}
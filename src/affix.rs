@@ -1,32 +1,44 @@
-use crate::prelude::*;
-use core::{marker::PhantomData, ptr};
+use crate::{asan, prelude::*};
+use core::{cmp, marker::PhantomData, mem, mem::MaybeUninit, ptr};
 
 /// ```text
-/// ┌─────────────────────────────────────────┐
-/// │ outer                                   │
-/// ├──────────────┬────────────────┬─────────┤
-/// │ PrefixT      │ body           │ SuffixT │
-/// ├──────────────┼────────────────┴─────────┘
-/// ├─body_offset─►│                :         :
-/// │              :                :         :
-/// └────────────────suffix_offset─►│         :
-/// :                                         :
-/// ├─outer.size()───────────────────────────►│
+/// ┌───────────────────────────────────────────────┐
+/// │ outer                                         │
+/// ├─────┬─────────┬────────────────┬──────────────┤
+/// │ Tag │ PrefixT │ body           │ SuffixT      │
+/// ├─────┼─────────┼────────────────┼──────────────┘
+/// ├────►│ prefix_offset            :              :
+/// ├─body_offset──►│                :              :
+/// ├─suffix_offset─────────────────►│              :
+/// ├─outer.size()─────────────────────────────────►│
 /// ```
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub(crate) struct AffixLayout {
+    pub prefix_offset: usize,
     pub body_offset: usize,
     pub suffix_offset: usize,
     pub outer: Layout,
 }
 
+/// Stamped at the start of each allocation, so that [`Affix`] can recognise its own.
+type Tag = usize;
+
+/// The [`Tag`] for the allocation with the given `body`.
+#[inline(always)]
+fn tag(body: NonNull<u8>) -> Tag {
+    const MAGIC: u64 = 0xAFF1_C5AF_F1C5_AFF1;
+    crate::rng::mix(MAGIC ^ body.as_ptr() as usize as u64) as Tag
+}
+
 impl AffixLayout {
     #[inline(always)]
     pub fn new<PrefixT, SuffixT>(body: Layout) -> Option<Self> {
-        let (outer, body_offset) = Layout::new::<PrefixT>().extend(body).ok()?;
+        let (outer, prefix_offset) = Layout::new::<Tag>().extend(Layout::new::<PrefixT>()).ok()?;
+        let (outer, body_offset) = outer.extend(body).ok()?;
         let (outer, suffix_offset) = outer.extend(Layout::new::<SuffixT>()).ok()?;
         let outer = outer.pad_to_align();
         Some(AffixLayout {
+            prefix_offset,
             body_offset,
             suffix_offset,
             outer,
@@ -37,16 +49,22 @@ impl AffixLayout {
     #[inline(always)]
     pub unsafe fn narrow(&self, outer: NonNull<[u8]>) -> NonNull<[u8]> {
         let ptr = outer.as_ptr().cast::<u8>().byte_add(self.body_offset);
-        NonNull::slice_from_raw_parts(NonNull::new_unchecked(ptr), self.suffix_offset)
+        NonNull::slice_from_raw_parts(
+            NonNull::new_unchecked(ptr),
+            self.suffix_offset - self.body_offset,
+        )
     }
+    /// Get `(start, prefix, suffix)` given a `body`.
+    ///
     /// # Safety
     /// - `body` must be from a call to [`Affix::affix_allocate`].
     #[inline(always)]
-    pub unsafe fn broaden(&self, body: NonNull<u8>) -> (NonNull<u8>, NonNull<u8>) {
-        let prefix = NonNull::new_unchecked(body.as_ptr().byte_sub(self.body_offset));
+    pub unsafe fn broaden(&self, body: NonNull<u8>) -> (NonNull<u8>, NonNull<u8>, NonNull<u8>) {
+        let start = NonNull::new_unchecked(body.as_ptr().byte_sub(self.body_offset));
         (
-            prefix,
-            NonNull::new_unchecked(prefix.as_ptr().byte_add(self.suffix_offset)),
+            start,
+            NonNull::new_unchecked(start.as_ptr().byte_add(self.prefix_offset)),
+            NonNull::new_unchecked(start.as_ptr().byte_add(self.suffix_offset)),
         )
     }
 }
@@ -72,23 +90,185 @@ where
     pub fn affix_allocate(
         &self,
         body: Layout,
+    ) -> Result<(NonNull<u8>, NonNull<[u8]>, NonNull<u8>), AllocError> {
+        self.affix_allocate_with(body, false)
+    }
+    /// Like [`Self::affix_allocate`], but the body is zeroed.
+    #[inline(always)]
+    #[allow(clippy::type_complexity)]
+    pub fn affix_allocate_zeroed(
+        &self,
+        body: Layout,
+    ) -> Result<(NonNull<u8>, NonNull<[u8]>, NonNull<u8>), AllocError> {
+        self.affix_allocate_with(body, true)
+    }
+    #[inline(always)]
+    #[allow(clippy::type_complexity)]
+    fn affix_allocate_with(
+        &self,
+        body: Layout,
+        zeroed: bool,
     ) -> Result<(NonNull<u8>, NonNull<[u8]>, NonNull<u8>), AllocError> {
         let affix_layout = AffixLayout::new::<PrefixT, SuffixT>(body).ok_or(AllocError)?;
-        let outer = self.inner.allocate(affix_layout.outer)?;
+        let outer = match zeroed {
+            true => self.inner.allocate_zeroed(affix_layout.outer)?,
+            false => self.inner.allocate(affix_layout.outer)?,
+        };
         debug_assert!(outer.len() >= affix_layout.outer.size());
         let body = unsafe { affix_layout.narrow(outer) };
-        let (prefix, suffix) = unsafe { affix_layout.broaden(body.cast::<u8>()) };
+        let (start, prefix, suffix) = unsafe { affix_layout.broaden(body.cast::<u8>()) };
+        unsafe { start.cast::<Tag>().write(tag(body.cast())) };
         Ok((prefix, body, suffix))
     }
+    /// Resize an affixed allocation, preserving its prefix, body and suffix,
+    /// and returning `(prefix, body, suffix)` like [`Self::affix_allocate`].
+    ///
+    /// Bytes added to the body are zeroed if `zeroed` is true.
+    ///
+    /// # Safety
+    /// - `body` must be from a call to [`Self::affix_allocate`] with `old_layout`.
+    /// - as for [`Allocator::grow`] if `new_layout` is larger, and [`Allocator::shrink`] otherwise.
+    #[inline(always)]
+    #[allow(clippy::type_complexity)]
+    pub unsafe fn affix_resize(
+        &self,
+        body: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        zeroed: bool,
+    ) -> Result<(NonNull<u8>, NonNull<[u8]>, NonNull<u8>), AllocError> {
+        let old = AffixLayout::new::<PrefixT, SuffixT>(old_layout).unwrap_unchecked();
+        let new = AffixLayout::new::<PrefixT, SuffixT>(new_layout).ok_or(AllocError)?;
+        let (old_start, _, old_suffix) = old.broaden(body);
+        let suffix = ptr::read(old_suffix.cast::<MaybeUninit<SuffixT>>().as_ptr());
+        let start = match old.body_offset == new.body_offset {
+            // the prefix and body stay put, so let `A` resize in place if it can
+            true => match new.outer.size() >= old.outer.size() {
+                true => self.inner.grow(old_start, old.outer, new.outer)?,
+                false => self.inner.shrink(old_start, old.outer, new.outer)?,
+            }
+            .cast::<u8>(),
+            false => {
+                let start = self.inner.allocate(new.outer)?.cast::<u8>();
+                ptr::copy_nonoverlapping(
+                    old_start.as_ptr(),
+                    start.as_ptr(),
+                    old.prefix_offset + mem::size_of::<PrefixT>(),
+                );
+                ptr::copy_nonoverlapping(
+                    body.as_ptr(),
+                    start.as_ptr().add(new.body_offset),
+                    cmp::min(old_layout.size(), new_layout.size()),
+                );
+                old_start.cast::<Tag>().write(0);
+                self.inner.deallocate(old_start, old.outer);
+                start
+            }
+        };
+        let body = NonNull::new_unchecked(start.as_ptr().add(new.body_offset));
+        if zeroed && new_layout.size() > old_layout.size() {
+            ptr::write_bytes(
+                body.as_ptr().add(old_layout.size()),
+                0,
+                new_layout.size() - old_layout.size(),
+            );
+        }
+        let (_, prefix, suffix_ptr) = new.broaden(body);
+        ptr::write(suffix_ptr.cast::<MaybeUninit<SuffixT>>().as_ptr(), suffix);
+        start.cast::<Tag>().write(tag(body));
+        Ok((
+            prefix,
+            NonNull::slice_from_raw_parts(body, new.suffix_offset - new.body_offset),
+            suffix_ptr,
+        ))
+    }
     /// Get `(prefix, suffix)` given a pointer to a `body` of an allocation.
     ///
     /// # Safety
     /// - `body` must be from a call to [`Self::affix_allocate`].
     #[inline(always)]
     pub unsafe fn affix_get(body: NonNull<u8>, layout: Layout) -> (NonNull<u8>, NonNull<u8>) {
-        AffixLayout::new::<PrefixT, SuffixT>(layout)
+        let (_, prefix, suffix) = AffixLayout::new::<PrefixT, SuffixT>(layout)
             .unwrap_unchecked()
-            .broaden(body)
+            .broaden(body);
+        (prefix, suffix)
+    }
+}
+
+impl<A, PrefixT, SuffixT> Affix<A, PrefixT, SuffixT> {
+    pub const fn new(inner: A) -> Self {
+        Self {
+            inner,
+            prefix: PhantomData,
+            suffix: PhantomData,
+        }
+    }
+    /// Get the `PrefixT` of an allocation, given a pointer to its `body`.
+    ///
+    /// The prefix is uninitialized until written, e.g with [`Self::write_prefix`].
+    ///
+    /// # Safety
+    /// - `body` must be from a call to [`Self::affix_allocate`] with `layout`,
+    ///   or an [`Allocator`] method on this [`Affix`].
+    #[inline(always)]
+    pub unsafe fn prefix_of(body: NonNull<u8>, layout: Layout) -> NonNull<PrefixT> {
+        let affix_layout = AffixLayout::new::<PrefixT, SuffixT>(layout).unwrap_unchecked();
+        affix_layout.broaden(body).1.cast()
+    }
+    /// Get the `SuffixT` of an allocation, given a pointer to its `body`.
+    ///
+    /// The suffix is uninitialized until written, e.g with [`Self::write_suffix`].
+    ///
+    /// # Safety
+    /// - As for [`Self::prefix_of`].
+    #[inline(always)]
+    pub unsafe fn suffix_of(body: NonNull<u8>, layout: Layout) -> NonNull<SuffixT> {
+        let affix_layout = AffixLayout::new::<PrefixT, SuffixT>(layout).unwrap_unchecked();
+        affix_layout.broaden(body).2.cast()
+    }
+    /// Read the `PrefixT` of an allocation.
+    ///
+    /// # Safety
+    /// - As for [`Self::prefix_of`].
+    /// - The prefix must have been initialized, e.g with [`Self::write_prefix`].
+    #[inline(always)]
+    pub unsafe fn read_prefix(body: NonNull<u8>, layout: Layout) -> PrefixT
+    where
+        PrefixT: Copy,
+    {
+        Self::prefix_of(body, layout).read()
+    }
+    /// Read the `SuffixT` of an allocation.
+    ///
+    /// # Safety
+    /// - As for [`Self::prefix_of`].
+    /// - The suffix must have been initialized, e.g with [`Self::write_suffix`].
+    #[inline(always)]
+    pub unsafe fn read_suffix(body: NonNull<u8>, layout: Layout) -> SuffixT
+    where
+        SuffixT: Copy,
+    {
+        Self::suffix_of(body, layout).read()
+    }
+    /// Initialize or overwrite the `PrefixT` of an allocation.
+    ///
+    /// Any previous value is not dropped.
+    ///
+    /// # Safety
+    /// - As for [`Self::prefix_of`].
+    #[inline(always)]
+    pub unsafe fn write_prefix(body: NonNull<u8>, layout: Layout, prefix: PrefixT) {
+        Self::prefix_of(body, layout).write(prefix)
+    }
+    /// Initialize or overwrite the `SuffixT` of an allocation.
+    ///
+    /// Any previous value is not dropped.
+    ///
+    /// # Safety
+    /// - As for [`Self::prefix_of`].
+    #[inline(always)]
+    pub unsafe fn write_suffix(body: NonNull<u8>, layout: Layout, suffix: SuffixT) {
+        Self::suffix_of(body, layout).write(suffix)
     }
 }
 
@@ -104,30 +284,99 @@ where
     #[inline(always)]
     unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
         let affix_layout = AffixLayout::new::<PrefixT, SuffixT>(layout).unwrap_unchecked();
-        let (start, _) = affix_layout.broaden(ptr);
+        let (start, _, _) = affix_layout.broaden(ptr);
+        // so stale pointers aren't recognised by `owns`
+        start.cast::<Tag>().write(0);
         self.inner.deallocate(start, affix_layout.outer)
     }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let (_, body, _) = self.affix_allocate_zeroed(layout)?;
+        Ok(body)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let (_, body, _) = self.affix_resize(ptr, old_layout, new_layout, false)?;
+        Ok(body)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let (_, body, _) = self.affix_resize(ptr, old_layout, new_layout, true)?;
+        Ok(body)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let (_, body, _) = self.affix_resize(ptr, old_layout, new_layout, false)?;
+        Ok(body)
+    }
+}
+
+impl<A, PrefixT, SuffixT> DeallocateAll for Affix<A, PrefixT, SuffixT>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all()
+    }
 }
 
+impl<A, PrefixT, SuffixT> Trim for Affix<A, PrefixT, SuffixT>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+/// `ptr` is only dereferenced if `A` [owns](Owns::owns) the whole affixed allocation,
+/// and is then recognised by a tag derived from its address.
+///
+/// If `A` is shared with other users, this is probabilistic:
+/// a foreign allocation could contain a matching tag by chance.
 unsafe impl<A, PrefixT, SuffixT> Owns for Affix<A, PrefixT, SuffixT>
 where
     A: Owns,
 {
     #[inline(always)]
     fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
-        match AffixLayout::new::<PrefixT, SuffixT>(layout) {
-            Some(affix_layout) => {
-                // BUG(aatifsyed): this is bad
-                let (ptr, _) = unsafe { affix_layout.broaden(ptr) };
-                self.inner.owns(ptr, affix_layout.outer)
-            }
-            None => false,
-        }
+        let Some(affix_layout) = AffixLayout::new::<PrefixT, SuffixT>(layout) else {
+            return false;
+        };
+        // `ptr` may not be ours, so don't assume it's in bounds
+        let Some(start) = NonNull::new(ptr.as_ptr().wrapping_byte_sub(affix_layout.body_offset))
+        else {
+            return false;
+        };
+        self.inner.owns(start, affix_layout.outer)
+            && unsafe { start.cast::<Tag>().as_ptr().read_unaligned() } == tag(ptr)
     }
 }
 
 /// An [`Allocator`] which checks [`Self::prefix`] and [`Self::suffix`] are
 /// maintained around each allocation, [`panic`]-ing if they aren't.
+///
+/// With the `asan` feature, the prefix and suffix are also poisoned,
+/// so AddressSanitizer reports out-of-bounds accesses as they happen.
+/// Allocations aren't tracked, so they can't be unpoisoned all at once,
+/// and [`DeallocateAll`] isn't implemented.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Guard<A, PrefixT, SuffixT> {
     pub inner: Affix<A, PrefixT, SuffixT>,
@@ -135,6 +384,70 @@ pub struct Guard<A, PrefixT, SuffixT> {
     pub suffix: SuffixT,
 }
 
+impl<A, PrefixT, SuffixT> Guard<A, PrefixT, SuffixT> {
+    pub const fn new(inner: A, prefix: PrefixT, suffix: SuffixT) -> Self {
+        Self {
+            inner: Affix::new(inner),
+            prefix,
+            suffix,
+        }
+    }
+}
+
+impl<A, PrefixT, SuffixT> Guard<A, PrefixT, SuffixT>
+where
+    PrefixT: Copy + PartialEq,
+    SuffixT: Copy + PartialEq,
+{
+    #[inline(always)]
+    unsafe fn write(&self, prefix: NonNull<u8>, suffix: NonNull<u8>) {
+        ptr::write(prefix.as_ptr().cast::<PrefixT>(), self.prefix);
+        ptr::write(suffix.as_ptr().cast::<SuffixT>(), self.suffix);
+        asan::poison(prefix, mem::size_of::<PrefixT>());
+        asan::poison(suffix, mem::size_of::<SuffixT>());
+    }
+    #[inline(always)]
+    unsafe fn check(&self, body: NonNull<u8>, layout: Layout) {
+        let prefix = Affix::<A, PrefixT, SuffixT>::prefix_of(body, layout).cast();
+        let suffix = Affix::<A, PrefixT, SuffixT>::suffix_of(body, layout).cast();
+        asan::unpoison(prefix, mem::size_of::<PrefixT>());
+        asan::unpoison(suffix, mem::size_of::<SuffixT>());
+        if Affix::<A, PrefixT, SuffixT>::read_prefix(body, layout) != self.prefix {
+            panic!("prefix guard doesn't match")
+        }
+        if Affix::<A, PrefixT, SuffixT>::read_suffix(body, layout) != self.suffix {
+            panic!("suffix guard doesn't match")
+        }
+    }
+    #[inline(always)]
+    unsafe fn resize(
+        &self,
+        body: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        zeroed: bool,
+    ) -> Result<NonNull<[u8]>, AllocError>
+    where
+        A: Allocator,
+    {
+        self.check(body, old_layout);
+        let (prefix, body, suffix) = match self
+            .inner
+            .affix_resize(body, old_layout, new_layout, zeroed)
+        {
+            Ok(it) => it,
+            Err(AllocError) => {
+                // the allocation is untouched, so restore its poisoning
+                let (prefix, suffix) = Affix::<A, PrefixT, SuffixT>::affix_get(body, old_layout);
+                self.write(prefix, suffix);
+                return Err(AllocError);
+            }
+        };
+        self.write(prefix, suffix);
+        Ok(body)
+    }
+}
+
 unsafe impl<A, PrefixT, SuffixT> Allocator for Guard<A, PrefixT, SuffixT>
 where
     A: Allocator,
@@ -144,29 +457,469 @@ where
     #[inline(always)]
     fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
         let (prefix, body, suffix) = self.inner.affix_allocate(layout)?;
-        unsafe { ptr::write(prefix.as_ptr().cast::<PrefixT>(), self.prefix) };
-        unsafe { ptr::write(suffix.as_ptr().cast::<SuffixT>(), self.suffix) };
+        unsafe { self.write(prefix, suffix) };
         Ok(body)
     }
-
     #[inline(always)]
     unsafe fn deallocate(&self, body: NonNull<u8>, layout: Layout) {
-        let affix_layout = AffixLayout::new::<PrefixT, SuffixT>(layout).unwrap_unchecked();
-        let (prefix, suffix) = affix_layout.broaden(body);
-        let prefix = ptr::read(prefix.cast::<PrefixT>().as_ptr());
-        let suffix = ptr::read(suffix.cast::<SuffixT>().as_ptr());
-        if prefix != self.prefix {
+        self.check(body, layout);
+        self.inner.deallocate(body, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let (prefix, body, suffix) = self.inner.affix_allocate_zeroed(layout)?;
+        unsafe { self.write(prefix, suffix) };
+        Ok(body)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, false)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, true)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, false)
+    }
+}
+
+// the canaries would stay poisoned
+#[cfg(not(feature = "asan"))]
+impl<A, PrefixT, SuffixT> DeallocateAll for Guard<A, PrefixT, SuffixT>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all()
+    }
+}
+
+impl<A, PrefixT, SuffixT> Trim for Guard<A, PrefixT, SuffixT>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+unsafe impl<A, PrefixT, SuffixT> Owns for Guard<A, PrefixT, SuffixT>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+/// Like [`Guard`], but the prefix and suffix are derived from [`Self::seed`]
+/// and the address of each allocation, so they can't be predicted without the seed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RandomGuard<A> {
+    pub inner: Affix<A, u64, u64>,
+    pub seed: u64,
+}
+
+impl<A> RandomGuard<A> {
+    pub const fn new(inner: A, seed: u64) -> Self {
+        Self {
+            inner: Affix::new(inner),
+            seed,
+        }
+    }
+    #[inline(always)]
+    fn canaries(&self, body: NonNull<u8>) -> (u64, u64) {
+        let prefix = crate::rng::mix(self.seed ^ body.as_ptr() as usize as u64);
+        (prefix, crate::rng::mix(prefix))
+    }
+    #[inline(always)]
+    unsafe fn write(&self, prefix: NonNull<u8>, body: NonNull<u8>, suffix: NonNull<u8>) {
+        let (prefix_canary, suffix_canary) = self.canaries(body);
+        ptr::write(prefix.as_ptr().cast::<u64>(), prefix_canary);
+        ptr::write(suffix.as_ptr().cast::<u64>(), suffix_canary);
+        asan::poison(prefix, mem::size_of::<u64>());
+        asan::poison(suffix, mem::size_of::<u64>());
+    }
+    #[inline(always)]
+    unsafe fn check(&self, body: NonNull<u8>, layout: Layout) {
+        let affix_layout = AffixLayout::new::<u64, u64>(layout).unwrap_unchecked();
+        let (_, prefix, suffix) = affix_layout.broaden(body);
+        asan::unpoison(prefix, mem::size_of::<u64>());
+        asan::unpoison(suffix, mem::size_of::<u64>());
+        let (prefix_canary, suffix_canary) = self.canaries(body);
+        if ptr::read(prefix.cast::<u64>().as_ptr()) != prefix_canary {
             panic!("prefix guard doesn't match")
         }
-        if suffix != self.suffix {
+        if ptr::read(suffix.cast::<u64>().as_ptr()) != suffix_canary {
             panic!("suffix guard doesn't match")
         }
+    }
+    #[inline(always)]
+    unsafe fn resize(
+        &self,
+        body: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        zeroed: bool,
+    ) -> Result<NonNull<[u8]>, AllocError>
+    where
+        A: Allocator,
+    {
+        self.check(body, old_layout);
+        let (prefix, new_body, suffix) = match self
+            .inner
+            .affix_resize(body, old_layout, new_layout, zeroed)
+        {
+            Ok(it) => it,
+            Err(AllocError) => {
+                // the allocation is untouched, so restore its poisoning
+                let (prefix, suffix) = Affix::<A, u64, u64>::affix_get(body, old_layout);
+                self.write(prefix, body, suffix);
+                return Err(AllocError);
+            }
+        };
+        self.write(prefix, new_body.cast(), suffix);
+        Ok(new_body)
+    }
+}
+
+// the canaries would stay poisoned
+#[cfg(not(feature = "asan"))]
+impl<A> DeallocateAll for RandomGuard<A>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all()
+    }
+}
+
+impl<A> Trim for RandomGuard<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+unsafe impl<A> Owns for RandomGuard<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+unsafe impl<A> Allocator for RandomGuard<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let (prefix, body, suffix) = self.inner.affix_allocate(layout)?;
+        unsafe { self.write(prefix, body.cast(), suffix) };
+        Ok(body)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, body: NonNull<u8>, layout: Layout) {
+        self.check(body, layout);
+        self.inner.deallocate(body, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let (prefix, body, suffix) = self.inner.affix_allocate_zeroed(layout)?;
+        unsafe { self.write(prefix, body.cast(), suffix) };
+        Ok(body)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, false)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, true)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, false)
+    }
+}
+
+/// Like [`Affix`], but each prefix and suffix is constructed by
+/// [`Self::make_prefix`] and [`Self::make_suffix`] on allocation,
+/// and dropped on deallocation.
+///
+/// This allows storing metadata which isn't [`Copy`] alongside each allocation.
+/// Resizing moves the metadata with the allocation.
+///
+/// [`DeallocateAll`] isn't implemented, since the metadata of outstanding allocations
+/// couldn't be dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AffixInit<A, PrefixT, SuffixT, MakePrefixT, MakeSuffixT> {
+    pub inner: Affix<A, PrefixT, SuffixT>,
+    pub make_prefix: MakePrefixT,
+    pub make_suffix: MakeSuffixT,
+}
+
+impl<A, PrefixT, SuffixT, MakePrefixT, MakeSuffixT>
+    AffixInit<A, PrefixT, SuffixT, MakePrefixT, MakeSuffixT>
+{
+    pub const fn new(inner: A, make_prefix: MakePrefixT, make_suffix: MakeSuffixT) -> Self {
+        Self {
+            inner: Affix::new(inner),
+            make_prefix,
+            make_suffix,
+        }
+    }
+}
+
+impl<A, PrefixT, SuffixT, MakePrefixT, MakeSuffixT>
+    AffixInit<A, PrefixT, SuffixT, MakePrefixT, MakeSuffixT>
+{
+    /// Get `(prefix, suffix)` given a pointer to the `body` of an allocation.
+    ///
+    /// # Safety
+    /// - `body` must be from an [`Allocator`] method on this [`AffixInit`] with `layout`.
+    /// - The allocation must not be deallocated or resized while the references are live.
+    #[inline(always)]
+    pub unsafe fn get(&self, body: NonNull<u8>, layout: Layout) -> (&PrefixT, &SuffixT) {
+        (
+            Affix::<A, PrefixT, SuffixT>::prefix_of(body, layout).as_ref(),
+            Affix::<A, PrefixT, SuffixT>::suffix_of(body, layout).as_ref(),
+        )
+    }
+}
+
+impl<A, PrefixT, SuffixT, MakePrefixT, MakeSuffixT>
+    AffixInit<A, PrefixT, SuffixT, MakePrefixT, MakeSuffixT>
+where
+    MakePrefixT: Fn(Layout) -> PrefixT,
+    MakeSuffixT: Fn(Layout) -> SuffixT,
+{
+    #[inline(always)]
+    unsafe fn init(&self, prefix: NonNull<u8>, layout: Layout, suffix: NonNull<u8>) {
+        ptr::write(
+            prefix.as_ptr().cast::<PrefixT>(),
+            (self.make_prefix)(layout),
+        );
+        ptr::write(
+            suffix.as_ptr().cast::<SuffixT>(),
+            (self.make_suffix)(layout),
+        );
+    }
+}
+
+unsafe impl<A, PrefixT, SuffixT, MakePrefixT, MakeSuffixT> Allocator
+    for AffixInit<A, PrefixT, SuffixT, MakePrefixT, MakeSuffixT>
+where
+    A: Allocator,
+    MakePrefixT: Fn(Layout) -> PrefixT,
+    MakeSuffixT: Fn(Layout) -> SuffixT,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let (prefix, body, suffix) = self.inner.affix_allocate(layout)?;
+        unsafe { self.init(prefix, layout, suffix) };
+        Ok(body)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, body: NonNull<u8>, layout: Layout) {
+        ptr::drop_in_place(Affix::<A, PrefixT, SuffixT>::prefix_of(body, layout).as_ptr());
+        ptr::drop_in_place(Affix::<A, PrefixT, SuffixT>::suffix_of(body, layout).as_ptr());
         self.inner.deallocate(body, layout)
     }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let (prefix, body, suffix) = self.inner.affix_allocate_zeroed(layout)?;
+        unsafe { self.init(prefix, layout, suffix) };
+        Ok(body)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let (_, body, _) = self
+            .inner
+            .affix_resize(ptr, old_layout, new_layout, false)?;
+        Ok(body)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let (_, body, _) = self.inner.affix_resize(ptr, old_layout, new_layout, true)?;
+        Ok(body)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let (_, body, _) = self
+            .inner
+            .affix_resize(ptr, old_layout, new_layout, false)?;
+        Ok(body)
+    }
+}
+
+unsafe impl<A, PrefixT, SuffixT, MakePrefixT, MakeSuffixT> Owns
+    for AffixInit<A, PrefixT, SuffixT, MakePrefixT, MakeSuffixT>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+#[test]
+fn owns() {
+    let inline = Inline::<64>::new();
+    let a = (&inline).guard(1u8, 2u8);
+    let layout = Layout::new::<u32>();
+    let it = a.allocate(layout).unwrap().cast();
+    assert!(a.owns(it, layout));
+    let affix_layout = AffixLayout::new::<u8, u8>(layout).unwrap();
+    let foreign = (&inline).allocate_zeroed(affix_layout.outer).unwrap();
+    let foreign = unsafe { affix_layout.narrow(foreign) }.cast();
+    assert!(!a.owns(foreign, layout));
+    unsafe { a.deallocate(it, layout) };
+    assert!(!a.owns(it, layout));
 }
 
 #[cfg(feature = "malloc")]
 #[test]
 fn guard() {
     let _ = Box::new_in(1, Malloc.zero().guard([0xFF_u8; 3], [0xEE_u8; 3]));
+    let a = Malloc.tracked().guard(0xFF_u8, 0xEE_u8).or(Malloc);
+    let layout = Layout::new::<u64>();
+    let theirs = a.fallback.allocate(layout).unwrap().cast();
+    let _ours = Box::new_in(1u64, &a);
+    // would panic if freed through the guard
+    unsafe { a.deallocate(theirs, layout) };
+}
+
+// AddressSanitizer aborts on the corrupting write instead
+#[cfg(all(feature = "malloc", not(feature = "asan")))]
+#[test]
+#[should_panic = "suffix guard doesn't match"]
+fn random_guard() {
+    let a = Malloc.random_guard(0xDEADBEEF);
+    let _ = Box::new_in([1u8; 3], &a);
+    let layout = Layout::new::<[u8; 3]>();
+    let body = a.allocate(layout).unwrap().cast();
+    unsafe { Affix::<Malloc, u64, u64>::write_suffix(body, layout, 0) };
+    unsafe { a.deallocate(body, layout) };
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn accessors() {
+    type Tagged = Affix<Malloc, usize, [u8; 3]>;
+    let a: Tagged = Affix {
+        inner: Malloc,
+        prefix: PhantomData,
+        suffix: PhantomData,
+    };
+    let layout = Layout::new::<[u16; 5]>();
+    let body = a.allocate(layout).unwrap().cast();
+    unsafe {
+        Tagged::write_prefix(body, layout, 10);
+        Tagged::write_suffix(body, layout, *b"end");
+        assert_eq!(Tagged::read_prefix(body, layout), 10);
+        assert_eq!(Tagged::read_suffix(body, layout), *b"end");
+        assert_eq!(
+            Tagged::suffix_of(body, layout).cast::<u8>(),
+            body.add(layout.size())
+        );
+        a.deallocate(body, layout)
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn resize() {
+    let a = Malloc.random_guard(0xDEADBEEF).guard(0xFF_u8, 0xEE_u16);
+    let mut v = allocator_api2::vec::Vec::<u8, _>::new_in(&a);
+    v.extend(0..=255);
+    v.truncate(3);
+    v.shrink_to_fit();
+    assert_eq!(v, [0, 1, 2]);
+    let body = a.allocate_zeroed(Layout::new::<u8>()).unwrap().cast();
+    let body = unsafe {
+        a.grow_zeroed(body, Layout::new::<u8>(), Layout::new::<[u64; 4]>())
+            .unwrap()
+    };
+    assert_eq!(unsafe { body.cast::<[u64; 4]>().read() }, [0; 4]);
+    unsafe { a.deallocate(body.cast(), Layout::new::<[u64; 4]>()) };
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn affix_init() {
+    use core::cell::Cell;
+    struct Counted<'a>(&'a Cell<usize>);
+    impl Drop for Counted<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1)
+        }
+    }
+    let dropped = Cell::new(0);
+    let a = Malloc.affix_init(|layout: Layout| layout.size(), |_| Counted(&dropped));
+    let mut v = allocator_api2::vec::Vec::<u8, _>::with_capacity_in(4, &a);
+    let body = NonNull::new(v.as_mut_ptr()).unwrap();
+    assert_eq!(*unsafe { a.get(body, Layout::new::<[u8; 4]>()) }.0, 4);
+    v.extend(0..=255);
+    assert_eq!(dropped.get(), 0);
+    drop(v);
+    assert_eq!(dropped.get(), 1);
 }
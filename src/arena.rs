@@ -0,0 +1,413 @@
+use crate::prelude::*;
+use core::{cell::Cell, cmp, ptr};
+
+/// Header written at the start of each chunk.
+struct Chunk {
+    prev: Option<NonNull<Chunk>>,
+    layout: Layout,
+}
+
+/// A position in an [`Arena`], see [`Rewind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Checkpoint {
+    chunk: Option<NonNull<Chunk>>,
+    cursor: *mut u8,
+}
+
+impl Checkpoint {
+    /// Before any chunk was requested, so rewinding to it returns every chunk.
+    pub(crate) const START: Self = Self {
+        chunk: None,
+        cursor: ptr::null_mut(),
+    };
+}
+
+/// An [`Allocator`] which bump-allocates out of chunks of at least
+/// [`chunk_size`](Self::chunk_size) bytes requested from `A`.
+///
+/// With [`Self::growing`], each chunk is twice the size of the last.
+///
+/// Memory is only returned to `A` when the [`Arena`] is dropped, [rewound](Rewind)
+/// or [reset](DeallocateAll), except that deallocating (or resizing) the most recent allocation reuses its space.
+///
+/// Values may be allocated directly, borrowing the arena,
+/// alongside collections which use it as an [`Allocator`]:
+/// ```
+/// # use composable_allocators::*;
+/// let arena = Malloc.arena(4096);
+/// let name = arena.alloc_str("arena");
+/// let point = arena.alloc_value((1, 2));
+/// let mut v = allocator_api2::vec::Vec::new_in(&arena);
+/// v.extend_from_slice(arena.alloc_slice_copy(&[3, 4]));
+/// assert_eq!((name, *point, v[0]), ("arena", (1, 2), 3));
+/// ```
+#[derive(Debug)]
+pub struct Arena<A: Allocator> {
+    inner: A,
+    chunk_size: usize,
+    next_chunk_size: Cell<usize>,
+    growing: bool,
+    chunk: Cell<Option<NonNull<Chunk>>>,
+    cursor: Cell<*mut u8>,
+    end: Cell<*mut u8>,
+}
+
+unsafe impl<A: Allocator + Send> Send for Arena<A> {}
+
+impl<A: Allocator> Arena<A> {
+    pub const fn new(inner: A, chunk_size: usize) -> Self {
+        Self {
+            inner,
+            chunk_size,
+            next_chunk_size: Cell::new(chunk_size),
+            growing: false,
+            chunk: Cell::new(None),
+            cursor: Cell::new(ptr::null_mut()),
+            end: Cell::new(ptr::null_mut()),
+        }
+    }
+    /// Like [`Self::new`], but each chunk is twice the size of the last,
+    /// starting from `chunk_size`.
+    ///
+    /// Rewinding past every chunk starts again from `chunk_size`.
+    pub const fn growing(inner: A, chunk_size: usize) -> Self {
+        Self {
+            inner,
+            chunk_size,
+            next_chunk_size: Cell::new(chunk_size),
+            growing: true,
+            chunk: Cell::new(None),
+            cursor: Cell::new(ptr::null_mut()),
+            end: Cell::new(ptr::null_mut()),
+        }
+    }
+    /// The minimum number of bytes in the next chunk requested from [`Self::inner`].
+    pub fn chunk_size(&self) -> usize {
+        self.next_chunk_size.get()
+    }
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+    /// Try and bump-allocate out of the current chunk.
+    #[inline(always)]
+    fn bump(&self, layout: Layout) -> Option<NonNull<[u8]>> {
+        let cursor = self.cursor.get();
+        if cursor.is_null() {
+            // no chunk yet, so there's nowhere to put even a zero-sized allocation
+            return None;
+        }
+        let end = self.end.get();
+        let padding = cursor.align_offset(layout.align());
+        let available = (end as usize).checked_sub(cursor as usize)?;
+        if padding.checked_add(layout.size())? > available {
+            return None;
+        }
+        let start = unsafe { cursor.add(padding) };
+        self.cursor.set(unsafe { start.add(layout.size()) });
+        Some(NonNull::slice_from_raw_parts(
+            unsafe { NonNull::new_unchecked(start) },
+            layout.size(),
+        ))
+    }
+    /// Request a new chunk from [`Self::inner`] which is big enough for `layout`.
+    #[inline(always)]
+    fn refill(&self, layout: Layout) -> Result<(), AllocError> {
+        let body = Layout::from_size_align(
+            cmp::max(self.next_chunk_size.get(), layout.size()),
+            cmp::max(layout.align(), core::mem::align_of::<Chunk>()),
+        )
+        .map_err(|_| AllocError)?;
+        let (outer, body_offset) = Layout::new::<Chunk>()
+            .extend(body)
+            .map_err(|_| AllocError)?;
+        let outer = outer.pad_to_align();
+        let allocation = self.inner.allocate(outer)?;
+        let chunk = allocation.cast::<Chunk>();
+        unsafe {
+            chunk.as_ptr().write(Chunk {
+                prev: self.chunk.get(),
+                layout: outer,
+            })
+        };
+        let start = allocation.cast::<u8>().as_ptr();
+        self.chunk.set(Some(chunk));
+        self.cursor.set(unsafe { start.add(body_offset) });
+        self.end.set(unsafe { start.add(outer.size()) });
+        if self.growing {
+            let next = self.next_chunk_size.get();
+            self.next_chunk_size.set(next.saturating_mul(2));
+        }
+        Ok(())
+    }
+    /// Return chunks to [`Self::inner`] until `keep` is the current chunk.
+    ///
+    /// # Safety
+    /// - `keep` must be [`None`], or a live chunk.
+    #[inline(always)]
+    unsafe fn release_until(&self, keep: Option<NonNull<Chunk>>) {
+        while self.chunk.get() != keep {
+            let Some(it) = self.chunk.get() else {
+                return;
+            };
+            let Chunk { prev, layout } = it.as_ptr().read();
+            self.inner.deallocate(it.cast(), layout);
+            self.chunk.set(prev);
+        }
+    }
+    #[inline(always)]
+    fn allocate_or_panic(&self, layout: Layout) -> NonNull<u8> {
+        match self.allocate(layout) {
+            Ok(it) => it.cast(),
+            Err(AllocError) => panic!("allocation of {layout:?} failed"),
+        }
+    }
+    /// Move `value` into the arena.
+    ///
+    /// `value` is never dropped, though its memory is freed with the arena.
+    ///
+    /// # Panics
+    /// - if the allocation fails.
+    pub fn alloc_value<T>(&self, value: T) -> &T {
+        let ptr = self.allocate_or_panic(Layout::new::<T>()).cast::<T>();
+        unsafe {
+            ptr.as_ptr().write(value);
+            &*ptr.as_ptr()
+        }
+    }
+    /// Copy `slice` into the arena.
+    ///
+    /// # Panics
+    /// - if the allocation fails.
+    pub fn alloc_slice_copy<T>(&self, slice: &[T]) -> &[T]
+    where
+        T: Copy,
+    {
+        let ptr = self.allocate_or_panic(Layout::for_value(slice)).cast::<T>();
+        unsafe {
+            ptr::copy_nonoverlapping(slice.as_ptr(), ptr.as_ptr(), slice.len());
+            core::slice::from_raw_parts(ptr.as_ptr(), slice.len())
+        }
+    }
+    /// Copy `s` into the arena.
+    ///
+    /// # Panics
+    /// - if the allocation fails.
+    pub fn alloc_str(&self, s: &str) -> &str {
+        let bytes = self.alloc_slice_copy(s.as_bytes());
+        unsafe { core::str::from_utf8_unchecked(bytes) }
+    }
+    /// Whether `ptr` with `layout` was the most recent allocation.
+    #[inline(always)]
+    fn is_last(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        ptr.as_ptr().wrapping_add(layout.size()) == self.cursor.get()
+    }
+}
+
+unsafe impl<A> Allocator for Arena<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if let Some(it) = self.bump(layout) {
+            return Ok(it);
+        }
+        self.refill(layout)?;
+        self.bump(layout).ok_or(AllocError)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if self.is_last(ptr, layout) {
+            self.cursor.set(ptr.as_ptr())
+        }
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if self.is_last(ptr, old_layout)
+            && ptr.as_ptr().align_offset(new_layout.align()) == 0
+            && (self.end.get() as usize - ptr.as_ptr() as usize) >= new_layout.size()
+        {
+            self.cursor.set(ptr.as_ptr().add(new_layout.size()));
+            return Ok(NonNull::slice_from_raw_parts(ptr, new_layout.size()));
+        }
+        let new = self.allocate(new_layout)?;
+        ptr::copy_nonoverlapping(ptr.as_ptr(), new.cast::<u8>().as_ptr(), old_layout.size());
+        self.deallocate(ptr, old_layout);
+        Ok(new)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if ptr.as_ptr().align_offset(new_layout.align()) != 0 {
+            let new = self.allocate(new_layout)?;
+            ptr::copy_nonoverlapping(ptr.as_ptr(), new.cast::<u8>().as_ptr(), new_layout.size());
+            self.deallocate(ptr, old_layout);
+            return Ok(new);
+        }
+        if self.is_last(ptr, old_layout) {
+            self.cursor.set(ptr.as_ptr().add(new_layout.size()));
+        }
+        Ok(NonNull::slice_from_raw_parts(ptr, new_layout.size()))
+    }
+}
+
+/// Keeps the current chunk, returning the rest to [`Self::inner`].
+impl<A> DeallocateAll for Arena<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        let Some(current) = self.chunk.get() else {
+            return;
+        };
+        let mut chunk = (*current.as_ptr()).prev.take();
+        while let Some(it) = chunk {
+            let Chunk { prev, layout } = it.as_ptr().read();
+            self.inner.deallocate(it.cast(), layout);
+            chunk = prev;
+        }
+        self.cursor.set(current.as_ptr().add(1).cast())
+    }
+}
+
+/// Chunks allocated since the [`Checkpoint`] are returned to [`Arena::inner`].
+impl<A> Rewind for Arena<A>
+where
+    A: Allocator,
+{
+    type Checkpoint = Checkpoint;
+    #[inline(always)]
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            chunk: self.chunk.get(),
+            cursor: self.cursor.get(),
+        }
+    }
+    #[inline(always)]
+    unsafe fn rewind(&self, checkpoint: Checkpoint) {
+        self.release_until(checkpoint.chunk);
+        match checkpoint.chunk {
+            Some(chunk) => {
+                let layout = (*chunk.as_ptr()).layout;
+                self.end.set(chunk.as_ptr().cast::<u8>().add(layout.size()));
+            }
+            None => {
+                self.end.set(ptr::null_mut());
+                self.next_chunk_size.set(self.chunk_size);
+            }
+        }
+        self.cursor.set(checkpoint.cursor)
+    }
+}
+
+unsafe impl<A> Owns for Arena<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        let start = ptr.as_ptr() as usize;
+        let Some(end) = start.checked_add(layout.size()) else {
+            return false;
+        };
+        let mut chunk = self.chunk.get();
+        while let Some(it) = chunk {
+            let Chunk { prev, layout } = unsafe { it.as_ptr().read() };
+            let chunk_start = it.as_ptr() as usize;
+            if chunk_start <= start && end <= chunk_start + layout.size() {
+                return true;
+            }
+            chunk = prev;
+        }
+        false
+    }
+}
+
+impl<A> Drop for Arena<A>
+where
+    A: Allocator,
+{
+    fn drop(&mut self) {
+        let mut chunk = self.chunk.take();
+        while let Some(it) = chunk {
+            let Chunk { prev, layout } = unsafe { it.as_ptr().read() };
+            unsafe { self.inner.deallocate(it.cast(), layout) };
+            chunk = prev;
+        }
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn arena() {
+    let a = Malloc.arena(64);
+    let small = Box::new_in(1u8, &a);
+    let big = Box::new_in([1u8; 128], &a);
+    assert!(a.owns(NonNull::from(&*small).cast(), Layout::new::<u8>()));
+    assert!(a.owns(NonNull::from(&*big).cast(), Layout::new::<[u8; 128]>()));
+    assert!(!a.owns(NonNull::from(&a).cast(), Layout::new::<u8>()));
+    let b = Arena::new(Malloc, 64).or(Malloc);
+    let _ = Box::new_in([1u8; 32], &b);
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn zero_sized_first() {
+    let a = Malloc.stats().arena(64);
+    let unit = a.allocate(Layout::new::<()>()).unwrap();
+    assert!(a.owns(unit.cast(), Layout::new::<()>()));
+    assert_eq!(a.inner().snapshot().allocations, 1);
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn rewind() {
+    let a = Malloc.stats().arena(64);
+    let before = a.checkpoint();
+    let first = a.allocate(Layout::new::<u8>()).unwrap();
+    let checkpoint = a.checkpoint();
+    a.allocate(Layout::new::<[u8; 128]>()).unwrap();
+    let chunks = |it: Snapshot| it.allocations - it.deallocations;
+    assert_eq!(chunks(a.inner().snapshot()), 2);
+    unsafe { a.rewind(checkpoint) };
+    assert_eq!(chunks(a.inner().snapshot()), 1);
+    let second = a.allocate(Layout::new::<u8>()).unwrap();
+    assert_eq!(
+        first.cast::<u8>().as_ptr().wrapping_add(1),
+        second.cast().as_ptr()
+    );
+    unsafe { a.rewind(before) };
+    assert_eq!(chunks(a.inner().snapshot()), 0);
+    let _ = Box::new_in(1u8, &a);
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn typed() {
+    let a = Malloc.stats().arena(64);
+    let unit = a.alloc_value(());
+    let value = a.alloc_value(7u64);
+    assert_eq!(NonNull::from(value).as_ptr() as usize % 8, 0);
+    let big = a.alloc_slice_copy(&[1u32; 32]);
+    let s = a.alloc_str("hello");
+    assert_eq!((*unit, *value, big, s), ((), 7, &[1; 32][..], "hello"));
+    assert_eq!(a.inner().snapshot().allocations, 3);
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+#[should_panic = "allocation of"]
+fn typed_oom() {
+    let a = Malloc.limit_size(64).arena(16);
+    a.alloc_value([0u8; 128]);
+}
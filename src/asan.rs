@@ -0,0 +1,28 @@
+// AddressSanitizer's manual poisoning interface, which is a no-op without the `asan` feature.
+// With the feature, the crate must be built with `-Zsanitizer=address`.
+
+use core::ptr::NonNull;
+
+#[cfg(feature = "asan")]
+extern "C" {
+    fn __asan_poison_memory_region(addr: *const u8, size: usize);
+    fn __asan_unpoison_memory_region(addr: *const u8, size: usize);
+}
+
+/// Mark `len` bytes at `ptr` as inaccessible.
+#[inline(always)]
+pub(crate) unsafe fn poison(ptr: NonNull<u8>, len: usize) {
+    #[cfg(feature = "asan")]
+    __asan_poison_memory_region(ptr.as_ptr(), len);
+    #[cfg(not(feature = "asan"))]
+    let _ = (ptr, len);
+}
+
+/// Mark `len` bytes at `ptr` as accessible.
+#[inline(always)]
+pub(crate) unsafe fn unpoison(ptr: NonNull<u8>, len: usize) {
+    #[cfg(feature = "asan")]
+    __asan_unpoison_memory_region(ptr.as_ptr(), len);
+    #[cfg(not(feature = "asan"))]
+    let _ = (ptr, len);
+}
@@ -0,0 +1,213 @@
+use crate::prelude::*;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// A byte allowance which may be shared between several [`Budgeted`] allocators.
+///
+/// Budgets may be arranged in a tree with [`Self::child`],
+/// where charging a child also charges each of its ancestors:
+/// ```
+/// # use composable_allocators::*;
+/// let global = Budget::new(1024);
+/// let (parser, cache) = (global.child(768), global.child(768));
+/// let (parser, cache) = (Malloc.budgeted(&parser), Malloc.budgeted(&cache));
+/// let _parsed = allocator_api2::boxed::Box::new_in([0u8; 512], &parser);
+/// allocator_api2::boxed::Box::try_new_in([0u8; 768], &cache).unwrap_err();
+/// let _cached = allocator_api2::boxed::Box::new_in([0u8; 512], &cache);
+/// ```
+#[derive(Debug)]
+pub struct Budget<'a> {
+    limit: usize,
+    used: AtomicUsize,
+    parent: Option<&'a Budget<'a>>,
+}
+
+impl<'a> Budget<'a> {
+    /// A root budget of `limit` bytes.
+    pub const fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: AtomicUsize::new(0),
+            parent: None,
+        }
+    }
+    /// A budget of `limit` bytes, which also draws from `self`.
+    pub const fn child(&'a self, limit: usize) -> Self {
+        Self {
+            limit,
+            used: AtomicUsize::new(0),
+            parent: Some(self),
+        }
+    }
+    pub const fn limit(&self) -> usize {
+        self.limit
+    }
+    pub const fn parent(&self) -> Option<&'a Budget<'a>> {
+        self.parent
+    }
+    /// Bytes charged to this budget, including by its children.
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Acquire)
+    }
+    /// Bytes which may still be charged to this budget,
+    /// accounting for its ancestors.
+    pub fn remaining(&self) -> usize {
+        let here = self.limit.saturating_sub(self.used());
+        match self.parent {
+            Some(parent) => here.min(parent.remaining()),
+            None => here,
+        }
+    }
+    /// Charge `size` bytes to this budget and its ancestors,
+    /// or nothing if any of them would exceed its limit.
+    pub fn try_charge(&self, size: usize) -> Result<(), AllocError> {
+        self.used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                used.checked_add(size).filter(|it| *it <= self.limit)
+            })
+            .map_err(|_| AllocError)?;
+        if let Some(parent) = self.parent {
+            if let Err(e) = parent.try_charge(size) {
+                self.used.fetch_sub(size, Ordering::AcqRel);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+    /// Return `size` bytes to this budget and its ancestors.
+    pub fn refund(&self, size: usize) {
+        self.used.fetch_sub(size, Ordering::AcqRel);
+        if let Some(parent) = self.parent {
+            parent.refund(size)
+        }
+    }
+}
+
+/// An [`Allocator`] which charges allocations in `A` to a shared [`Budget`].
+///
+/// Unlike [`SizeLimit`], this doesn't implement [`DeallocateAll`],
+/// since other allocators may be charging the same budget.
+#[derive(Debug, Clone, Copy)]
+pub struct Budgeted<'a, A> {
+    pub inner: A,
+    pub budget: &'a Budget<'a>,
+}
+
+impl<'a, A> Budgeted<'a, A> {
+    pub const fn new(inner: A, budget: &'a Budget<'a>) -> Self {
+        Self { inner, budget }
+    }
+}
+
+impl<A> Budgeted<'_, A> {
+    /// Charge `size` to the budget, then call `f`,
+    /// refunding it if `f` fails.
+    #[inline(always)]
+    fn charged(
+        &self,
+        size: usize,
+        f: impl FnOnce() -> Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.budget.try_charge(size)?;
+        let res = f();
+        if res.is_err() {
+            self.budget.refund(size);
+        }
+        res
+    }
+}
+
+unsafe impl<A> Allocator for Budgeted<'_, A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.charged(layout.size(), || self.inner.allocate(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.budget.refund(layout.size());
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.charged(layout.size(), || self.inner.allocate_zeroed(layout))
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.charged(new_layout.size() - old_layout.size(), || {
+            self.inner.grow(ptr, old_layout, new_layout)
+        })
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.charged(new_layout.size() - old_layout.size(), || {
+            self.inner.grow_zeroed(ptr, old_layout, new_layout)
+        })
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.shrink(ptr, old_layout, new_layout);
+        if res.is_ok() {
+            self.budget.refund(old_layout.size() - new_layout.size());
+        }
+        res
+    }
+}
+
+unsafe impl<A> AllocatesZeroed for Budgeted<'_, A> where A: AllocatesZeroed {}
+
+unsafe impl<A> Owns for Budgeted<'_, A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A> Trim for Budgeted<'_, A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn budget() {
+    let root = Budget::new(8);
+    let (left, right) = (root.child(6), root.child(6));
+    let (l, r) = (Malloc.budgeted(&left), Malloc.budgeted(&right));
+    let first = Box::new_in(1u32, &l);
+    Box::try_new_in(1u32, &l).unwrap_err();
+    let mut v = allocator_api2::vec::Vec::<u8, _>::with_capacity_in(2, &r);
+    v.try_reserve_exact(5).unwrap_err();
+    assert_eq!((left.used(), right.used(), root.used()), (4, 2, 6));
+    assert_eq!(right.remaining(), 2);
+    v.try_reserve_exact(4).unwrap();
+    drop(first);
+    assert_eq!((left.used(), right.used(), root.used()), (0, 4, 4));
+    assert_eq!(left.remaining(), 4);
+    drop(v);
+    assert_eq!(root.used(), 0);
+}
@@ -0,0 +1,233 @@
+use crate::prelude::*;
+use core::{
+    ffi::{c_int, c_void},
+    mem, ptr,
+};
+
+/// The alignment of allocations from `malloc`, matching `max_align_t` on common platforms.
+const MIN_ALIGN: usize = 2 * mem::size_of::<usize>();
+
+/// C-style allocation functions, for use by [`export_c_allocator`](crate::export_c_allocator).
+///
+/// These follow the usual semantics,
+/// returning null (or an error code for [`Self::posix_memalign`]) on failure.
+impl<A> StoreLayout<A>
+where
+    A: Allocator,
+{
+    /// # Safety
+    /// - As for C's `malloc`.
+    pub unsafe fn malloc(&self, size: usize) -> *mut c_void {
+        match Layout::from_size_align(size, MIN_ALIGN) {
+            Ok(layout) => self
+                .allocate(layout)
+                .map_or(ptr::null_mut(), |it| it.as_ptr().cast()),
+            Err(_) => ptr::null_mut(),
+        }
+    }
+    /// # Safety
+    /// - As for C's `calloc`.
+    pub unsafe fn calloc(&self, count: usize, size: usize) -> *mut c_void {
+        let Some(size) = count.checked_mul(size) else {
+            return ptr::null_mut();
+        };
+        match Layout::from_size_align(size, MIN_ALIGN) {
+            Ok(layout) => self
+                .allocate_zeroed(layout)
+                .map_or(ptr::null_mut(), |it| it.as_ptr().cast()),
+            Err(_) => ptr::null_mut(),
+        }
+    }
+    /// Resizing to zero bytes frees `ptr` and returns null.
+    ///
+    /// # Safety
+    /// - As for C's `realloc`, where `ptr` must be from this allocator.
+    pub unsafe fn realloc(&self, ptr: *mut c_void, size: usize) -> *mut c_void {
+        let Some(body) = NonNull::new(ptr.cast::<u8>()) else {
+            return self.malloc(size);
+        };
+        if size == 0 {
+            self.deallocate_untyped(body);
+            return ptr::null_mut();
+        }
+        let old_layout = Self::layout_of(body);
+        let Ok(new_layout) = Layout::from_size_align(size, old_layout.align()) else {
+            return ptr::null_mut();
+        };
+        let res = match new_layout.size() >= old_layout.size() {
+            true => self.grow(body, old_layout, new_layout),
+            false => self.shrink(body, old_layout, new_layout),
+        };
+        res.map_or(ptr::null_mut(), |it| it.as_ptr().cast())
+    }
+    /// # Safety
+    /// - As for C's `free`, where `ptr` must be from this allocator.
+    pub unsafe fn free(&self, ptr: *mut c_void) {
+        if let Some(body) = NonNull::new(ptr.cast::<u8>()) {
+            self.deallocate_untyped(body)
+        }
+    }
+    /// # Safety
+    /// - As for C's `aligned_alloc`.
+    pub unsafe fn aligned_alloc(&self, align: usize, size: usize) -> *mut c_void {
+        match Layout::from_size_align(size, align) {
+            Ok(layout) => self
+                .allocate(layout)
+                .map_or(ptr::null_mut(), |it| it.as_ptr().cast()),
+            Err(_) => ptr::null_mut(),
+        }
+    }
+    /// # Safety
+    /// - As for C's `memalign`.
+    pub unsafe fn memalign(&self, align: usize, size: usize) -> *mut c_void {
+        self.aligned_alloc(align, size)
+    }
+    /// # Safety
+    /// - As for C's `valloc`.
+    #[cfg(unix)]
+    pub unsafe fn valloc(&self, size: usize) -> *mut c_void {
+        self.aligned_alloc(libc::sysconf(libc::_SC_PAGESIZE) as usize, size)
+    }
+    /// The size requested for `ptr`, or zero if it is null.
+    ///
+    /// # Safety
+    /// - As for C's `malloc_usable_size`, where `ptr` must be from this allocator.
+    pub unsafe fn malloc_usable_size(&self, ptr: *mut c_void) -> usize {
+        match NonNull::new(ptr.cast::<u8>()) {
+            Some(body) => Self::layout_of(body).size(),
+            None => 0,
+        }
+    }
+    /// # Safety
+    /// - As for C's `posix_memalign`.
+    pub unsafe fn posix_memalign(
+        &self,
+        memptr: *mut *mut c_void,
+        align: usize,
+        size: usize,
+    ) -> c_int {
+        if !align.is_power_of_two() || !align.is_multiple_of(mem::size_of::<*mut c_void>()) {
+            return libc::EINVAL;
+        }
+        let Ok(layout) = Layout::from_size_align(size, align) else {
+            return libc::ENOMEM;
+        };
+        match self.allocate(layout) {
+            Ok(it) => {
+                memptr.write(it.as_ptr().cast());
+                0
+            }
+            Err(AllocError) => libc::ENOMEM,
+        }
+    }
+}
+
+/// Export `malloc`, `calloc`, `realloc`, `free`, `posix_memalign`, `aligned_alloc`,
+/// `memalign`, `valloc` (on unix) and `malloc_usable_size` symbols
+/// which allocate from a `static` [`StoreLayout`](crate::StoreLayout).
+///
+/// This allows a composed allocator to replace the C allocator,
+/// either by linking it statically or with `LD_PRELOAD`.
+///
+/// The backing allocator must not itself call these symbols,
+/// so e.g [`Malloc`](crate::Malloc) can't be used.
+///
+/// ```no_run
+/// use composable_allocators::*;
+///
+/// static ALLOCATOR: StoreLayout<Pages> = StoreLayout {
+///     inner: Pages::new(),
+/// };
+///
+/// export_c_allocator!(ALLOCATOR);
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! export_c_allocator {
+    ($allocator:path) => {
+        #[no_mangle]
+        pub unsafe extern "C" fn malloc(size: usize) -> *mut ::core::ffi::c_void {
+            $crate::StoreLayout::malloc(&$allocator, size)
+        }
+        #[no_mangle]
+        pub unsafe extern "C" fn calloc(count: usize, size: usize) -> *mut ::core::ffi::c_void {
+            $crate::StoreLayout::calloc(&$allocator, count, size)
+        }
+        #[no_mangle]
+        pub unsafe extern "C" fn realloc(
+            ptr: *mut ::core::ffi::c_void,
+            size: usize,
+        ) -> *mut ::core::ffi::c_void {
+            $crate::StoreLayout::realloc(&$allocator, ptr, size)
+        }
+        #[no_mangle]
+        pub unsafe extern "C" fn free(ptr: *mut ::core::ffi::c_void) {
+            $crate::StoreLayout::free(&$allocator, ptr)
+        }
+        #[no_mangle]
+        pub unsafe extern "C" fn posix_memalign(
+            memptr: *mut *mut ::core::ffi::c_void,
+            align: usize,
+            size: usize,
+        ) -> ::core::ffi::c_int {
+            $crate::StoreLayout::posix_memalign(&$allocator, memptr, align, size)
+        }
+        #[no_mangle]
+        pub unsafe extern "C" fn aligned_alloc(
+            align: usize,
+            size: usize,
+        ) -> *mut ::core::ffi::c_void {
+            $crate::StoreLayout::aligned_alloc(&$allocator, align, size)
+        }
+        #[no_mangle]
+        pub unsafe extern "C" fn memalign(align: usize, size: usize) -> *mut ::core::ffi::c_void {
+            $crate::StoreLayout::memalign(&$allocator, align, size)
+        }
+        #[cfg(unix)]
+        #[no_mangle]
+        pub unsafe extern "C" fn valloc(size: usize) -> *mut ::core::ffi::c_void {
+            $crate::StoreLayout::valloc(&$allocator, size)
+        }
+        #[no_mangle]
+        pub unsafe extern "C" fn malloc_usable_size(ptr: *mut ::core::ffi::c_void) -> usize {
+            $crate::StoreLayout::malloc_usable_size(&$allocator, ptr)
+        }
+    };
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn c_abi() {
+    let a = Malloc.store_layout();
+    unsafe {
+        let p = a.malloc(3).cast::<u8>();
+        assert_eq!(p as usize % MIN_ALIGN, 0);
+        p.copy_from_nonoverlapping([1, 2, 3].as_ptr(), 3);
+        let p = a.realloc(p.cast(), 64).cast::<u8>();
+        assert_eq!(*p.add(2), 3);
+        let p = a.realloc(p.cast(), 2).cast::<u8>();
+        assert_eq!(*p.add(1), 2);
+        a.free(p.cast());
+        let p = a.calloc(4, 8).cast::<u64>();
+        assert_eq!(p.cast::<[u64; 4]>().read(), [0; 4]);
+        assert!(a.realloc(p.cast(), 0).is_null());
+        assert!(a.calloc(usize::MAX, 2).is_null());
+        a.free(ptr::null_mut());
+        let mut p = ptr::null_mut();
+        assert_eq!(a.posix_memalign(&mut p, 3, 8), libc::EINVAL);
+        assert_eq!(a.posix_memalign(&mut p, 4096, 8), 0);
+        assert_eq!(p as usize % 4096, 0);
+        a.free(p);
+        let p = a.aligned_alloc(64, 3);
+        assert_eq!(p as usize % 64, 0);
+        assert_eq!(a.malloc_usable_size(p), 3);
+        a.free(p);
+        assert!(a.memalign(3, 8).is_null());
+        let p = a.valloc(5);
+        assert_eq!(p as usize % libc::sysconf(libc::_SC_PAGESIZE) as usize, 0);
+        let p = a.realloc(p, 7);
+        assert_eq!(a.malloc_usable_size(p), 7);
+        a.free(p);
+        assert_eq!(a.malloc_usable_size(ptr::null_mut()), 0);
+    }
+}
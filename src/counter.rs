@@ -0,0 +1,71 @@
+use core::{
+    cell::Cell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Interior-mutable storage for a `usize`.
+///
+/// [`AtomicUsize`] is [`Sync`], while [`Cell<usize>`] avoids the cost of atomic operations.
+pub trait Counter: From<usize> {
+    fn get(&self) -> usize;
+    fn set(&self, value: usize);
+    /// Apply `f`, returning the previous value if it returned [`Some`].
+    fn try_update(&self, f: impl FnMut(usize) -> Option<usize>) -> Result<usize, usize>;
+    /// Returns the previous value.
+    fn add(&self, value: usize) -> usize;
+    /// Returns the previous value.
+    fn sub(&self, value: usize) -> usize;
+}
+
+impl Counter for AtomicUsize {
+    #[inline(always)]
+    fn get(&self) -> usize {
+        self.load(Ordering::Acquire)
+    }
+    #[inline(always)]
+    fn set(&self, value: usize) {
+        self.store(value, Ordering::Release)
+    }
+    #[inline(always)]
+    fn try_update(&self, f: impl FnMut(usize) -> Option<usize>) -> Result<usize, usize> {
+        self.fetch_update(Ordering::Release, Ordering::Acquire, f)
+    }
+    #[inline(always)]
+    fn add(&self, value: usize) -> usize {
+        self.fetch_add(value, Ordering::Release)
+    }
+    #[inline(always)]
+    fn sub(&self, value: usize) -> usize {
+        self.fetch_sub(value, Ordering::Release)
+    }
+}
+
+impl Counter for Cell<usize> {
+    #[inline(always)]
+    fn get(&self) -> usize {
+        Cell::get(self)
+    }
+    #[inline(always)]
+    fn set(&self, value: usize) {
+        Cell::set(self, value)
+    }
+    #[inline(always)]
+    fn try_update(&self, mut f: impl FnMut(usize) -> Option<usize>) -> Result<usize, usize> {
+        let prev = Cell::get(self);
+        match f(prev) {
+            Some(it) => {
+                Cell::set(self, it);
+                Ok(prev)
+            }
+            None => Err(prev),
+        }
+    }
+    #[inline(always)]
+    fn add(&self, value: usize) -> usize {
+        self.replace(Cell::get(self).wrapping_add(value))
+    }
+    #[inline(always)]
+    fn sub(&self, value: usize) -> usize {
+        self.replace(Cell::get(self).wrapping_sub(value))
+    }
+}
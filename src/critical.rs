@@ -0,0 +1,224 @@
+use crate::prelude::*;
+use core::{
+    cell::{Cell, UnsafeCell},
+    fmt,
+};
+
+/// Runs code with interrupts disabled,
+/// e.g with `critical_section::with` or `cortex_m::interrupt::free`.
+///
+/// # Safety
+/// - [`Self::with`] must not run `f` concurrently with another call on any thread or interrupt.
+pub unsafe trait CriticalSection {
+    fn with<R>(&self, f: impl FnOnce() -> R) -> R;
+}
+
+/// An [`Allocator`] which runs every call to `A` in a [`CriticalSection`].
+///
+/// Unlike [`Locked`], this can't deadlock if an interrupt handler allocates,
+/// so it suits single-core embedded targets.
+/// Heaps such as `LinkedListHeap`, with the `linked_list_allocator` feature,
+/// can be made [`Sync`] with this.
+/// Others can be wrapped in an [`Allocator`], and given [`Owns`] with [`InSpan`].
+///
+/// Use [`Self::with_mut`] to reach `A` mutably, e.g to extend a heap:
+/// ```
+/// # use composable_allocators::*;
+/// struct Interrupts;
+/// unsafe impl CriticalSection for Interrupts {
+///     fn with<R>(&self, f: impl FnOnce() -> R) -> R {
+///         // disable interrupts...
+///         f()
+///     }
+/// }
+/// # let mut buffer = [core::mem::MaybeUninit::uninit(); 256];
+/// let a = Critical::new(Region::new(&mut buffer), Interrupts);
+/// a.with_mut(|it| unsafe { it.deallocate_all() });
+/// ```
+pub struct Critical<A, C> {
+    inner: UnsafeCell<A>,
+    busy: Cell<bool>,
+    critical_section: C,
+}
+
+unsafe impl<A: Send, C: Send> Send for Critical<A, C> {}
+unsafe impl<A: Send, C: Sync> Sync for Critical<A, C> {}
+
+impl<A, C> Critical<A, C> {
+    pub const fn new(inner: A, critical_section: C) -> Self {
+        Self {
+            inner: UnsafeCell::new(inner),
+            busy: Cell::new(false),
+            critical_section,
+        }
+    }
+    pub fn into_inner(self) -> A {
+        self.inner.into_inner()
+    }
+}
+
+impl<A, C> Critical<A, C>
+where
+    C: CriticalSection,
+{
+    /// Run `f` with exclusive access to `A`, in the critical section.
+    ///
+    /// # Panics
+    /// - If `f` calls back into this [`Critical`].
+    #[inline(always)]
+    pub fn with_mut<R>(&self, f: impl FnOnce(&mut A) -> R) -> R {
+        self.critical_section.with(|| {
+            // `busy` is only touched in the critical section
+            assert!(!self.busy.replace(true), "reentrant call to Critical");
+            let res = f(unsafe { &mut *self.inner.get() });
+            self.busy.set(false);
+            res
+        })
+    }
+    #[inline(always)]
+    fn with<R>(&self, f: impl FnOnce(&A) -> R) -> R {
+        self.with_mut(|it| f(it))
+    }
+}
+
+impl<A, C> fmt::Debug for Critical<A, C>
+where
+    C: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Critical")
+            .field("critical_section", &self.critical_section)
+            .finish_non_exhaustive()
+    }
+}
+
+unsafe impl<A, C> Allocator for Critical<A, C>
+where
+    A: Allocator,
+    C: CriticalSection,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.with(|it| it.allocate(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.with(|it| it.deallocate(ptr, layout))
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.with(|it| it.allocate_zeroed(layout))
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.with(|it| it.grow(ptr, old_layout, new_layout))
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.with(|it| it.grow_zeroed(ptr, old_layout, new_layout))
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.with(|it| it.shrink(ptr, old_layout, new_layout))
+    }
+}
+
+unsafe impl<A, C> AllocatesZeroed for Critical<A, C>
+where
+    A: AllocatesZeroed,
+    C: CriticalSection,
+{
+}
+
+impl<A, C> DeallocateAll for Critical<A, C>
+where
+    A: DeallocateAll,
+    C: CriticalSection,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.with(|it| it.deallocate_all())
+    }
+}
+
+unsafe impl<A, C> Owns for Critical<A, C>
+where
+    A: Owns,
+    C: CriticalSection,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.with(|it| it.owns(ptr, layout))
+    }
+}
+
+impl<A, C> UsableSize for Critical<A, C>
+where
+    A: UsableSize,
+    C: CriticalSection,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.with(|it| it.usable_size(ptr, layout))
+    }
+}
+
+impl<A, C> Trim for Critical<A, C>
+where
+    A: Trim,
+    C: CriticalSection,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.with(|it| it.trim())
+    }
+}
+
+#[test]
+fn critical() {
+    use core::sync::atomic::{AtomicBool, Ordering};
+    struct Flag(AtomicBool);
+    unsafe impl CriticalSection for Flag {
+        fn with<R>(&self, f: impl FnOnce() -> R) -> R {
+            assert!(!self.0.swap(true, Ordering::Acquire));
+            let res = f();
+            self.0.store(false, Ordering::Release);
+            res
+        }
+    }
+    let mut buffer = [core::mem::MaybeUninit::uninit(); 64];
+    let a = Critical::new(Region::new(&mut buffer), Flag(AtomicBool::new(false)));
+    let it = Box::new_in(1u32, &a);
+    assert!(a.owns(NonNull::from(&*it).cast(), Layout::new::<u32>()));
+    drop(it);
+    assert!(a.with_mut(|it| it.allocate(Layout::new::<u64>()).is_ok()));
+}
+
+#[test]
+#[should_panic = "reentrant call to Critical"]
+fn critical_reentrant() {
+    struct Nop;
+    unsafe impl CriticalSection for Nop {
+        fn with<R>(&self, f: impl FnOnce() -> R) -> R {
+            f()
+        }
+    }
+    let mut buffer = [core::mem::MaybeUninit::uninit(); 64];
+    let a = Critical::new(Region::new(&mut buffer), Nop);
+    a.with_mut(|_| a.allocate(Layout::new::<u32>())).unwrap();
+}
@@ -0,0 +1,152 @@
+use crate::prelude::*;
+
+/// An [`Allocator`] which emits a [`defmt`] log frame for every call to `A`,
+/// labelled with [`Self::name`], like `Logged` but for embedded targets.
+///
+/// Successes are logged at `trace` level, and failures at `debug`.
+/// Formatting is deferred to the host, so this is cheap enough for e.g RTT on Cortex-M.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DefmtLogged<A> {
+    pub inner: A,
+    pub name: &'static str,
+}
+
+impl<A> DefmtLogged<A> {
+    pub const fn new(inner: A, name: &'static str) -> Self {
+        Self { inner, name }
+    }
+    #[inline(always)]
+    fn log(
+        &self,
+        method: &str,
+        old_layout: Option<Layout>,
+        layout: Layout,
+        res: Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let Self { name, .. } = *self;
+        let (size, align) = (layout.size(), layout.align());
+        match (res, old_layout) {
+            (Ok(ptr), None) => defmt::trace!(
+                "{=str}: {=str}(size={=usize}, align={=usize}) -> {=usize:#x}",
+                name,
+                method,
+                size,
+                align,
+                ptr.cast::<u8>().as_ptr() as usize
+            ),
+            (Ok(ptr), Some(old)) => defmt::trace!(
+                "{=str}: {=str}(old_size={=usize}, size={=usize}, align={=usize}) -> {=usize:#x}",
+                name,
+                method,
+                old.size(),
+                size,
+                align,
+                ptr.cast::<u8>().as_ptr() as usize
+            ),
+            (Err(AllocError), None) => defmt::debug!(
+                "{=str}: {=str}(size={=usize}, align={=usize}) failed",
+                name,
+                method,
+                size,
+                align
+            ),
+            (Err(AllocError), Some(old)) => defmt::debug!(
+                "{=str}: {=str}(old_size={=usize}, size={=usize}, align={=usize}) failed",
+                name,
+                method,
+                old.size(),
+                size,
+                align
+            ),
+        }
+        res
+    }
+}
+
+unsafe impl<A> Allocator for DefmtLogged<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.log("allocate", None, layout, self.inner.allocate(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        defmt::trace!(
+            "{=str}: deallocate({=usize:#x}, size={=usize}, align={=usize})",
+            self.name,
+            ptr.as_ptr() as usize,
+            layout.size(),
+            layout.align()
+        );
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.allocate_zeroed(layout);
+        self.log("allocate_zeroed", None, layout, res)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.grow(ptr, old_layout, new_layout);
+        self.log("grow", Some(old_layout), new_layout, res)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.grow_zeroed(ptr, old_layout, new_layout);
+        self.log("grow_zeroed", Some(old_layout), new_layout, res)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.shrink(ptr, old_layout, new_layout);
+        self.log("shrink", Some(old_layout), new_layout, res)
+    }
+}
+
+unsafe impl<A> AllocatesZeroed for DefmtLogged<A> where A: AllocatesZeroed {}
+
+unsafe impl<A> Owns for DefmtLogged<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A> UsableSize for DefmtLogged<A>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A> Trim for DefmtLogged<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
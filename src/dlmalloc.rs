@@ -0,0 +1,131 @@
+use crate::{global::wrap, prelude::*, segregate::relocate, spin::Spin};
+use core::{fmt, ptr};
+
+/// An allocator using [`dlmalloc`](https://docs.rs/dlmalloc), a port of dlmalloc to Rust,
+/// for targets without a C allocator, like `wasm32-unknown-unknown`.
+///
+/// Each instance is a separate heap behind a spinlock,
+/// which requests memory from the OS as it grows,
+/// and doesn't return it when dropped.
+pub struct Dlmalloc {
+    heap: Spin<::dlmalloc::Dlmalloc>,
+}
+
+// the heap only holds pointers to memory it owns
+unsafe impl Send for Dlmalloc {}
+unsafe impl Sync for Dlmalloc {}
+
+impl Dlmalloc {
+    pub const fn new() -> Self {
+        Self {
+            heap: Spin::new(::dlmalloc::Dlmalloc::new()),
+        }
+    }
+    /// # Safety
+    /// - As for [`Allocator::grow`] or [`Allocator::shrink`].
+    #[inline(always)]
+    unsafe fn resize(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        zeroed: bool,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        // `realloc` keeps the old alignment
+        if old_layout.align() != new_layout.align() {
+            return relocate(self, self, ptr, old_layout, new_layout, zeroed);
+        }
+        let new = self.heap.lock().realloc(
+            ptr.as_ptr(),
+            old_layout.size(),
+            old_layout.align(),
+            new_layout.size(),
+        );
+        let new = wrap(new, new_layout.size())?;
+        if zeroed {
+            ptr::write_bytes(
+                new.cast::<u8>().as_ptr().add(old_layout.size()),
+                0,
+                new_layout.size() - old_layout.size(),
+            )
+        }
+        Ok(new)
+    }
+}
+
+impl Default for Dlmalloc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Dlmalloc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dlmalloc").finish_non_exhaustive()
+    }
+}
+
+unsafe impl Allocator for Dlmalloc {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = unsafe { self.heap.lock().malloc(layout.size(), layout.align()) };
+        wrap(ptr, layout.size())
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.heap
+            .lock()
+            .free(ptr.as_ptr(), layout.size(), layout.align())
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = unsafe { self.heap.lock().calloc(layout.size(), layout.align()) };
+        wrap(ptr, layout.size())
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, false)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, true)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, false)
+    }
+}
+
+#[test]
+fn dlmalloc() {
+    let a = Dlmalloc::new();
+    let mut v = allocator_api2::vec::Vec::<u64, _>::new_in(&a);
+    v.extend(0..1024);
+    v.truncate(4);
+    v.shrink_to_fit();
+    assert_eq!(v, [0, 1, 2, 3]);
+    let aligned = Box::new_in(Aligned([1; 3]), &a);
+    assert_eq!(NonNull::from(&*aligned).as_ptr() as usize % 256, 0);
+    assert_eq!(aligned.0, [1; 3]);
+    let zeroed = Box::<[u8; 64], _>::new_zeroed_in(&a);
+    assert_eq!(*unsafe { zeroed.assume_init() }, [0; 64]);
+}
+
+#[cfg(test)]
+#[repr(align(256))]
+struct Aligned([u8; 3]);
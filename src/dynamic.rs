@@ -0,0 +1,100 @@
+use crate::prelude::*;
+use alloc::boxed::Box;
+use core::fmt;
+
+/// Object-safe combination of the traits erased by [`DynAllocator`].
+trait Erased: Allocator + Owns + Send + Sync {}
+impl<A> Erased for A where A: Allocator + Owns + Send + Sync {}
+
+/// An [`Allocator`] which erases the type of a composition behind a vtable.
+///
+/// Construct with [`Self::new`], or [`From`] a boxed allocator.
+pub struct DynAllocator {
+    inner: Box<dyn Erased>,
+}
+
+impl DynAllocator {
+    pub fn new<A>(inner: A) -> Self
+    where
+        A: Allocator + Owns + Send + Sync + 'static,
+    {
+        Self {
+            inner: Box::new(inner),
+        }
+    }
+}
+
+impl<A> From<Box<A>> for DynAllocator
+where
+    A: Allocator + Owns + Send + Sync + 'static,
+{
+    fn from(inner: Box<A>) -> Self {
+        Self { inner }
+    }
+}
+
+impl fmt::Debug for DynAllocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynAllocator").finish_non_exhaustive()
+    }
+}
+
+unsafe impl Allocator for DynAllocator {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.allocate(layout)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.allocate_zeroed(layout)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.grow(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.grow_zeroed(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.shrink(ptr, old_layout, new_layout)
+    }
+}
+
+unsafe impl Owns for DynAllocator {
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+#[test]
+fn dyn_allocator() {
+    let a = DynAllocator::new(Null);
+    allocator_api2::boxed::Box::try_new_in(1u8, &a).unwrap_err();
+    let a = DynAllocator::from(Box::new(Region::new(Box::leak(Box::new(
+        [core::mem::MaybeUninit::uninit(); 8],
+    )))));
+    let it = allocator_api2::boxed::Box::new_in(1u8, &a);
+    assert!(a.owns(NonNull::from(&*it).cast(), Layout::new::<u8>()));
+}
@@ -0,0 +1,116 @@
+use crate::prelude::*;
+
+/// An [`Allocator`] which is one of two allocators, chosen at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Either<A, B> {
+    Left(A),
+    Right(B),
+}
+
+macro_rules! either {
+    ($self:expr, $it:ident => $expr:expr) => {
+        match $self {
+            Either::Left($it) => $expr,
+            Either::Right($it) => $expr,
+        }
+    };
+}
+
+unsafe impl<A, B> Allocator for Either<A, B>
+where
+    A: Allocator,
+    B: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        either!(self, it => it.allocate(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        either!(self, it => it.deallocate(ptr, layout))
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        either!(self, it => it.allocate_zeroed(layout))
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        either!(self, it => it.grow(ptr, old_layout, new_layout))
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        either!(self, it => it.grow_zeroed(ptr, old_layout, new_layout))
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        either!(self, it => it.shrink(ptr, old_layout, new_layout))
+    }
+}
+
+impl<A, B> DeallocateAll for Either<A, B>
+where
+    A: DeallocateAll,
+    B: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        either!(self, it => it.deallocate_all())
+    }
+}
+
+unsafe impl<A, B> Owns for Either<A, B>
+where
+    A: Owns,
+    B: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        either!(self, it => it.owns(ptr, layout))
+    }
+}
+
+impl<A, B> UsableSize for Either<A, B>
+where
+    A: UsableSize,
+    B: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        either!(self, it => it.usable_size(ptr, layout))
+    }
+}
+
+impl<A, B> Trim for Either<A, B>
+where
+    A: Trim,
+    B: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        either!(self, it => it.trim())
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn either() {
+    let a = Either::<_, Null>::Left(Malloc);
+    let _ = Box::new_in(1u8, &a);
+    let a = Either::<Malloc, _>::Right(Null);
+    Box::try_new_in(1u8, &a).unwrap_err();
+}
@@ -0,0 +1,381 @@
+use crate::prelude::*;
+use crate::rng::SplitMix;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// An [`Allocator`] which fails every allocation after [`remaining`](Self::remaining)
+/// successful ones.
+///
+/// Growing counts as an allocation.
+#[derive(Debug)]
+pub struct FailAfter<A> {
+    pub inner: A,
+    pub remaining: AtomicUsize,
+}
+
+impl<A> FailAfter<A> {
+    pub const fn new(inner: A, remaining: usize) -> Self {
+        Self {
+            inner,
+            remaining: AtomicUsize::new(remaining),
+        }
+    }
+    /// Allow `remaining` more allocations.
+    pub fn reset(&self, remaining: usize) {
+        self.remaining.store(remaining, Ordering::Release)
+    }
+    #[inline(always)]
+    fn check(&self) -> Result<(), AllocError> {
+        self.remaining
+            .fetch_update(Ordering::Release, Ordering::Acquire, |it| it.checked_sub(1))
+            .map(drop)
+            .map_err(|_| AllocError)
+    }
+}
+
+unsafe impl<A> Allocator for FailAfter<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.check()?;
+        self.inner.allocate(layout)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.check()?;
+        self.inner.allocate_zeroed(layout)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.check()?;
+        self.inner.grow(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.check()?;
+        self.inner.grow_zeroed(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.shrink(ptr, old_layout, new_layout)
+    }
+}
+
+unsafe impl<A> AllocatesZeroed for FailAfter<A> where A: AllocatesZeroed {}
+
+unsafe impl<A> Owns for FailAfter<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A> UsableSize for FailAfter<A>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A> Trim for FailAfter<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn fail_after() {
+    let a = Malloc.fail_after(1);
+    let _ = Box::new_in(1, &a);
+    Box::try_new_in(1, &a).unwrap_err();
+    a.reset(1);
+    let _ = Box::new_in(1, &a);
+}
+
+/// An [`Allocator`] which fails every [`every`](Self::every)th allocation.
+///
+/// Growing counts as an allocation.
+#[derive(Debug)]
+pub struct FailEvery<A> {
+    pub inner: A,
+    pub every: AtomicUsize,
+    pub count: AtomicUsize,
+}
+
+impl<A> FailEvery<A> {
+    pub const fn new(inner: A, every: usize) -> Self {
+        Self {
+            inner,
+            every: AtomicUsize::new(every),
+            count: AtomicUsize::new(0),
+        }
+    }
+    /// Fail every `every`th allocation, starting the count again.
+    pub fn reset(&self, every: usize) {
+        self.every.store(every, Ordering::Release);
+        self.count.store(0, Ordering::Release);
+    }
+    #[inline(always)]
+    fn check(&self) -> Result<(), AllocError> {
+        let count = self.count.fetch_add(1, Ordering::AcqRel).wrapping_add(1);
+        match self.every.load(Ordering::Acquire) {
+            0 => Ok(()),
+            every if count.is_multiple_of(every) => Err(AllocError),
+            _ => Ok(()),
+        }
+    }
+}
+
+unsafe impl<A> Allocator for FailEvery<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.check()?;
+        self.inner.allocate(layout)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.check()?;
+        self.inner.allocate_zeroed(layout)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.check()?;
+        self.inner.grow(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.check()?;
+        self.inner.grow_zeroed(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.shrink(ptr, old_layout, new_layout)
+    }
+}
+
+unsafe impl<A> AllocatesZeroed for FailEvery<A> where A: AllocatesZeroed {}
+
+unsafe impl<A> Owns for FailEvery<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A> UsableSize for FailEvery<A>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A> Trim for FailEvery<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn fail_every() {
+    let a = Malloc.fail_every(2);
+    let _ = Box::new_in(1, &a);
+    Box::try_new_in(1, &a).unwrap_err();
+    let _ = Box::new_in(1, &a);
+    Box::try_new_in(1, &a).unwrap_err();
+}
+
+/// An [`Allocator`] which fails a random fraction of allocations,
+/// using a generator seeded on construction.
+///
+/// Growing counts as an allocation.
+#[derive(Debug)]
+pub struct FailRandomly<A> {
+    pub inner: A,
+    /// Out of [`u64::MAX`].
+    pub threshold: AtomicU64,
+    rng: SplitMix,
+}
+
+impl<A> FailRandomly<A> {
+    /// Fail each allocation with the given `probability` between `0.0` and `1.0`.
+    pub const fn new(inner: A, seed: u64, probability: f64) -> Self {
+        Self {
+            inner,
+            threshold: AtomicU64::new(Self::threshold(probability)),
+            rng: SplitMix::new(seed),
+        }
+    }
+    pub fn set_probability(&self, probability: f64) {
+        self.threshold
+            .store(Self::threshold(probability), Ordering::Release)
+    }
+    const fn threshold(probability: f64) -> u64 {
+        (probability * u64::MAX as f64) as u64
+    }
+    #[inline(always)]
+    fn check(&self) -> Result<(), AllocError> {
+        match self.rng.next() < self.threshold.load(Ordering::Acquire) {
+            true => Err(AllocError),
+            false => Ok(()),
+        }
+    }
+}
+
+unsafe impl<A> Allocator for FailRandomly<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.check()?;
+        self.inner.allocate(layout)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.check()?;
+        self.inner.allocate_zeroed(layout)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.check()?;
+        self.inner.grow(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.check()?;
+        self.inner.grow_zeroed(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.shrink(ptr, old_layout, new_layout)
+    }
+}
+
+unsafe impl<A> AllocatesZeroed for FailRandomly<A> where A: AllocatesZeroed {}
+
+unsafe impl<A> Owns for FailRandomly<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A> UsableSize for FailRandomly<A>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A> Trim for FailRandomly<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn fail_randomly() {
+    let a = Malloc.fail_randomly(0, 0.5);
+    let failures = (0..1000)
+        .filter(|_| Box::try_new_in(1, &a).is_err())
+        .count();
+    assert!((400..600).contains(&failures));
+    a.set_probability(0.0);
+    assert!((0..1000).all(|_| Box::try_new_in(1, &a).is_ok()));
+    a.set_probability(1.0);
+    assert!((0..1000).all(|_| Box::try_new_in(1, &a).is_err()));
+}
@@ -0,0 +1,210 @@
+use crate::{prelude::*, segregate::relocate};
+use core::{marker::PhantomData, ptr};
+
+/// A byte to fill memory with, see [`Fill`].
+pub trait Pattern {
+    /// [`None`] to leave memory untouched.
+    const BYTE: Option<u8>;
+}
+
+/// A [`Pattern`] which fills memory with `B`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Byte<const B: u8>;
+
+impl<const B: u8> Pattern for Byte<B> {
+    const BYTE: Option<u8> = Some(B);
+}
+
+/// A [`Pattern`] which leaves memory untouched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Untouched;
+
+impl Pattern for Untouched {
+    const BYTE: Option<u8> = None;
+}
+
+#[inline(always)]
+unsafe fn write_pattern<P: Pattern>(ptr: *mut u8, len: usize) {
+    if let Some(byte) = P::BYTE {
+        ptr::write_bytes(ptr, byte, len)
+    }
+}
+
+/// An [`Allocator`] which fills memory with `AllocT` when it is allocated,
+/// and `FreeT` when it is deallocated.
+///
+/// This makes reads of uninitialized or freed memory easy to spot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fill<A, AllocT = Byte<0xAA>, FreeT = Byte<0xDD>> {
+    pub inner: A,
+    pub alloc: PhantomData<fn() -> AllocT>,
+    pub free: PhantomData<fn() -> FreeT>,
+}
+
+impl<A, AllocT, FreeT> Fill<A, AllocT, FreeT> {
+    pub const fn new(inner: A) -> Self {
+        Self {
+            inner,
+            alloc: PhantomData,
+            free: PhantomData,
+        }
+    }
+}
+
+unsafe impl<A, AllocT, FreeT> Allocator for Fill<A, AllocT, FreeT>
+where
+    A: Allocator,
+    AllocT: Pattern,
+    FreeT: Pattern,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.inner.allocate(layout)?;
+        unsafe { write_pattern::<AllocT>(ptr.cast::<u8>().as_ptr(), ptr.len()) };
+        Ok(ptr)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        write_pattern::<FreeT>(ptr.as_ptr(), layout.size());
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.allocate_zeroed(layout)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        // `A` would free the old block unfilled if it moved it,
+        // so move it ourselves, filling the new block and then the old one
+        if FreeT::BYTE.is_some() {
+            return relocate(self, self, ptr, old_layout, new_layout, false);
+        }
+        let new = self.inner.grow(ptr, old_layout, new_layout)?;
+        write_pattern::<AllocT>(
+            new.cast::<u8>().as_ptr().add(old_layout.size()),
+            new.len() - old_layout.size(),
+        );
+        Ok(new)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        match FreeT::BYTE {
+            Some(_) => relocate(self, self, ptr, old_layout, new_layout, true),
+            None => self.inner.grow_zeroed(ptr, old_layout, new_layout),
+        }
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        // the freed tail belongs to `A` once it has shrunk in place,
+        // so move the allocation instead, filling the whole old block
+        match FreeT::BYTE {
+            Some(_) => relocate(self, &self.inner, ptr, old_layout, new_layout, false),
+            None => self.inner.shrink(ptr, old_layout, new_layout),
+        }
+    }
+}
+
+unsafe impl<A, AllocT, FreeT> Owns for Fill<A, AllocT, FreeT>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A, AllocT, FreeT> UsableSize for Fill<A, AllocT, FreeT>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A, AllocT, FreeT> Trim for Fill<A, AllocT, FreeT>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn fill() {
+    let a = Malloc.fill();
+    let ptr = a.allocate(Layout::new::<[u8; 4]>()).unwrap();
+    assert_eq!(unsafe { ptr.as_ref() }, [0xAA; 4]);
+    unsafe { a.deallocate(ptr.cast(), Layout::new::<[u8; 4]>()) };
+    let _ = Box::new_in(1u8, Malloc.fill().guard(0xFF_u8, 0xEE_u8));
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn failed_shrink() {
+    let a = Fill::<_, Byte<0xAA>, Byte<0xDD>>::new(Malloc.limit_count(1));
+    let old = Layout::new::<[u8; 4]>();
+    let new = Layout::new::<[u8; 2]>();
+    unsafe {
+        let ptr = a.allocate(old).unwrap().cast::<[u8; 4]>();
+        ptr.write([1, 2, 3, 4]);
+        a.shrink(ptr.cast(), old, new).unwrap_err();
+        assert_eq!(ptr.read(), [1, 2, 3, 4]);
+        a.deallocate(ptr.cast(), old);
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn grow_moved() {
+    // read each block just before it's freed
+    let freed = core::cell::Cell::new(0);
+    let a = Malloc
+        .hooked(|event| {
+            if let Event::Deallocated { ptr, layout } = event {
+                let block = unsafe { core::slice::from_raw_parts(ptr.as_ptr(), layout.size()) };
+                assert!(block.iter().all(|it| *it == 0xDD));
+                freed.set(freed.get() + 1);
+            }
+        })
+        .never_in_place()
+        .fill();
+    let old = Layout::new::<[u8; 4]>();
+    let new = Layout::new::<[u8; 8]>();
+    unsafe {
+        let ptr = a.allocate(old).unwrap().cast::<[u8; 4]>();
+        ptr.write([1, 2, 3, 4]);
+        let grown = a.grow(ptr.cast(), old, new).unwrap().cast::<[u8; 8]>();
+        assert_eq!(freed.get(), 1);
+        assert_eq!(grown.read(), [1, 2, 3, 4, 0xAA, 0xAA, 0xAA, 0xAA]);
+        let zeroed = a.grow_zeroed(grown.cast(), new, Layout::new::<[u8; 16]>());
+        let zeroed = zeroed.unwrap().cast::<[u8; 16]>();
+        assert_eq!(freed.get(), 2);
+        assert_eq!(
+            zeroed.read()[4..],
+            [0xAA, 0xAA, 0xAA, 0xAA, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+        a.deallocate(zeroed.cast(), Layout::new::<[u8; 16]>());
+    }
+    assert_eq!(freed.get(), 3);
+}
@@ -0,0 +1,173 @@
+use crate::prelude::*;
+use core::cell::Cell;
+
+/// An [`Allocator`] for per-frame allocations, e.g in a game or simulation,
+/// which allocates from the current of two bump allocators,
+/// and frees everything in the other when [frames are swapped](Self::swap_frames).
+///
+/// So allocations survive until the end of the frame after the one they were made in,
+/// and may be handed from one frame to the next.
+/// ```
+/// # use composable_allocators::*;
+/// let frames = FrameAllocator::new(Malloc.arena(4096), Malloc.arena(4096));
+/// let mut last = allocator_api2::vec::Vec::new_in(&frames);
+/// for frame in 0..4 {
+///     let mut next = allocator_api2::vec::Vec::new_in(&frames);
+///     next.extend(last.iter().map(|it| it + 1));
+///     next.push(0);
+///     last = next;
+///     // SAFETY: `last` was allocated in this frame, and nothing older is kept
+///     unsafe { frames.swap_frames() };
+///     assert_eq!(last.len(), frame + 1);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct FrameAllocator<A> {
+    pub frames: [A; 2],
+    current: Cell<usize>,
+}
+
+impl<A> FrameAllocator<A> {
+    pub const fn new(first: A, second: A) -> Self {
+        Self {
+            frames: [first, second],
+            current: Cell::new(0),
+        }
+    }
+    /// The allocator for this frame.
+    pub fn current(&self) -> &A {
+        &self.frames[self.current.get()]
+    }
+    /// The allocator for the previous frame.
+    pub fn previous(&self) -> &A {
+        &self.frames[1 - self.current.get()]
+    }
+}
+
+impl<A> FrameAllocator<A>
+where
+    A: DeallocateAll,
+{
+    /// Begin a new frame, freeing every allocation made before the current one.
+    ///
+    /// # Safety
+    /// - allocations made before the current frame began must not be used after this call.
+    pub unsafe fn swap_frames(&self) {
+        self.previous().deallocate_all();
+        self.current.set(1 - self.current.get())
+    }
+}
+
+impl<A> FrameAllocator<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owner(&self, ptr: NonNull<u8>, layout: Layout) -> &A {
+        match self.current().owns(ptr, layout) {
+            true => self.current(),
+            false => self.previous(),
+        }
+    }
+}
+
+unsafe impl<A> Allocator for FrameAllocator<A>
+where
+    A: Allocator + Owns,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.current().allocate(layout)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.owner(ptr, layout).deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.current().allocate_zeroed(layout)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.owner(ptr, old_layout)
+            .grow(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.owner(ptr, old_layout)
+            .grow_zeroed(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.owner(ptr, old_layout)
+            .shrink(ptr, old_layout, new_layout)
+    }
+}
+
+impl<A> DeallocateAll for FrameAllocator<A>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.frames.iter().for_each(|it| it.deallocate_all())
+    }
+}
+
+unsafe impl<A> Owns for FrameAllocator<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.frames.iter().any(|it| it.owns(ptr, layout))
+    }
+}
+
+impl<A> Trim for FrameAllocator<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.frames.iter().for_each(A::trim)
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn frame_allocator() {
+    let stats = Malloc.stats();
+    let frames = FrameAllocator::new((&stats).stack(64), (&stats).stack(64));
+    let first = Box::new_in(1u64, &frames);
+    unsafe { frames.swap_frames() };
+    let second = Box::new_in(2u64, &frames);
+    assert!(frames
+        .previous()
+        .owns(NonNull::from(&*first).cast(), Layout::new::<u64>()));
+    assert!(frames
+        .current()
+        .owns(NonNull::from(&*second).cast(), Layout::new::<u64>()));
+    let first_ptr = NonNull::from(&*first);
+    drop(first);
+    unsafe { frames.swap_frames() };
+    // the first frame's space is reused
+    let third = Box::new_in(3u64, &frames);
+    assert_eq!(NonNull::from(&*third), first_ptr);
+    assert_eq!((*second, *third), (2, 3));
+}
@@ -0,0 +1,320 @@
+use crate::{prelude::*, spin::lock};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Mutex, MutexGuard},
+};
+
+/// A live allocation, as returned to the caller.
+#[derive(Debug, Clone, Copy)]
+struct Live {
+    layout: Layout,
+    len: usize,
+}
+
+#[derive(Debug)]
+struct State {
+    live: BTreeMap<usize, Live>,
+    /// The most recently freed allocations, oldest first.
+    freed: VecDeque<(usize, Layout)>,
+}
+
+/// An [`Allocator`] which [`panic`]s instead of passing an invalid pointer or [`Layout`] to `A`,
+/// i.e when memory is deallocated or resized:
+/// - twice, remembering the last [`Self::history`] frees.
+/// - without having been allocated.
+/// - with a [`Layout`] which doesn't fit the allocation.
+///
+/// Zero-sized allocations aren't checked.
+///
+/// See [`Guard`] to detect buffer overruns.
+#[derive(Debug)]
+pub struct FreeCheck<A> {
+    pub inner: A,
+    history: usize,
+    state: Mutex<State>,
+}
+
+impl<A> FreeCheck<A> {
+    pub const fn new(inner: A, history: usize) -> Self {
+        Self {
+            inner,
+            history,
+            state: Mutex::new(State {
+                live: BTreeMap::new(),
+                freed: VecDeque::new(),
+            }),
+        }
+    }
+    /// The number of freed allocations remembered to detect double frees.
+    pub fn history(&self) -> usize {
+        self.history
+    }
+    #[inline(always)]
+    fn state(&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+    #[inline(always)]
+    fn allocated(
+        &self,
+        layout: Layout,
+        res: Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        // zero-sized allocations may share an address, so aren't tracked
+        if layout.size() == 0 {
+            return res;
+        }
+        if let Ok(ptr) = res {
+            let addr = ptr.cast::<u8>().as_ptr() as usize;
+            let mut state = self.state();
+            state.freed.retain(|(it, _)| *it != addr);
+            let live = Live {
+                layout,
+                len: ptr.len(),
+            };
+            state.live.insert(addr, live);
+        }
+        res
+    }
+    /// [`panic`] unless `ptr` and `layout` may be passed to `A`,
+    /// then stop tracking it.
+    #[inline(always)]
+    #[track_caller]
+    fn freed(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() == 0 {
+            return;
+        }
+        let addr = ptr.as_ptr() as usize;
+        let mut state = self.state();
+        let Some(live) = state.live.get(&addr).copied() else {
+            drop(state);
+            match self.state().freed.iter().rev().find(|(it, _)| *it == addr) {
+                Some((_, freed)) => panic!(
+                    "double free of {ptr:p} with {layout:?}, previously freed with {freed:?}"
+                ),
+                None => {
+                    panic!("invalid free of {ptr:p} with {layout:?}, which was never allocated")
+                }
+            }
+        };
+        let Live {
+            layout: allocated,
+            len,
+        } = live;
+        if layout.align() != allocated.align() || !(allocated.size()..=len).contains(&layout.size())
+        {
+            drop(state);
+            panic!("{ptr:p} was freed with {layout:?}, but allocated with {allocated:?}")
+        }
+        state.live.remove(&addr);
+        if self.history != 0 {
+            if state.freed.len() == self.history {
+                state.freed.pop_front();
+            }
+            state.freed.push_back((addr, layout));
+        }
+    }
+    /// Check a resize before passing it to `A`,
+    /// then track the new allocation (or restore the old one).
+    #[inline(always)]
+    #[track_caller]
+    fn reallocate(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        f: impl FnOnce() -> Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let before = self.state().live.get(&(ptr.as_ptr() as usize)).copied();
+        self.freed(ptr, old_layout);
+        match f() {
+            Ok(new) => self.allocated(new_layout, Ok(new)),
+            Err(AllocError) => {
+                if let Some(before) = before {
+                    let addr = ptr.as_ptr() as usize;
+                    let mut state = self.state();
+                    // other frees may have been recorded since
+                    if let Some(ix) = state.freed.iter().rposition(|(it, _)| *it == addr) {
+                        state.freed.remove(ix);
+                    }
+                    state.live.insert(addr, before);
+                }
+                Err(AllocError)
+            }
+        }
+    }
+}
+
+unsafe impl<A> Allocator for FreeCheck<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocated(layout, self.inner.allocate(layout))
+    }
+    #[inline(always)]
+    #[track_caller]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.freed(ptr, layout);
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocated(layout, self.inner.allocate_zeroed(layout))
+    }
+    #[inline(always)]
+    #[track_caller]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.reallocate(ptr, old_layout, new_layout, || {
+            self.inner.grow(ptr, old_layout, new_layout)
+        })
+    }
+    #[inline(always)]
+    #[track_caller]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.reallocate(ptr, old_layout, new_layout, || {
+            self.inner.grow_zeroed(ptr, old_layout, new_layout)
+        })
+    }
+    #[inline(always)]
+    #[track_caller]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.reallocate(ptr, old_layout, new_layout, || {
+            self.inner.shrink(ptr, old_layout, new_layout)
+        })
+    }
+}
+
+impl<A> DeallocateAll for FreeCheck<A>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        let mut state = self.state();
+        self.inner.deallocate_all();
+        state.live.clear();
+        state.freed.clear()
+    }
+}
+
+unsafe impl<A> Owns for FreeCheck<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A> Trim for FreeCheck<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[test]
+fn free_check() {
+    let a = System.free_check(8);
+    let mut v = allocator_api2::vec::Vec::<u8, _>::new_in(&a);
+    v.extend(0..=255);
+    v.truncate(3);
+    v.shrink_to_fit();
+    drop(v);
+    let _ = Box::new_in(1u32, &a);
+}
+
+#[test]
+#[should_panic = "double free"]
+fn double_free() {
+    let a = System.free_check(8);
+    let layout = Layout::new::<u32>();
+    let ptr = a.allocate(layout).unwrap().cast();
+    unsafe { a.deallocate(ptr, layout) };
+    unsafe { a.deallocate(ptr, layout) };
+}
+
+#[test]
+#[should_panic = "never allocated"]
+fn invalid_free() {
+    let a = System.free_check(8);
+    let mut it = 1u32;
+    unsafe { a.deallocate(NonNull::from(&mut it).cast(), Layout::new::<u32>()) };
+}
+
+#[test]
+#[should_panic = "but allocated with"]
+fn wrong_layout() {
+    let a = System.free_check(8);
+    let ptr = a.allocate(Layout::new::<u32>()).unwrap().cast();
+    unsafe { a.deallocate(ptr, Layout::new::<u64>()) };
+}
+
+#[test]
+#[should_panic = "double free"]
+fn failed_resize() {
+    use core::cell::{Cell, OnceCell};
+    /// Frees `other` through the [`FreeCheck`] while failing to grow.
+    struct Inner<'a> {
+        outer: &'a OnceCell<&'a FreeCheck<Inner<'a>>>,
+        other: Cell<Option<NonNull<u8>>>,
+    }
+    unsafe impl Allocator for Inner<'_> {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            System.allocate(layout)
+        }
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            System.deallocate(ptr, layout)
+        }
+        unsafe fn grow(
+            &self,
+            _: NonNull<u8>,
+            _: Layout,
+            _: Layout,
+        ) -> Result<NonNull<[u8]>, AllocError> {
+            let other = self.other.take().unwrap();
+            self.outer
+                .get()
+                .unwrap()
+                .deallocate(other, Layout::new::<u32>());
+            Err(AllocError)
+        }
+    }
+    let outer = OnceCell::new();
+    let a = FreeCheck::new(
+        Inner {
+            outer: &outer,
+            other: Cell::new(None),
+        },
+        8,
+    );
+    let _ = outer.set(&a);
+    let layout = Layout::new::<u32>();
+    let [ptr, other] = [(); 2].map(|()| a.allocate(layout).unwrap().cast());
+    a.inner.other.set(Some(other));
+    unsafe {
+        a.grow(ptr, layout, Layout::new::<u64>()).unwrap_err();
+        a.deallocate(ptr, layout);
+        a.deallocate(other, layout);
+    }
+}
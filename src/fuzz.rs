@@ -0,0 +1,219 @@
+use crate::prelude::*;
+use alloc::vec::Vec;
+use arbitrary::{Arbitrary, Result, Unstructured};
+use core::slice;
+
+/// A [`Layout`] which implements [`Arbitrary`],
+/// with an alignment of up to 4KiB and a size of up to 64KiB, biased towards small sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ArbitraryLayout(pub Layout);
+
+impl<'a> Arbitrary<'a> for ArbitraryLayout {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let align = 1 << u.int_in_range(0..=12)?;
+        let size = match u.ratio(7, 8)? {
+            true => u.int_in_range(0..=256)?,
+            false => u.int_in_range(0..=64 * 1024)?,
+        };
+        Ok(Self(Layout::from_size_align(size, align).unwrap()))
+    }
+}
+
+/// An operation performed by [`exercise`].
+///
+/// Each `index` is taken modulo the number of live allocations,
+/// and the operation is skipped if there are none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Op {
+    Allocate {
+        layout: Layout,
+        zeroed: bool,
+    },
+    Deallocate {
+        index: usize,
+    },
+    /// Grow by `by` bytes.
+    Grow {
+        index: usize,
+        by: usize,
+        zeroed: bool,
+    },
+    /// Shrink to at most `to` bytes.
+    Shrink {
+        index: usize,
+        to: usize,
+    },
+}
+
+impl<'a> Arbitrary<'a> for Op {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.choose_index(4)? {
+            0 => Op::Allocate {
+                layout: ArbitraryLayout::arbitrary(u)?.0,
+                zeroed: u.arbitrary()?,
+            },
+            1 => Op::Deallocate {
+                index: u.arbitrary()?,
+            },
+            2 => Op::Grow {
+                index: u.arbitrary()?,
+                by: ArbitraryLayout::arbitrary(u)?.0.size(),
+                zeroed: u.arbitrary()?,
+            },
+            _ => Op::Shrink {
+                index: u.arbitrary()?,
+                to: ArbitraryLayout::arbitrary(u)?.0.size(),
+            },
+        })
+    }
+}
+
+struct Live {
+    ptr: NonNull<u8>,
+    layout: Layout,
+    seed: u8,
+}
+
+impl Live {
+    unsafe fn bytes(&self) -> &[u8] {
+        slice::from_raw_parts(self.ptr.as_ptr(), self.layout.size())
+    }
+    unsafe fn fill(&self, from: usize) {
+        for ix in from..self.layout.size() {
+            self.ptr.add(ix).write(self.seed.wrapping_add(ix as u8))
+        }
+    }
+    unsafe fn check(&self, up_to: usize) {
+        for (ix, byte) in self.bytes().iter().enumerate().take(up_to) {
+            assert_eq!(
+                *byte,
+                self.seed.wrapping_add(ix as u8),
+                "byte {ix} of the allocation at {:p} with {:?} was corrupted",
+                self.ptr,
+                self.layout
+            )
+        }
+    }
+}
+
+/// Perform `ops` against `allocator`, [`panic`]king if it returns a misaligned or
+/// too-small block, or if an allocation's contents are not preserved.
+///
+/// Failed allocations are ignored,
+/// and outstanding allocations are deallocated at the end.
+///
+/// ```
+/// # use composable_allocators::*;
+/// let data = [0xAB; 1024]; // e.g from a fuzzer
+/// let mut u = arbitrary::Unstructured::new(&data);
+/// let ops = u.arbitrary_iter::<Op>().unwrap().map_while(Result::ok);
+/// exercise(Malloc.arena(4096).guard(1u8, 2u8), ops);
+/// ```
+pub fn exercise<A: Allocator>(allocator: A, ops: impl IntoIterator<Item = Op>) {
+    let mut live = Vec::<Live>::new();
+    let mut seed = 0u8;
+    for op in ops {
+        seed = seed.wrapping_add(1);
+        unsafe {
+            match op {
+                Op::Allocate { layout, zeroed } => {
+                    let res = match zeroed {
+                        true => allocator.allocate_zeroed(layout),
+                        false => allocator.allocate(layout),
+                    };
+                    let Ok(block) = res else { continue };
+                    let it = checked(block, layout, seed);
+                    if zeroed {
+                        assert!(it.bytes().iter().all(|it| *it == 0), "not zeroed");
+                    }
+                    it.fill(0);
+                    live.push(it);
+                }
+                Op::Deallocate { index } => {
+                    if live.is_empty() {
+                        continue;
+                    }
+                    let it = live.swap_remove(index % live.len());
+                    it.check(it.layout.size());
+                    allocator.deallocate(it.ptr, it.layout)
+                }
+                Op::Grow { index, by, zeroed } => {
+                    if live.is_empty() {
+                        continue;
+                    }
+                    let ix = index % live.len();
+                    let old = &live[ix];
+                    let Ok(new_layout) =
+                        Layout::from_size_align(old.layout.size() + by, old.layout.align())
+                    else {
+                        continue;
+                    };
+                    let res = match zeroed {
+                        true => allocator.grow_zeroed(old.ptr, old.layout, new_layout),
+                        false => allocator.grow(old.ptr, old.layout, new_layout),
+                    };
+                    let Ok(block) = res else { continue };
+                    let new = checked(block, new_layout, old.seed);
+                    new.check(old.layout.size());
+                    if zeroed {
+                        let tail = &new.bytes()[old.layout.size()..];
+                        assert!(tail.iter().all(|it| *it == 0), "not zeroed");
+                    }
+                    new.fill(old.layout.size());
+                    live[ix] = new;
+                }
+                Op::Shrink { index, to } => {
+                    if live.is_empty() {
+                        continue;
+                    }
+                    let ix = index % live.len();
+                    let old = &live[ix];
+                    let new_layout = Layout::from_size_align_unchecked(
+                        to.min(old.layout.size()),
+                        old.layout.align(),
+                    );
+                    let Ok(block) = allocator.shrink(old.ptr, old.layout, new_layout) else {
+                        continue;
+                    };
+                    let new = checked(block, new_layout, old.seed);
+                    new.check(new_layout.size());
+                    live[ix] = new;
+                }
+            }
+        }
+    }
+    for it in live {
+        unsafe {
+            it.check(it.layout.size());
+            allocator.deallocate(it.ptr, it.layout)
+        }
+    }
+}
+
+fn checked(block: NonNull<[u8]>, layout: Layout, seed: u8) -> Live {
+    let ptr = block.cast::<u8>();
+    assert_eq!(ptr.as_ptr() as usize % layout.align(), 0, "misaligned");
+    assert!(block.len() >= layout.size(), "too small");
+    Live { ptr, layout, seed }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn fuzz() {
+    use crate::rng::SplitMix;
+    let rng = SplitMix::new(0xDEADBEEF);
+    let data = (0..4096)
+        .flat_map(|_| rng.next().to_le_bytes())
+        .collect::<Vec<_>>();
+    let ops = || {
+        let mut u = Unstructured::new(&data);
+        (0..256)
+            .map(|_| Op::arbitrary(&mut u).unwrap())
+            .collect::<Vec<_>>()
+    };
+    exercise(Malloc, ops());
+    exercise(Malloc.arena(4096), ops());
+    exercise(Malloc.random_guard(1).guard(1u8, 2u16), ops());
+    exercise(Malloc.store_layout().shuffle(1, 3), ops());
+    exercise(Malloc.limit_size(64 * 1024).round_to(PowersOfTwo), ops());
+}
@@ -0,0 +1,200 @@
+use crate::prelude::*;
+use core::{alloc::GlobalAlloc, ptr};
+
+/// [`GlobalAlloc`] doesn't support zero-sized allocations.
+#[inline(always)]
+pub(crate) fn dangling(layout: Layout) -> NonNull<[u8]> {
+    let ptr = unsafe { NonNull::new_unchecked(ptr::without_provenance_mut(layout.align())) };
+    NonNull::slice_from_raw_parts(ptr, 0)
+}
+
+#[inline(always)]
+pub(crate) fn wrap(ptr: *mut u8, size: usize) -> Result<NonNull<[u8]>, AllocError> {
+    match NonNull::new(ptr) {
+        Some(it) => Ok(NonNull::slice_from_raw_parts(it, size)),
+        None => Err(AllocError),
+    }
+}
+
+/// An [`Allocator`] which uses a [`GlobalAlloc`] implementation,
+/// e.g a `#[global_allocator]` from another crate.
+/// See `Dlmalloc`, with the `dlmalloc` feature, for a general purpose allocator without any C dependencies.
+///
+/// Zero-sized allocations don't reach `G`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct FromGlobal<G> {
+    pub inner: G,
+}
+
+impl<G> FromGlobal<G> {
+    pub const fn new(inner: G) -> Self {
+        Self { inner }
+    }
+}
+
+unsafe impl<G> Allocator for FromGlobal<G>
+where
+    G: GlobalAlloc,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        match layout.size() {
+            0 => Ok(dangling(layout)),
+            size => wrap(unsafe { self.inner.alloc(layout) }, size),
+        }
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            self.inner.dealloc(ptr.as_ptr(), layout)
+        }
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        match layout.size() {
+            0 => Ok(dangling(layout)),
+            size => wrap(unsafe { self.inner.alloc_zeroed(layout) }, size),
+        }
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        match old_layout.size() != 0 && old_layout.align() == new_layout.align() {
+            true => wrap(
+                self.inner
+                    .realloc(ptr.as_ptr(), old_layout, new_layout.size()),
+                new_layout.size(),
+            ),
+            false => crate::segregate::relocate(self, self, ptr, old_layout, new_layout, false),
+        }
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        match new_layout.size() != 0 && old_layout.align() == new_layout.align() {
+            true => wrap(
+                self.inner
+                    .realloc(ptr.as_ptr(), old_layout, new_layout.size()),
+                new_layout.size(),
+            ),
+            false => crate::segregate::relocate(self, self, ptr, old_layout, new_layout, false),
+        }
+    }
+}
+
+/// A [`GlobalAlloc`] which uses an [`Allocator`], e.g to install a composition with
+/// [`#[global_allocator]`](https://doc.rust-lang.org/std/alloc/index.html#the-global_allocator-attribute).
+///
+/// See [`composable_global`](crate::composable_global).
+///
+/// `A` must not use the global allocator itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ToGlobal<A> {
+    pub inner: A,
+}
+
+impl<A> ToGlobal<A> {
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+unsafe impl<A> GlobalAlloc for ToGlobal<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.inner
+            .allocate(layout)
+            .map_or(ptr::null_mut(), |it| it.as_ptr().cast())
+    }
+    #[inline(always)]
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.deallocate(NonNull::new_unchecked(ptr), layout)
+    }
+    #[inline(always)]
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.inner
+            .allocate_zeroed(layout)
+            .map_or(ptr::null_mut(), |it| it.as_ptr().cast())
+    }
+    #[inline(always)]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let ptr = NonNull::new_unchecked(ptr);
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let res = match new_size >= layout.size() {
+            true => self.inner.grow(ptr, layout, new_layout),
+            false => self.inner.shrink(ptr, layout, new_layout),
+        };
+        res.map_or(ptr::null_mut(), |it| it.as_ptr().cast())
+    }
+}
+
+/// Declare a `static` composition, and install it as the
+/// [`#[global_allocator]`](https://doc.rust-lang.org/std/alloc/index.html#the-global_allocator-attribute)
+/// through [`ToGlobal`](crate::ToGlobal).
+///
+/// The composition must be `Sync` and constructible in a `const` context,
+/// and must not use the global allocator itself.
+/// The `static` is a [`ToGlobal`](crate::ToGlobal), so reach the composition through its `inner` field:
+/// ```
+/// use composable_allocators::*;
+///
+/// composable_global!(static GLOBAL: Stats<Malloc> = Stats::new(Malloc));
+///
+/// fn main() {
+///     let before = GLOBAL.inner.snapshot();
+///     let v = vec![0u8; 1024];
+///     assert!(GLOBAL.inner.snapshot().live >= before.live + 1024);
+///     drop(v);
+/// }
+/// ```
+#[macro_export]
+macro_rules! composable_global {
+    ($(#[$attr:meta])* $vis:vis static $name:ident: $ty:ty = $init:expr $(;)?) => {
+        $(#[$attr])*
+        #[global_allocator]
+        $vis static $name: $crate::ToGlobal<$ty> = $crate::ToGlobal::new($init);
+    };
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn from_global() {
+    let a = FromGlobal::new(std::alloc::System);
+    let _ = Box::new_in(1, &a);
+    let _ = Box::new_in((), &a);
+    let mut v = allocator_api2::vec::Vec::new_in(&a);
+    v.extend_from_slice(&[1u8; 1024]);
+    v.truncate(1);
+    v.shrink_to_fit();
+    assert_eq!(v, [1]);
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn to_global() {
+    let a = ToGlobal::new(Malloc.stats());
+    let layout = Layout::from_size_align(3, 64).unwrap();
+    unsafe {
+        let p = a.alloc_zeroed(layout);
+        assert_eq!(p.cast::<[u8; 3]>().read(), [0; 3]);
+        assert_eq!(p as usize % 64, 0);
+        let p = a.realloc(p, layout, 128);
+        assert_eq!(p as usize % 64, 0);
+        a.dealloc(p, Layout::from_size_align(128, 64).unwrap());
+        assert!(a
+            .alloc(Layout::from_size_align(1 << 62, 1).unwrap())
+            .is_null());
+    }
+    assert_eq!(a.inner.snapshot().live, 0);
+}
@@ -0,0 +1,228 @@
+use crate::prelude::*;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// An [`Allocator`] which tracks the peak number of live bytes in `A`,
+/// calling [`Self::callback`] with `(threshold, live)` the first time the
+/// peak reaches each of [`Self::thresholds`].
+///
+/// This allows callers to apply backpressure before a [`SizeLimit`] starts failing:
+/// ```
+/// # use composable_allocators::*;
+/// # use core::sync::atomic::{AtomicUsize, Ordering};
+/// let budget = 1024;
+/// let warnings = AtomicUsize::new(0);
+/// let a = Malloc
+///     .limit_size(budget)
+///     .high_water([budget / 4 * 3, budget / 10 * 9], |_threshold, _live| {
+///         warnings.fetch_add(1, Ordering::Relaxed);
+///     });
+/// let _ = allocator_api2::boxed::Box::new_in([0u8; 800], &a);
+/// assert_eq!(warnings.load(Ordering::Relaxed), 1);
+/// ```
+///
+/// `thresholds` must be sorted in ascending order.
+/// Callbacks are called on the allocating thread, so must not allocate through this allocator.
+#[derive(Debug)]
+pub struct HighWater<A, T, F> {
+    pub inner: A,
+    pub thresholds: T,
+    pub callback: F,
+    live: AtomicUsize,
+    peak: AtomicUsize,
+    crossed: AtomicUsize,
+}
+
+impl<A, T, F> HighWater<A, T, F> {
+    pub const fn new(inner: A, thresholds: T, callback: F) -> Self {
+        Self {
+            inner,
+            thresholds,
+            callback,
+            live: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            crossed: AtomicUsize::new(0),
+        }
+    }
+    /// Bytes currently allocated.
+    pub fn live(&self) -> usize {
+        self.live.load(Ordering::Acquire)
+    }
+    /// The maximum of [`Self::live`].
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::Acquire)
+    }
+    /// The number of [`Self::thresholds`] that [`Self::peak`] has reached.
+    pub fn crossed(&self) -> usize {
+        self.crossed.load(Ordering::Acquire)
+    }
+    #[inline(always)]
+    fn refund(&self, size: usize) {
+        self.live.fetch_sub(size, Ordering::AcqRel);
+    }
+}
+
+impl<A, T, F> HighWater<A, T, F>
+where
+    T: AsRef<[usize]>,
+    F: Fn(usize, usize),
+{
+    #[inline(always)]
+    fn charge(&self, size: usize) {
+        let live = self.live.fetch_add(size, Ordering::AcqRel) + size;
+        if self.peak.fetch_max(live, Ordering::AcqRel) >= live {
+            return;
+        }
+        let thresholds = self.thresholds.as_ref();
+        let mut crossed = self.crossed.load(Ordering::Acquire);
+        while let Some(&threshold) = thresholds.get(crossed) {
+            if threshold > live {
+                break;
+            }
+            // Whoever wins the race reports the threshold, exactly once.
+            match self.crossed.compare_exchange(
+                crossed,
+                crossed + 1,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => {
+                    (self.callback)(threshold, live);
+                    crossed += 1
+                }
+                Err(actual) => crossed = actual,
+            }
+        }
+    }
+    #[inline(always)]
+    fn allocated(
+        &self,
+        layout: Layout,
+        res: Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if res.is_ok() {
+            self.charge(layout.size())
+        }
+        res
+    }
+    #[inline(always)]
+    fn reallocated(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        res: Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if res.is_ok() {
+            match new_layout.size() >= old_layout.size() {
+                true => self.charge(new_layout.size() - old_layout.size()),
+                false => self.refund(old_layout.size() - new_layout.size()),
+            }
+        }
+        res
+    }
+}
+
+unsafe impl<A, T, F> Allocator for HighWater<A, T, F>
+where
+    A: Allocator,
+    T: AsRef<[usize]>,
+    F: Fn(usize, usize),
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocated(layout, self.inner.allocate(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.refund(layout.size());
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocated(layout, self.inner.allocate_zeroed(layout))
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.grow(ptr, old_layout, new_layout);
+        self.reallocated(old_layout, new_layout, res)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.grow_zeroed(ptr, old_layout, new_layout);
+        self.reallocated(old_layout, new_layout, res)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.shrink(ptr, old_layout, new_layout);
+        self.reallocated(old_layout, new_layout, res)
+    }
+}
+
+/// Thresholds that have already been reported are not reported again.
+impl<A, T, F> DeallocateAll for HighWater<A, T, F>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all();
+        self.live.store(0, Ordering::Release)
+    }
+}
+
+unsafe impl<A, T, F> Owns for HighWater<A, T, F>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A, T, F> Trim for HighWater<A, T, F>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn high_water() {
+    use core::cell::RefCell;
+    let reported = RefCell::new(allocator_api2::vec::Vec::new());
+    let a = Malloc.high_water([4, 8, 16], |threshold, live| {
+        reported.borrow_mut().push((threshold, live))
+    });
+    let first = Box::new_in(1u32, &a);
+    drop(first);
+    let mut v = allocator_api2::vec::Vec::<u8, _>::with_capacity_in(4, &a);
+    v.extend([0; 4]);
+    v.try_reserve_exact(4).unwrap();
+    v.shrink_to_fit();
+    v.extend([0; 12]);
+    assert_eq!(a.live(), 16);
+    assert_eq!(a.peak(), 16);
+    assert_eq!(a.crossed(), 3);
+    drop(v);
+    assert_eq!(a.live(), 0);
+    assert_eq!(&*reported.borrow(), &[(4, 4), (8, 8), (16, 16)]);
+}
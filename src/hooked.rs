@@ -0,0 +1,212 @@
+use crate::prelude::*;
+
+/// Passed to the callback in [`Hooked`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Event {
+    /// A call to [`Allocator::allocate`] or [`Allocator::allocate_zeroed`] succeeded.
+    Allocated { ptr: NonNull<[u8]>, layout: Layout },
+    /// A call to [`Allocator::grow`], [`Allocator::grow_zeroed`] or [`Allocator::shrink`] succeeded.
+    Reallocated {
+        old_ptr: NonNull<u8>,
+        old_layout: Layout,
+        ptr: NonNull<[u8]>,
+        layout: Layout,
+    },
+    /// Any allocating call failed.
+    Failed { layout: Layout },
+    /// [`Allocator::deallocate`] is about to be called.
+    Deallocated { ptr: NonNull<u8>, layout: Layout },
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Event {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        match *self {
+            Event::Allocated { ptr, layout } => defmt::write!(
+                f,
+                "Allocated {{ ptr: {=usize:#x}, size: {=usize}, align: {=usize} }}",
+                ptr.cast::<u8>().as_ptr() as usize,
+                layout.size(),
+                layout.align()
+            ),
+            Event::Reallocated {
+                old_ptr,
+                old_layout,
+                ptr,
+                layout,
+            } => defmt::write!(
+                f,
+                "Reallocated {{ old_ptr: {=usize:#x}, old_size: {=usize}, ptr: {=usize:#x}, size: {=usize}, align: {=usize} }}",
+                old_ptr.as_ptr() as usize,
+                old_layout.size(),
+                ptr.cast::<u8>().as_ptr() as usize,
+                layout.size(),
+                layout.align()
+            ),
+            Event::Failed { layout } => defmt::write!(
+                f,
+                "Failed {{ size: {=usize}, align: {=usize} }}",
+                layout.size(),
+                layout.align()
+            ),
+            Event::Deallocated { ptr, layout } => defmt::write!(
+                f,
+                "Deallocated {{ ptr: {=usize:#x}, size: {=usize}, align: {=usize} }}",
+                ptr.as_ptr() as usize,
+                layout.size(),
+                layout.align()
+            ),
+        }
+    }
+}
+
+/// An [`Allocator`] which calls [`Self::hook`] with an [`Event`] for every call to `A`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Hooked<A, F> {
+    pub inner: A,
+    pub hook: F,
+}
+
+impl<A, F> Hooked<A, F> {
+    pub const fn new(inner: A, hook: F) -> Self {
+        Self { inner, hook }
+    }
+}
+
+impl<A, F> Hooked<A, F>
+where
+    F: Fn(Event),
+{
+    #[inline(always)]
+    fn allocated(
+        &self,
+        layout: Layout,
+        res: Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        (self.hook)(match res {
+            Ok(ptr) => Event::Allocated { ptr, layout },
+            Err(AllocError) => Event::Failed { layout },
+        });
+        res
+    }
+    #[inline(always)]
+    fn reallocated(
+        &self,
+        old_ptr: NonNull<u8>,
+        old_layout: Layout,
+        layout: Layout,
+        res: Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        (self.hook)(match res {
+            Ok(ptr) => Event::Reallocated {
+                old_ptr,
+                old_layout,
+                ptr,
+                layout,
+            },
+            Err(AllocError) => Event::Failed { layout },
+        });
+        res
+    }
+}
+
+unsafe impl<A, F> Allocator for Hooked<A, F>
+where
+    A: Allocator,
+    F: Fn(Event),
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocated(layout, self.inner.allocate(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        (self.hook)(Event::Deallocated { ptr, layout });
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocated(layout, self.inner.allocate_zeroed(layout))
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.grow(ptr, old_layout, new_layout);
+        self.reallocated(ptr, old_layout, new_layout, res)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.grow_zeroed(ptr, old_layout, new_layout);
+        self.reallocated(ptr, old_layout, new_layout, res)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.shrink(ptr, old_layout, new_layout);
+        self.reallocated(ptr, old_layout, new_layout, res)
+    }
+}
+
+unsafe impl<A, F> AllocatesZeroed for Hooked<A, F>
+where
+    A: AllocatesZeroed,
+    F: Fn(Event),
+{
+}
+
+unsafe impl<A, F> Owns for Hooked<A, F>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A, F> UsableSize for Hooked<A, F>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A, F> Trim for Hooked<A, F>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn hooked() {
+    use core::cell::Cell;
+    let events = Cell::new(0);
+    let a = Malloc
+        .limit_count(1)
+        .hooked(|_| events.set(events.get() + 1));
+    let occupied = Box::new_in(1, &a);
+    Box::try_new_in(1, &a).unwrap_err();
+    drop(occupied);
+    assert_eq!(events.get(), 3);
+}
@@ -0,0 +1,109 @@
+use crate::prelude::*;
+use core::{
+    cell::{Cell, UnsafeCell},
+    mem::MaybeUninit,
+};
+
+/// An [`Allocator`] which bump-allocates out of an internal buffer of `N` bytes.
+///
+/// Deallocating the most recent allocation reuses its space.
+///
+/// Allocations point into the [`Inline`] itself,
+/// so only `&Inline<N>` is an [`Allocator`], which keeps it from moving while any are live:
+/// ```
+/// # use composable_allocators::{AllocatorExt as _, Inline, Null};
+/// # use allocator_api2::boxed::Box;
+/// let inline = Inline::<256>::new();
+/// let alloc = (&inline).or(Null);
+/// let _ = Box::new_in(1, &alloc);
+/// ```
+/// ```compile_fail
+/// # use composable_allocators::Inline;
+/// # use allocator_api2::boxed::Box;
+/// let _ = Box::new_in(1, Inline::<256>::new());
+/// ```
+/// Use a [`Region`](crate::Region) to own an allocator over a buffer, e.g in a [`Locked`](crate::Locked).
+#[derive(Debug)]
+pub struct Inline<const N: usize> {
+    buf: UnsafeCell<[MaybeUninit<u8>; N]>,
+    cursor: Cell<usize>,
+}
+
+impl<const N: usize> Inline<N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: UnsafeCell::new([MaybeUninit::uninit(); N]),
+            cursor: Cell::new(0),
+        }
+    }
+    #[inline(always)]
+    fn start(&self) -> *mut u8 {
+        self.buf.get().cast::<u8>()
+    }
+}
+
+impl<const N: usize> Default for Inline<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl<const N: usize> Allocator for &Inline<N> {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let cursor = self.cursor.get();
+        let padding = self
+            .start()
+            .wrapping_add(cursor)
+            .align_offset(layout.align());
+        let offset = cursor.checked_add(padding).ok_or(AllocError)?;
+        let end = offset.checked_add(layout.size()).ok_or(AllocError)?;
+        if end > N {
+            return Err(AllocError);
+        }
+        self.cursor.set(end);
+        let ptr = unsafe { NonNull::new_unchecked(self.start().add(offset)) };
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let offset = ptr.as_ptr() as usize - self.start() as usize;
+        if offset + layout.size() == self.cursor.get() {
+            self.cursor.set(offset)
+        }
+    }
+}
+
+impl<const N: usize> DeallocateAll for &Inline<N> {
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.cursor.set(0)
+    }
+}
+
+unsafe impl<const N: usize> Owns for &Inline<N> {
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        let start = self.start() as usize;
+        let ptr = ptr.as_ptr() as usize;
+        start <= ptr && ptr.saturating_add(layout.size()) <= start + N
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn inline() {
+    let inline = Inline::<4>::new();
+    let a = (&inline).or(Malloc);
+    let small = Box::new_in([1u8; 4], &a);
+    let big = Box::new_in([1u8; 4], &a);
+    assert!(a
+        .primary
+        .owns(NonNull::from(&*small).cast(), Layout::new::<[u8; 4]>()));
+    assert!(!a
+        .primary
+        .owns(NonNull::from(&*big).cast(), Layout::new::<[u8; 4]>()));
+    drop(big);
+    drop(small);
+    Box::try_new_in(1u32, &Inline::<2>::new()).unwrap_err();
+}
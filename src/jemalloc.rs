@@ -1,38 +1,688 @@
 use crate::prelude::*;
-use core::{cmp, ffi::c_void, mem, ptr};
+use core::{
+    cmp,
+    ffi::{c_int, c_uint, c_void, CStr},
+    fmt, hash, mem, ptr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 /// An allocator using [`jemalloc`](https://jemalloc.net/).
+///
+/// See [`Self::in_arena`] to isolate allocations in a dedicated arena.
+///
+/// This doesn't implement [`Owns`], since jemalloc's pointer lookups
+/// (e.g `arenas.lookup`) crash on memory it has never mapped.
+/// Use [`Self::create_reserved_arena`] instead.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Jemalloc;
 
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// The types of [`mallctl`](https://jemalloc.net/jemalloc.3.html#mallctl_namespace) controls,
+/// see [`Jemalloc::mallctl_read`].
+pub trait MallctlValue: sealed::Sealed + Copy {}
+
+macro_rules! mallctl_value {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl sealed::Sealed for $ty {}
+            impl MallctlValue for $ty {}
+        )*
+    };
+}
+mallctl_value!(bool, u32, u64, usize, isize);
+
+/// A failed call to `mallctl`, with its error number.
+///
+/// e.g `ENOENT` if the control doesn't exist, or `EINVAL` if it has a different type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MallctlError(pub c_int);
+
+impl fmt::Display for MallctlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "mallctl failed with error {}", self.0)
+    }
+}
+
+impl Jemalloc {
+    /// Read the control `name`, e.g `c"opt.narenas"`.
+    pub fn mallctl_read<T: MallctlValue>(name: &CStr) -> Result<T, MallctlError> {
+        let mut value = mem::MaybeUninit::<T>::uninit();
+        let mut len = mem::size_of::<T>();
+        match unsafe {
+            tikv_jemalloc_sys::mallctl(
+                name.as_ptr(),
+                value.as_mut_ptr().cast(),
+                &mut len,
+                ptr::null_mut(),
+                0,
+            )
+        } {
+            // jemalloc checks the size, so the whole value was written
+            0 => Ok(unsafe { value.assume_init() }),
+            errno => Err(MallctlError(errno)),
+        }
+    }
+    /// Write the control `name`, e.g `c"arena.0.decay"`.
+    pub fn mallctl_write<T: MallctlValue>(name: &CStr, mut value: T) -> Result<(), MallctlError> {
+        match unsafe {
+            tikv_jemalloc_sys::mallctl(
+                name.as_ptr(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::from_mut(&mut value).cast(),
+                mem::size_of::<T>(),
+            )
+        } {
+            0 => Ok(()),
+            errno => Err(MallctlError(errno)),
+        }
+    }
+    /// Create a new arena, returning its index for [`Self::in_arena`].
+    pub fn create_arena() -> Result<u32, MallctlError> {
+        Self::mallctl_read(c"arenas.create")
+    }
+    /// Create a new arena which takes all of its memory from a single mapping of `len` bytes,
+    /// so that [`Owns`] is a cheap range check.
+    ///
+    /// The address space is reserved up front, but only committed as it is used,
+    /// and allocations fail once it is exhausted, e.g to fall back in an [`Or`].
+    /// Purged memory is returned to the OS, but the reservation is never unmapped.
+    ///
+    /// Fails with `ENOMEM` if the mapping fails.
+    pub fn create_reserved_arena(len: usize) -> Result<JemallocReserved, MallctlError> {
+        let reservation = Reservation::map(len).ok_or(MallctlError(libc::ENOMEM))?;
+        let mut hooks = ptr::from_ref(&reservation.hooks).cast_mut();
+        let mut index = 0u32;
+        let mut len = mem::size_of::<u32>();
+        match unsafe {
+            tikv_jemalloc_sys::mallctl(
+                c"arenas.create".as_ptr(),
+                ptr::from_mut(&mut index).cast(),
+                &mut len,
+                ptr::from_mut(&mut hooks).cast(),
+                mem::size_of::<*mut tikv_jemalloc_sys::extent_hooks_t>(),
+            )
+        } {
+            0 => Ok(JemallocReserved {
+                arena: Self::in_arena(index),
+                reservation,
+            }),
+            errno => Err(MallctlError(errno)),
+        }
+    }
+    /// An [`Allocator`] which allocates from the arena with the given `index`,
+    /// e.g from [`Self::create_arena`].
+    pub const fn in_arena(index: u32) -> JemallocArena {
+        JemallocArena { index }
+    }
+}
+
 unsafe impl Allocator for Jemalloc {
     #[inline(always)]
     fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
-        let mut memptr = ptr::null_mut::<c_void>();
-        match unsafe {
-            tikv_jemalloc_sys::posix_memalign(
-                &mut memptr,
-                cmp::max(layout.align(), mem::size_of::<usize>()),
-                layout.size(),
+        mallocx(layout, flags(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        sdallocx(ptr, layout, flags(layout))
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        mallocx(layout, flags(layout) | tikv_jemalloc_sys::MALLOCX_ZERO)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        resize(ptr, old_layout, new_layout, flags(new_layout), false)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        resize(ptr, old_layout, new_layout, flags(new_layout), true)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        resize(ptr, old_layout, new_layout, flags(new_layout), false)
+    }
+}
+
+/// The alignment that jemalloc guarantees for allocations at least this large.
+const MIN_ALIGN: usize = 2 * mem::size_of::<usize>();
+
+/// Only ask for an alignment if jemalloc wouldn't provide it anyway,
+/// which keeps allocations on the fast path.
+#[inline(always)]
+fn flags(layout: Layout) -> c_int {
+    match layout.align() <= MIN_ALIGN && layout.align() <= layout.size() {
+        true => 0,
+        false => tikv_jemalloc_sys::MALLOCX_ALIGN(layout.align()),
+    }
+}
+
+/// Zero-sized allocations are undefined behaviour.
+#[inline(always)]
+fn size(layout: Layout) -> usize {
+    cmp::max(layout.size(), 1)
+}
+
+#[inline(always)]
+fn mallocx(layout: Layout, flags: c_int) -> Result<NonNull<[u8]>, AllocError> {
+    match NonNull::new(unsafe { tikv_jemalloc_sys::mallocx(size(layout), flags) }) {
+        Some(it) => Ok(NonNull::slice_from_raw_parts(
+            it.cast::<u8>(),
+            layout.size(),
+        )),
+        None => Err(AllocError),
+    }
+}
+
+/// # Safety
+/// - `ptr` must have been allocated with `layout` and `flags`.
+#[inline(always)]
+unsafe fn sdallocx(ptr: NonNull<u8>, layout: Layout, flags: c_int) {
+    tikv_jemalloc_sys::sdallocx(ptr.as_ptr().cast::<c_void>(), size(layout), flags)
+}
+
+/// Try and resize in place with `xallocx`, falling back to moving with `rallocx`.
+///
+/// # Safety
+/// - As for [`Allocator::grow`] or [`Allocator::shrink`].
+/// - `flags` must be the same as those used to allocate `ptr`,
+///   except for the alignment, which is for `new_layout`.
+#[inline(always)]
+unsafe fn resize(
+    ptr: NonNull<u8>,
+    old_layout: Layout,
+    new_layout: Layout,
+    flags: c_int,
+    zeroed: bool,
+) -> Result<NonNull<[u8]>, AllocError> {
+    let size = size(new_layout);
+    // sized deallocation requires the size class to match the new size exactly
+    let in_place = (ptr.as_ptr() as usize).is_multiple_of(new_layout.align())
+        && tikv_jemalloc_sys::xallocx(ptr.as_ptr().cast::<c_void>(), size, 0, flags)
+            == tikv_jemalloc_sys::nallocx(size, flags);
+    let new = match in_place {
+        true => ptr,
+        false => NonNull::new(
+            tikv_jemalloc_sys::rallocx(ptr.as_ptr().cast::<c_void>(), size, flags).cast::<u8>(),
+        )
+        .ok_or(AllocError)?,
+    };
+    if zeroed && new_layout.size() > old_layout.size() {
+        ptr::write_bytes(
+            new.as_ptr().add(old_layout.size()),
+            0,
+            new_layout.size() - old_layout.size(),
+        );
+    }
+    Ok(NonNull::slice_from_raw_parts(new, new_layout.size()))
+}
+
+impl UsableSize for Jemalloc {
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, _: Layout) -> usize {
+        tikv_jemalloc_sys::malloc_usable_size(ptr.as_ptr().cast::<c_void>())
+    }
+}
+
+/// Statistics are only available with the `jemalloc-stats` feature.
+impl BackendStats for Jemalloc {
+    fn allocated(&self) -> Option<usize> {
+        refresh();
+        Jemalloc::mallctl_read(c"stats.allocated").ok()
+    }
+    fn resident(&self) -> Option<usize> {
+        refresh();
+        Jemalloc::mallctl_read(c"stats.resident").ok()
+    }
+    fn mapped(&self) -> Option<usize> {
+        refresh();
+        Jemalloc::mallctl_read(c"stats.mapped").ok()
+    }
+}
+
+/// Flushes the calling thread's cache, and purges every arena.
+impl Trim for Jemalloc {
+    fn trim(&self) {
+        unsafe {
+            tikv_jemalloc_sys::mallctl(
+                c"thread.tcache.flush".as_ptr(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                0,
             )
-        } {
-            0 => match NonNull::new(memptr.cast::<u8>()) {
-                Some(it) => Ok(NonNull::slice_from_raw_parts(it, layout.size())),
-                None => unreachable!(),
-            },
-            libc::EINVAL => unreachable!(),
-            libc::ENOMEM => Err(AllocError),
-            _undocumented => Err(AllocError),
+        };
+        purge(ARENAS_ALL)
+    }
+}
+
+/// `MALLCTL_ARENAS_ALL`, to address every arena.
+const ARENAS_ALL: usize = 4096;
+
+/// Return the unused dirty pages of the arena with the given `index` to the OS.
+fn purge(index: usize) {
+    let mut mib = [0; 3];
+    let mut len = mib.len();
+    unsafe {
+        if tikv_jemalloc_sys::mallctlnametomib(
+            c"arena.0.purge".as_ptr(),
+            mib.as_mut_ptr(),
+            &mut len,
+        ) == 0
+        {
+            mib[1] = index;
+            tikv_jemalloc_sys::mallctlbymib(
+                mib.as_ptr(),
+                len,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                0,
+            );
         }
     }
+}
+
+/// Statistics are cached until the `epoch` control is written.
+fn refresh() {
+    let _ = Jemalloc::mallctl_write(c"epoch", 1u64);
+}
+
+/// A [`Jemalloc`] which allocates from a particular arena, see [`Jemalloc::in_arena`].
+///
+/// The thread cache is bypassed, so allocations don't come from other arenas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JemallocArena {
+    index: u32,
+}
+
+impl JemallocArena {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+    #[inline(always)]
+    fn flags(&self, layout: Layout) -> c_int {
+        tikv_jemalloc_sys::MALLOCX_ALIGN(layout.align())
+            | tikv_jemalloc_sys::MALLOCX_ARENA(self.index as usize)
+            | tikv_jemalloc_sys::MALLOCX_TCACHE_NONE
+    }
+}
+
+unsafe impl Allocator for JemallocArena {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        mallocx(layout, self.flags(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        sdallocx(ptr, layout, self.flags(layout))
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        mallocx(layout, self.flags(layout) | tikv_jemalloc_sys::MALLOCX_ZERO)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        resize(ptr, old_layout, new_layout, self.flags(new_layout), false)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        resize(ptr, old_layout, new_layout, self.flags(new_layout), true)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        resize(ptr, old_layout, new_layout, self.flags(new_layout), false)
+    }
+}
+
+impl Trim for JemallocArena {
+    fn trim(&self) {
+        purge(self.index as usize)
+    }
+}
+
+impl UsableSize for JemallocArena {
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        tikv_jemalloc_sys::sallocx(ptr.as_ptr().cast::<c_void>(), self.flags(layout))
+    }
+}
+
+/// A [`JemallocArena`] backed by a single mapping, see [`Jemalloc::create_reserved_arena`].
+#[derive(Debug, Clone, Copy)]
+pub struct JemallocReserved {
+    arena: JemallocArena,
+    reservation: &'static Reservation,
+}
+
+impl JemallocReserved {
+    pub fn arena(&self) -> JemallocArena {
+        self.arena
+    }
+    /// The size of the mapping.
+    pub fn len(&self) -> usize {
+        self.reservation.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.reservation.len == 0
+    }
+}
+
+impl PartialEq for JemallocReserved {
+    fn eq(&self, other: &Self) -> bool {
+        self.arena == other.arena
+    }
+}
+
+impl Eq for JemallocReserved {}
+
+impl hash::Hash for JemallocReserved {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.arena.hash(state)
+    }
+}
+
+unsafe impl Allocator for JemallocReserved {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.arena.allocate(layout)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.arena.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.arena.allocate_zeroed(layout)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.arena.grow(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.arena.grow_zeroed(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.arena.shrink(ptr, old_layout, new_layout)
+    }
+}
 
+unsafe impl Owns for JemallocReserved {
     #[inline(always)]
-    unsafe fn deallocate(&self, ptr: NonNull<u8>, _: Layout) {
-        tikv_jemalloc_sys::free(ptr.as_ptr().cast::<c_void>())
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        // only the arena allocates from the reservation
+        let base = ptr::from_ref(self.reservation) as usize;
+        let ptr = ptr.as_ptr() as usize;
+        base + mem::size_of::<Reservation>() <= ptr
+            && ptr.saturating_add(layout.size()) <= base + self.reservation.len
+    }
+}
+
+impl UsableSize for JemallocReserved {
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.arena.usable_size(ptr, layout)
+    }
+}
+
+impl Trim for JemallocReserved {
+    fn trim(&self) {
+        self.arena.trim()
+    }
+}
+
+/// Extent hooks which bump-allocate out of one mapping,
+/// stored at the start of that mapping.
+#[repr(C)]
+struct Reservation {
+    /// First, so that jemalloc's pointer to the hooks is a pointer to the whole.
+    hooks: tikv_jemalloc_sys::extent_hooks_t,
+    len: usize,
+    /// The offset of the first unused byte.
+    cursor: AtomicUsize,
+}
+
+impl fmt::Debug for Reservation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reservation")
+            .field("base", &ptr::from_ref(self))
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Reservation {
+    fn map(len: usize) -> Option<&'static Self> {
+        if len < mem::size_of::<Self>() {
+            return None;
+        }
+        let base = match unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_NORESERVE,
+                -1,
+                0,
+            )
+        } {
+            libc::MAP_FAILED => return None,
+            it => it.cast::<Self>(),
+        };
+        unsafe {
+            base.write(Self {
+                hooks: tikv_jemalloc_sys::extent_hooks_t {
+                    alloc: Some(Self::alloc),
+                    // opt out, so jemalloc retains extents instead
+                    dalloc: None,
+                    destroy: None,
+                    // the whole mapping is always committed
+                    commit: None,
+                    decommit: None,
+                    purge_lazy: None,
+                    purge_forced: Some(Self::purge_forced),
+                    split: Some(Self::split),
+                    merge: Some(Self::merge),
+                },
+                len,
+                cursor: AtomicUsize::new(mem::size_of::<Self>()),
+            });
+            Some(&*base)
+        }
+    }
+    unsafe extern "C" fn alloc(
+        hooks: *mut tikv_jemalloc_sys::extent_hooks_t,
+        new_addr: *mut c_void,
+        size: usize,
+        alignment: usize,
+        zero: *mut bool,
+        commit: *mut bool,
+        _: c_uint,
+    ) -> *mut c_void {
+        let this = &*hooks.cast::<Self>();
+        let base = hooks as usize;
+        let mut start = 0;
+        let res = this
+            .cursor
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |cursor| {
+                start = (base + cursor).checked_next_multiple_of(alignment)? - base;
+                let end = start.checked_add(size)?;
+                let fits =
+                    end <= this.len && (new_addr.is_null() || new_addr as usize == base + start);
+                fits.then_some(end)
+            });
+        match res {
+            Ok(_) => {
+                // fresh anonymous memory
+                *zero = true;
+                *commit = true;
+                (base + start) as *mut c_void
+            }
+            Err(_) => ptr::null_mut(),
+        }
+    }
+    unsafe extern "C" fn purge_forced(
+        _: *mut tikv_jemalloc_sys::extent_hooks_t,
+        addr: *mut c_void,
+        _: usize,
+        offset: usize,
+        length: usize,
+        _: c_uint,
+    ) -> bool {
+        // private anonymous pages read back as zero
+        libc::madvise(
+            addr.cast::<u8>().add(offset).cast(),
+            length,
+            libc::MADV_DONTNEED,
+        ) != 0
+    }
+    unsafe extern "C" fn split(
+        _: *mut tikv_jemalloc_sys::extent_hooks_t,
+        _: *mut c_void,
+        _: usize,
+        _: usize,
+        _: usize,
+        _: bool,
+        _: c_uint,
+    ) -> bool {
+        false
+    }
+    unsafe extern "C" fn merge(
+        _: *mut tikv_jemalloc_sys::extent_hooks_t,
+        _: *mut c_void,
+        _: usize,
+        _: *mut c_void,
+        _: usize,
+        _: bool,
+        _: c_uint,
+    ) -> bool {
+        false
     }
 }
 
 #[test]
 fn should_succeed() {
     let _ = Box::new_in(1, Jemalloc);
+    let layout = Layout::new::<[u8; 3]>();
+    let ptr = Jemalloc.allocate(layout).unwrap().cast();
+    assert!(unsafe { Jemalloc.usable_size(ptr, layout) } >= 3);
+    unsafe { Jemalloc.deallocate(ptr, layout) };
+}
+
+#[test]
+fn in_place() {
+    let mut v = allocator_api2::vec::Vec::<u8, _>::new_in(Jemalloc);
+    v.extend(0..=255);
+    v.truncate(3);
+    v.shrink_to_fit();
+    assert_eq!(v, [0, 1, 2]);
+    let layout = Layout::from_size_align(8, 64).unwrap();
+    let ptr = Jemalloc.allocate_zeroed(layout).unwrap().cast::<u8>();
+    let grown = Layout::from_size_align(1 << 20, 64).unwrap();
+    let ptr = unsafe { Jemalloc.grow_zeroed(ptr, layout, grown) }.unwrap();
+    assert_eq!(ptr.cast::<u8>().as_ptr() as usize % 64, 0);
+    assert!(unsafe { ptr.as_ref() }.iter().all(|it| *it == 0));
+    unsafe { Jemalloc.deallocate(ptr.cast(), grown) };
+}
+
+#[cfg(feature = "jemalloc-stats")]
+#[test]
+fn stats() {
+    let _it = Box::new_in([1u8; 4096], Jemalloc);
+    assert!(Jemalloc.allocated().unwrap() >= 4096);
+    assert!(Jemalloc.resident().unwrap() >= Jemalloc.allocated().unwrap());
+    assert!(Jemalloc.mapped().unwrap() >= Jemalloc.resident().unwrap());
+}
+
+#[test]
+fn mallctl() {
+    assert!(Jemalloc::mallctl_read::<u32>(c"arenas.narenas").unwrap() > 0);
+    assert_eq!(
+        Jemalloc::mallctl_read::<u64>(c"arenas.narenas"),
+        Err(MallctlError(libc::EINVAL))
+    );
+    assert_eq!(
+        Jemalloc::mallctl_read::<u32>(c"no.such.control"),
+        Err(MallctlError(libc::ENOENT))
+    );
+}
+
+#[test]
+fn arena() {
+    let a = Jemalloc::in_arena(Jemalloc::create_arena().unwrap());
+    let mut v = allocator_api2::vec::Vec::<u8, _>::new_in(a);
+    v.extend(0..=255);
+    let _ = Box::new_in(1u8, a);
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn reserved() {
+    let reserved = Jemalloc::create_reserved_arena(1 << 30).unwrap();
+    let a = Or {
+        primary: reserved,
+        fallback: Malloc,
+    };
+    let mut v = allocator_api2::vec::Vec::<u8, _>::new_in(a);
+    v.extend(0..=255);
+    let layout = Layout::array::<u8>(v.capacity()).unwrap();
+    assert!(reserved.owns(NonNull::new(v.as_mut_ptr()).unwrap(), layout));
+    let it = Box::new_in(1u64, Malloc);
+    assert!(!reserved.owns(NonNull::from(&*it).cast(), Layout::new::<u64>()));
+    let local = 0u64;
+    assert!(!reserved.owns(NonNull::from(&local).cast(), Layout::new::<u64>()));
+    // larger than the reservation
+    let big = Box::<[u8; 1 << 30], _>::try_new_uninit_in(a).unwrap();
+    assert!(!reserved.owns(NonNull::from(&*big).cast(), Layout::new::<[u8; 1 << 30]>()));
 }
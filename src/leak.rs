@@ -0,0 +1,360 @@
+use crate::{prelude::*, spin::lock};
+use core::{fmt, panic::Location};
+use std::{
+    backtrace::Backtrace,
+    collections::BTreeMap,
+    eprintln,
+    sync::{Arc, Mutex},
+    thread,
+    vec::Vec,
+};
+
+/// A live allocation recorded by [`LeakCheck`].
+#[derive(Debug, Clone)]
+pub struct Leak {
+    pub ptr: NonNull<u8>,
+    pub layout: Layout,
+    /// Where the allocating method was called.
+    ///
+    /// This is only the caller of [`LeakCheck`]'s own methods,
+    /// so for allocations through containers like `Box` and `Vec`,
+    /// or through `&LeakCheck`, it is somewhere in `allocator_api2`.
+    /// Set [`LeakCheck::backtraces`] to find the code responsible.
+    pub location: &'static Location<'static>,
+    /// Only captured if [`LeakCheck::backtraces`] is set.
+    pub backtrace: Option<Arc<Backtrace>>,
+}
+
+impl fmt::Display for Leak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            ptr,
+            layout,
+            location,
+            backtrace,
+        } = self;
+        write!(
+            f,
+            "{} bytes (align {}) at {ptr:p}, allocated at {location}",
+            layout.size(),
+            layout.align()
+        )?;
+        if let Some(backtrace) = backtrace {
+            write!(f, "\n{backtrace}")?;
+        }
+        Ok(())
+    }
+}
+
+/// What [`LeakCheck`] does with leaks when it is dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum OnDrop {
+    Ignore,
+    /// Print them to stderr.
+    Report,
+    /// [`panic`], unless the thread is already panicking.
+    #[default]
+    Panic,
+}
+
+/// An [`Allocator`] which records every live allocation,
+/// so that leaks can be reported with [`Self::leaks`] or [`Self::assert_no_leaks`],
+/// or when it is dropped.
+///
+/// Set [`Self::backtraces`] to capture a [`Backtrace`] for each allocation,
+/// since [`Leak::location`] rarely points at the leaking code.
+#[derive(Debug)]
+pub struct LeakCheck<A> {
+    pub inner: A,
+    pub backtraces: bool,
+    pub on_drop: OnDrop,
+    live: Mutex<BTreeMap<usize, Leak>>,
+}
+
+unsafe impl<A: Send> Send for LeakCheck<A> {}
+unsafe impl<A: Sync> Sync for LeakCheck<A> {}
+
+impl<A> LeakCheck<A> {
+    pub const fn new(inner: A) -> Self {
+        Self {
+            inner,
+            backtraces: false,
+            on_drop: OnDrop::Panic,
+            live: Mutex::new(BTreeMap::new()),
+        }
+    }
+    /// Every live allocation, in address order.
+    pub fn leaks(&self) -> Vec<Leak> {
+        self.live().values().cloned().collect()
+    }
+    /// [`panic`] if there are any live allocations, listing them.
+    #[track_caller]
+    pub fn assert_no_leaks(&self) {
+        let leaks = self.leaks();
+        if !leaks.is_empty() {
+            panic!("{}", Report(&leaks))
+        }
+    }
+    #[inline(always)]
+    fn live(&self) -> std::sync::MutexGuard<'_, BTreeMap<usize, Leak>> {
+        lock(&self.live)
+    }
+    #[inline(always)]
+    #[track_caller]
+    fn record(
+        &self,
+        res: Result<NonNull<[u8]>, AllocError>,
+        layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if let Ok(ptr) = res {
+            let ptr = ptr.cast::<u8>();
+            let leak = Leak {
+                ptr,
+                layout,
+                location: Location::caller(),
+                backtrace: self
+                    .backtraces
+                    .then(|| Arc::new(Backtrace::force_capture())),
+            };
+            self.live().insert(ptr.as_ptr() as usize, leak);
+        }
+        res
+    }
+    /// Forget `ptr` before `A` frees it in `resize`,
+    /// since another thread may then be given its address,
+    /// and remember it again if `resize` fails.
+    #[inline(always)]
+    #[track_caller]
+    fn reallocate(
+        &self,
+        ptr: NonNull<u8>,
+        new_layout: Layout,
+        resize: impl FnOnce() -> Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let addr = ptr.as_ptr() as usize;
+        let before = self.live().remove(&addr);
+        let res = resize();
+        if let (Err(AllocError), Some(before)) = (res, before) {
+            self.live().insert(addr, before);
+        }
+        self.record(res, new_layout)
+    }
+}
+
+pub(crate) struct Report<'a>(pub(crate) &'a [Leak]);
+
+impl fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} allocation(s) leaked", self.0.len())?;
+        for leak in self.0 {
+            write!(f, "\n- {leak}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<A> Drop for LeakCheck<A> {
+    fn drop(&mut self) {
+        let leaks = self.leaks();
+        if leaks.is_empty() {
+            return;
+        }
+        match self.on_drop {
+            OnDrop::Ignore => {}
+            OnDrop::Report => eprintln!("{}", Report(&leaks)),
+            OnDrop::Panic if thread::panicking() => {}
+            OnDrop::Panic => panic!("{}", Report(&leaks)),
+        }
+    }
+}
+
+unsafe impl<A> Allocator for LeakCheck<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    #[track_caller]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.record(self.inner.allocate(layout), layout)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.live().remove(&(ptr.as_ptr() as usize));
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    #[track_caller]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.record(self.inner.allocate_zeroed(layout), layout)
+    }
+    #[inline(always)]
+    #[track_caller]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.reallocate(ptr, new_layout, || {
+            self.inner.grow(ptr, old_layout, new_layout)
+        })
+    }
+    #[inline(always)]
+    #[track_caller]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.reallocate(ptr, new_layout, || {
+            self.inner.grow_zeroed(ptr, old_layout, new_layout)
+        })
+    }
+    #[inline(always)]
+    #[track_caller]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.reallocate(ptr, new_layout, || {
+            self.inner.shrink(ptr, old_layout, new_layout)
+        })
+    }
+}
+
+impl<A> DeallocateAll for LeakCheck<A>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        let mut live = self.live();
+        self.inner.deallocate_all();
+        live.clear()
+    }
+}
+
+unsafe impl<A> Owns for LeakCheck<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A> UsableSize for LeakCheck<A>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A> Trim for LeakCheck<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[test]
+fn leak_check() {
+    let a = System.leak_check();
+    let it = Box::new_in(1u32, &a);
+    let mut v = allocator_api2::vec::Vec::<u8, _>::new_in(&a);
+    v.extend(0..=255);
+    assert_eq!(a.leaks().len(), 2);
+    assert_eq!(a.leaks()[0].layout.size() + a.leaks()[1].layout.size(), 260);
+    drop((it, v));
+    a.assert_no_leaks();
+}
+
+#[test]
+#[should_panic = "1 allocation(s) leaked"]
+fn leak_check_on_drop() {
+    let mut a = System.leak_check();
+    a.backtraces = true;
+    core::mem::forget(Box::new_in(1u32, &a));
+}
+
+#[test]
+fn leak_check_locations() {
+    let mut a = System.leak_check();
+    a.backtraces = true;
+    let direct = a.allocate(Layout::new::<u8>()).unwrap();
+    let boxed = Box::new_in(1u32, &a);
+    let leaks = a.leaks();
+    let (direct_leak, boxed_leak) = match leaks[0].ptr == direct.cast() {
+        true => (&leaks[0], &leaks[1]),
+        false => (&leaks[1], &leaks[0]),
+    };
+    assert_eq!(direct_leak.location.file(), file!());
+    assert_ne!(boxed_leak.location.file(), file!());
+    let backtrace = std::format!("{}", boxed_leak.backtrace.as_ref().unwrap());
+    assert!(backtrace.contains("leak_check_locations"));
+    drop(boxed);
+    unsafe { a.deallocate(direct.cast(), Layout::new::<u8>()) };
+}
+
+#[test]
+fn reused_while_resizing() {
+    use core::cell::{Cell, OnceCell};
+    /// Moves on grow, handing the old block to an allocation through the [`LeakCheck`],
+    /// as another thread could once it's freed.
+    struct Inner<'a> {
+        outer: &'a OnceCell<&'a LeakCheck<Inner<'a>>>,
+        reuse: Cell<Option<NonNull<u8>>>,
+    }
+    unsafe impl Allocator for Inner<'_> {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            match self.reuse.take() {
+                Some(ptr) => Ok(NonNull::slice_from_raw_parts(ptr, layout.size())),
+                None => System.allocate(layout),
+            }
+        }
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            System.deallocate(ptr, layout)
+        }
+        unsafe fn grow(
+            &self,
+            ptr: NonNull<u8>,
+            old_layout: Layout,
+            new_layout: Layout,
+        ) -> Result<NonNull<[u8]>, AllocError> {
+            let new = System.allocate(new_layout)?;
+            core::ptr::copy_nonoverlapping(ptr.as_ptr(), new.cast().as_ptr(), old_layout.size());
+            self.reuse.set(Some(ptr));
+            self.outer.get().unwrap().allocate(old_layout)?;
+            Ok(new)
+        }
+    }
+    let outer = OnceCell::new();
+    // dropping would need `outer` to outlive it
+    let a = core::mem::ManuallyDrop::new(LeakCheck::new(Inner {
+        outer: &outer,
+        reuse: Cell::new(None),
+    }));
+    let _ = outer.set(&a);
+    let old = Layout::new::<u32>();
+    let new = Layout::new::<u64>();
+    let ptr = a.allocate(old).unwrap().cast::<u8>();
+    let grown = unsafe { a.grow(ptr, old, new) }.unwrap().cast::<u8>();
+    let leaks = a.leaks();
+    assert_eq!(leaks.len(), 2);
+    assert!(leaks.iter().any(|it| it.ptr == ptr && it.layout == old));
+    unsafe {
+        a.deallocate(ptr, old);
+        a.deallocate(grown, new);
+    }
+    a.assert_no_leaks();
+}
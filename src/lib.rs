@@ -1,4 +1,11 @@
 #![no_std]
+#![cfg_attr(feature = "nightly", feature(allocator_api))]
+#![cfg_attr(feature = "nightly", doc(test(attr(feature(allocator_api)))))]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
 use allocator_api2::alloc::Allocator;
 use core::{alloc::Layout, marker::PhantomData, ptr::NonNull};
@@ -10,22 +17,202 @@ pub use malloc::Malloc;
 #[cfg(feature = "jemalloc")]
 mod jemalloc;
 #[cfg(feature = "jemalloc")]
-pub use jemalloc::Jemalloc;
+pub use jemalloc::{Jemalloc, JemallocArena, JemallocReserved, MallctlError, MallctlValue};
 #[cfg(feature = "mimalloc")]
 mod mimalloc;
 #[cfg(feature = "mimalloc")]
 pub use mimalloc::Mimalloc;
+#[cfg(feature = "dlmalloc")]
+mod dlmalloc;
+#[cfg(feature = "dlmalloc")]
+pub use dlmalloc::Dlmalloc;
+#[cfg(feature = "linked_list_allocator")]
+mod linked_list;
+#[cfg(feature = "linked_list_allocator")]
+pub use linked_list::{LinkedListHeap, LockedLinkedListHeap};
+#[cfg(all(feature = "pages", any(unix, windows)))]
+mod pages;
+#[cfg(all(feature = "pages", any(unix, windows)))]
+pub use pages::{Decommit, FenceSide, Fenced, Pages, Pinned, Secure, ThpHint};
+#[cfg(all(feature = "pages", target_os = "linux"))]
+pub use pages::{HugePageSize, HugePages};
+#[cfg(all(feature = "pages", target_os = "linux"))]
+mod mapped_file;
+#[cfg(all(feature = "pages", target_os = "linux"))]
+pub use mapped_file::{MappedFile, OnFree};
+#[cfg(all(feature = "pages", target_os = "linux"))]
+mod memfd;
+#[cfg(all(feature = "pages", target_os = "linux"))]
+pub use memfd::Memfd;
+#[cfg(all(feature = "pages", unix))]
+mod shm;
+#[cfg(all(feature = "pages", unix))]
+pub use shm::{Offset, OsError, SharedMem};
+#[cfg(target_arch = "wasm32")]
+mod wasm;
+#[cfg(target_arch = "wasm32")]
+pub use wasm::WasmPages;
+#[cfg(feature = "std")]
+mod record;
+#[cfg(feature = "std")]
+pub use record::{Record, Recorder, Replayer, Trace};
+#[cfg(feature = "std")]
+mod system;
+#[cfg(feature = "std")]
+pub use system::System;
+#[cfg(feature = "std")]
+mod free_check;
+#[cfg(feature = "std")]
+pub use free_check::FreeCheck;
+#[cfg(feature = "std")]
+mod leak;
+#[cfg(feature = "std")]
+pub use leak::{Leak, LeakCheck, OnDrop};
+#[cfg(feature = "std")]
+mod mock;
+#[cfg(feature = "std")]
+pub use mock::{Call, Method, Mock, Response};
+#[cfg(feature = "std")]
+mod profile;
+#[cfg(feature = "std")]
+pub use profile::{Attributed, Profiler, Site};
+#[cfg(feature = "std")]
+mod prometheus;
+#[cfg(feature = "std")]
+pub use prometheus::prometheus;
+#[cfg(feature = "std")]
+mod thread_cache;
+#[cfg(feature = "std")]
+pub use thread_cache::ThreadCache;
+
+#[cfg(feature = "defmt")]
+mod defmt_logged;
+#[cfg(feature = "defmt")]
+pub use defmt_logged::DefmtLogged;
+#[cfg(feature = "log")]
+mod logged;
+#[cfg(feature = "log")]
+pub use logged::Logged;
+#[cfg(feature = "metrics")]
+mod metered;
+#[cfg(feature = "metrics")]
+pub use metered::Metered;
+#[cfg(feature = "tracing")]
+mod traced;
+#[cfg(feature = "tracing")]
+pub use traced::Traced;
+#[cfg(feature = "c-abi")]
+mod c_abi;
+#[cfg(feature = "pmr-ffi")]
+mod pmr_ffi;
+#[cfg(feature = "pmr-ffi")]
+pub use pmr_ffi::{FromMemoryResource, MemoryResourceVTable, ToMemoryResource};
+#[cfg(feature = "arbitrary")]
+mod fuzz;
+#[cfg(feature = "arbitrary")]
+pub use fuzz::{exercise, ArbitraryLayout, Op};
+#[cfg(feature = "valgrind")]
+mod valgrind;
+#[cfg(feature = "valgrind")]
+pub use valgrind::Valgrind;
 
 mod limit;
-pub use limit::{CountLimit, SizeLimit};
+pub use limit::{CountLimit, SizeLimit, UnsyncCountLimit, UnsyncSizeLimit};
 mod affix;
-pub use affix::{Affix, Guard};
+pub use affix::{Affix, AffixInit, Guard, RandomGuard};
+mod arena;
+pub use arena::{Arena, Checkpoint};
+mod asan;
+mod budget;
+pub use budget::{Budget, Budgeted};
+mod counter;
+pub use counter::Counter;
+mod critical;
+pub use critical::{Critical, CriticalSection};
+#[cfg(feature = "alloc")]
+mod dynamic;
+#[cfg(feature = "alloc")]
+pub use dynamic::DynAllocator;
+mod either;
+pub use either::Either;
+mod fail;
+pub use fail::{FailAfter, FailEvery, FailRandomly};
+mod fill;
+pub use fill::{Byte, Fill, Pattern, Untouched};
+mod frame;
+pub use frame::FrameAllocator;
+mod global;
+pub use global::{FromGlobal, ToGlobal};
+mod high_water;
+pub use high_water::HighWater;
+mod hooked;
+pub use hooked::{Event, Hooked};
+mod inline;
+pub use inline::Inline;
+mod locked;
+pub use locked::Locked;
+mod max_alloc_size;
+pub use max_alloc_size::MaxAllocSize;
+mod never_in_place;
+pub use never_in_place::NeverInPlace;
 mod null;
 pub use null::Null;
+mod on_oom;
+pub use on_oom::{OnOom, OomAction};
 mod or;
 pub use or::Or;
+mod or_die;
+pub use or_die::OrDie;
+mod pad;
+pub use pad::{CacheLines, PadToCacheLine, CACHE_LINE};
+mod pmr;
+pub use pmr::{MonotonicBufferResource, SyncPoolResource, UnsyncPoolResource};
+mod pool;
+pub use pool::Pool;
+mod recycle;
+pub use recycle::Recycle;
+mod region;
+pub use region::Region;
+mod rng;
+mod round;
+pub use round::{PowersOfTwo, Rounded, SizeClasses};
+mod route;
+pub use route::{AlignAtMost, Policy, RouteBy, SizeAtMost};
+mod scratch;
+pub use scratch::{Scope, Scratch};
+mod segregate;
+pub use segregate::Segregate;
+mod shuffle;
+pub use shuffle::Shuffle;
+mod slab;
+pub use slab::Slab;
+mod span;
+mod spin;
+pub use span::InSpan;
+mod stack;
+pub use stack::{Marker, Stack};
+mod stats;
+pub use stats::{BalanceGuard, Delta, Snapshot, Stats};
+mod store_layout;
+pub use store_layout::StoreLayout;
+mod striped;
+pub use striped::Striped;
+mod tagged;
+pub use tagged::{TagStats, Tagged, WithTag};
+mod tlsf;
+pub use tlsf::Tlsf;
+mod tracked;
+pub use tracked::Tracked;
+mod trim_on_fail;
+pub use trim_on_fail::TrimOnFail;
+mod typed_pool;
+pub use typed_pool::{Handle, TypedPool};
+mod validate;
+pub use validate::{LayoutRules, ValidateLayout, Violation};
+mod wipe;
+pub use wipe::WipeOnFree;
 mod zero;
-pub use zero::Zero;
+pub use zero::{Clear, Fresh, Zero, Zeroing};
 
 mod prelude {
     pub(crate) use crate::*;
@@ -43,7 +230,190 @@ pub unsafe trait Owns {
     fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool;
 }
 
+/// Allocators which can free every allocation at once,
+/// e.g to reuse an arena between frames.
+pub trait DeallocateAll {
+    /// Free every allocation, resetting any accounting.
+    ///
+    /// # Safety
+    /// - no allocation made before this call may be used after it.
+    unsafe fn deallocate_all(&self);
+}
+
+impl<A> DeallocateAll for &A
+where
+    A: DeallocateAll + ?Sized,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        (**self).deallocate_all()
+    }
+}
+
+/// Allocators which can free everything allocated since a [`Self::checkpoint`],
+/// e.g for phase-based allocation in a parser or compiler.
+pub trait Rewind {
+    type Checkpoint;
+    fn checkpoint(&self) -> Self::Checkpoint;
+    /// Free every allocation made since `checkpoint` was taken,
+    /// rewinding any accounting.
+    ///
+    /// # Safety
+    /// - `checkpoint` must be from [`Self::checkpoint`] on this allocator,
+    ///   and must not predate an earlier [`Self::rewind`] or [`DeallocateAll::deallocate_all`].
+    /// - allocations made after `checkpoint` was taken must not be used after this call.
+    unsafe fn rewind(&self, checkpoint: Self::Checkpoint);
+}
+
+impl<A> Rewind for &A
+where
+    A: Rewind + ?Sized,
+{
+    type Checkpoint = A::Checkpoint;
+    #[inline(always)]
+    fn checkpoint(&self) -> Self::Checkpoint {
+        (**self).checkpoint()
+    }
+    #[inline(always)]
+    unsafe fn rewind(&self, checkpoint: Self::Checkpoint) {
+        (**self).rewind(checkpoint)
+    }
+}
+
+/// Allocators which can report how many bytes of an allocation are usable,
+/// which may be more than were requested.
+///
+/// Containers can use this to claim slack capacity,
+/// and may then free the allocation with a [`Layout`] of the usable size.
+/// So combinators which account for allocations by size, like [`Stats`] and [`SizeLimit`],
+/// or route them by size, like [`Segregate`] and [`RouteBy`], don't implement this.
+pub trait UsableSize {
+    /// # Safety
+    /// - `ptr` must denote a live allocation from this allocator, which fits `layout`.
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize;
+}
+
+impl<A> UsableSize for &A
+where
+    A: UsableSize + ?Sized,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        (**self).usable_size(ptr, layout)
+    }
+}
+
+/// Allocators which can report on their whole heap,
+/// including allocations made outside this crate.
+///
+/// Each method returns [`None`] if the backend doesn't track that statistic.
+pub trait BackendStats {
+    /// Bytes allocated by the application.
+    fn allocated(&self) -> Option<usize>;
+    /// Bytes in physically resident pages, including allocator metadata and fragmentation.
+    fn resident(&self) -> Option<usize>;
+    /// Bytes in pages mapped by the allocator.
+    fn mapped(&self) -> Option<usize>;
+}
+
+impl<A> BackendStats for &A
+where
+    A: BackendStats + ?Sized,
+{
+    #[inline(always)]
+    fn allocated(&self) -> Option<usize> {
+        (**self).allocated()
+    }
+    #[inline(always)]
+    fn resident(&self) -> Option<usize> {
+        (**self).resident()
+    }
+    #[inline(always)]
+    fn mapped(&self) -> Option<usize> {
+        (**self).mapped()
+    }
+}
+
+/// Allocators which can release cached or free memory,
+/// e.g to shrink resident memory while a service is idle.
+///
+/// Combinators flush their own caches, and then trim the allocators they wrap.
+pub trait Trim {
+    /// Return as much unused memory as possible, to the OS or the inner allocator.
+    ///
+    /// Live allocations are unaffected.
+    fn trim(&self);
+}
+
+impl<A> Trim for &A
+where
+    A: Trim + ?Sized,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        (**self).trim()
+    }
+}
+
+/// [`Allocator`]s whose [`Allocator::allocate`] always returns zeroed memory,
+/// e.g because each allocation is fresh from the OS.
+///
+/// Implementors should forward [`Allocator::allocate_zeroed`] to [`Allocator::allocate`],
+/// so that zeroing callers don't clear the memory twice,
+/// and can be wrapped in [`Zero::fresh`], which never calls it.
+///
+/// # Safety
+/// - every block returned from [`Allocator::allocate`] must be zeroed.
+pub unsafe trait AllocatesZeroed: Allocator {}
+
+unsafe impl<A> AllocatesZeroed for &A where A: AllocatesZeroed + ?Sized {}
+
+unsafe impl<A> Owns for &A
+where
+    A: Owns + ?Sized,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        (**self).owns(ptr, layout)
+    }
+}
+
+#[cfg(feature = "alloc")]
+unsafe impl<A> Owns for alloc::boxed::Box<A>
+where
+    A: Owns + ?Sized,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        (**self).owns(ptr, layout)
+    }
+}
+
+#[cfg(feature = "alloc")]
+unsafe impl<A> Owns for alloc::rc::Rc<A>
+where
+    A: Owns + ?Sized,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        (**self).owns(ptr, layout)
+    }
+}
+
+#[cfg(feature = "alloc")]
+unsafe impl<A> Owns for alloc::sync::Arc<A>
+where
+    A: Owns + ?Sized,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        (**self).owns(ptr, layout)
+    }
+}
+
 /// Extension traits for [`Allocator`].
+///
+/// Methods take `self` by value, use [`Allocator::by_ref`] to compose a borrowed allocator.
 pub trait AllocatorExt: Allocator {
     fn or<A: Allocator>(self, fallback: A) -> Or<Self, A>
     where
@@ -58,18 +428,45 @@ pub trait AllocatorExt: Allocator {
     where
         Self: Sized,
     {
-        SizeLimit {
-            inner: self,
-            limit: limit.into(),
-        }
+        SizeLimit::<Self>::new(self, limit)
     }
     fn limit_count(self, limit: usize) -> CountLimit<Self>
     where
         Self: Sized,
     {
-        CountLimit {
+        CountLimit::<Self>::new(self, limit)
+    }
+    fn limit_size_unsync(self, limit: usize) -> UnsyncSizeLimit<Self>
+    where
+        Self: Sized,
+    {
+        UnsyncSizeLimit::new(self, limit)
+    }
+    fn limit_count_unsync(self, limit: usize) -> UnsyncCountLimit<Self>
+    where
+        Self: Sized,
+    {
+        UnsyncCountLimit::new(self, limit)
+    }
+    fn max_alloc_size(self, max: usize) -> MaxAllocSize<Self>
+    where
+        Self: Sized,
+    {
+        MaxAllocSize { inner: self, max }
+    }
+    fn validate_layout(self, rules: LayoutRules) -> ValidateLayout<Self>
+    where
+        Self: Sized,
+    {
+        ValidateLayout { inner: self, rules }
+    }
+    fn budgeted<'a>(self, budget: &'a Budget<'a>) -> Budgeted<'a, Self>
+    where
+        Self: Sized,
+    {
+        Budgeted {
             inner: self,
-            limit: limit.into(),
+            budget,
         }
     }
     fn guard<PrefixT, SuffixT>(
@@ -80,21 +477,399 @@ pub trait AllocatorExt: Allocator {
     where
         Self: Sized,
     {
-        Guard {
-            inner: Affix {
-                inner: self,
-                prefix: PhantomData,
-                suffix: PhantomData,
-            },
-            prefix,
-            suffix,
-        }
+        Guard::new(self, prefix, suffix)
+    }
+    fn affix_init<PrefixT, SuffixT, MakePrefixT, MakeSuffixT>(
+        self,
+        make_prefix: MakePrefixT,
+        make_suffix: MakeSuffixT,
+    ) -> AffixInit<Self, PrefixT, SuffixT, MakePrefixT, MakeSuffixT>
+    where
+        Self: Sized,
+        MakePrefixT: Fn(Layout) -> PrefixT,
+        MakeSuffixT: Fn(Layout) -> SuffixT,
+    {
+        AffixInit::new(self, make_prefix, make_suffix)
+    }
+    fn tagged<T, const N: usize>(self) -> Tagged<Self, T, N>
+    where
+        Self: Sized,
+    {
+        Tagged::new(self)
+    }
+    fn store_layout(self) -> StoreLayout<Self>
+    where
+        Self: Sized,
+    {
+        StoreLayout { inner: self }
+    }
+    #[cfg(feature = "valgrind")]
+    fn valgrind(self) -> Valgrind<Self>
+    where
+        Self: Sized,
+    {
+        Valgrind { inner: self }
+    }
+    fn shuffle(self, seed: u64, slots: usize) -> Shuffle<Self>
+    where
+        Self: Sized,
+    {
+        Shuffle::new(self, seed, slots)
+    }
+    fn random_guard(self, seed: u64) -> RandomGuard<Self>
+    where
+        Self: Sized,
+    {
+        RandomGuard::new(self, seed)
     }
     fn zero(self) -> Zero<Self>
     where
         Self: Sized,
     {
-        Zero { inner: self }
+        Zero::new(self)
+    }
+    fn wipe_on_free(self) -> WipeOnFree<Self>
+    where
+        Self: Sized,
+    {
+        WipeOnFree { inner: self }
+    }
+    fn arena(self, chunk_size: usize) -> Arena<Self>
+    where
+        Self: Sized,
+    {
+        Arena::new(self, chunk_size)
+    }
+    fn stack(self, capacity: usize) -> Stack<Self>
+    where
+        Self: Sized,
+    {
+        Stack::new(self, capacity)
+    }
+    fn scratch(self) -> Scratch<Self>
+    where
+        Self: Sized + Rewind,
+    {
+        Scratch::new(self)
+    }
+    fn pool(self, block: Layout, capacity: usize) -> Pool<Self>
+    where
+        Self: Sized,
+    {
+        Pool::new(self, block, capacity)
+    }
+    fn typed_pool<T>(self, capacity: usize) -> TypedPool<T, Self>
+    where
+        Self: Sized,
+    {
+        TypedPool::new(self, capacity)
+    }
+    fn slab<T>(self, chunk_len: usize) -> Slab<T, Self>
+    where
+        Self: Sized,
+    {
+        Slab::new(self, chunk_len)
+    }
+    fn recycle(self, capacity: usize) -> Recycle<Self>
+    where
+        Self: Sized,
+    {
+        Recycle::new(self, capacity)
+    }
+    #[cfg(feature = "std")]
+    fn thread_cache(self, capacity: usize) -> ThreadCache<Self>
+    where
+        Self: Sized + Send + Sync + 'static,
+    {
+        ThreadCache::new(self, capacity)
+    }
+    fn round_to<C: SizeClasses>(self, classes: C) -> Rounded<Self, C>
+    where
+        Self: Sized,
+    {
+        Rounded {
+            inner: self,
+            classes,
+        }
+    }
+    fn never_in_place(self) -> NeverInPlace<Self>
+    where
+        Self: Sized,
+    {
+        NeverInPlace { inner: self }
+    }
+    fn pad_to_cache_line(self) -> PadToCacheLine<Self>
+    where
+        Self: Sized,
+    {
+        Rounded::new(self, CacheLines)
+    }
+    #[cfg(feature = "std")]
+    fn free_check(self, history: usize) -> FreeCheck<Self>
+    where
+        Self: Sized,
+    {
+        FreeCheck::new(self, history)
+    }
+    #[cfg(feature = "std")]
+    fn recorded(self) -> Recorder<Self>
+    where
+        Self: Sized,
+    {
+        Recorder::new(self)
+    }
+    #[cfg(feature = "std")]
+    fn profiled(self) -> Profiler<Self>
+    where
+        Self: Sized,
+    {
+        Profiler::new(self)
+    }
+    #[cfg(feature = "std")]
+    fn leak_check(self) -> LeakCheck<Self>
+    where
+        Self: Sized,
+    {
+        LeakCheck::new(self)
+    }
+    fn segregate<A: Allocator>(self, threshold: usize, large: A) -> Segregate<Self, A>
+    where
+        Self: Sized,
+    {
+        Segregate {
+            small: self,
+            large,
+            threshold,
+        }
+    }
+    fn stats(self) -> Stats<Self>
+    where
+        Self: Sized,
+    {
+        Stats::new(self)
+    }
+    #[cfg(feature = "log")]
+    fn logged(self, name: &'static str) -> Logged<Self>
+    where
+        Self: Sized,
+    {
+        Logged { inner: self, name }
+    }
+    #[cfg(feature = "defmt")]
+    fn defmt_logged(self, name: &'static str) -> DefmtLogged<Self>
+    where
+        Self: Sized,
+    {
+        DefmtLogged { inner: self, name }
+    }
+    #[cfg(feature = "tracing")]
+    fn traced(self, name: &'static str) -> Traced<Self>
+    where
+        Self: Sized,
+    {
+        Traced { inner: self, name }
+    }
+    #[cfg(feature = "metrics")]
+    fn metered(self, name: &'static str) -> Metered<Self>
+    where
+        Self: Sized,
+    {
+        Metered { inner: self, name }
+    }
+    fn fail_after(self, remaining: usize) -> FailAfter<Self>
+    where
+        Self: Sized,
+    {
+        FailAfter::new(self, remaining)
+    }
+    fn fail_every(self, every: usize) -> FailEvery<Self>
+    where
+        Self: Sized,
+    {
+        FailEvery::new(self, every)
+    }
+    fn fail_randomly(self, seed: u64, probability: f64) -> FailRandomly<Self>
+    where
+        Self: Sized,
+    {
+        FailRandomly::new(self, seed, probability)
+    }
+    fn fill(self) -> Fill<Self>
+    where
+        Self: Sized,
+    {
+        Fill {
+            inner: self,
+            alloc: PhantomData,
+            free: PhantomData,
+        }
+    }
+    fn locked(self) -> Locked<Self>
+    where
+        Self: Sized,
+    {
+        Locked::new(self)
+    }
+    fn hooked<F: Fn(Event)>(self, hook: F) -> Hooked<Self, F>
+    where
+        Self: Sized,
+    {
+        Hooked { inner: self, hook }
+    }
+    fn high_water<T, F>(self, thresholds: T, callback: F) -> HighWater<Self, T, F>
+    where
+        Self: Sized,
+        T: AsRef<[usize]>,
+        F: Fn(usize, usize),
+    {
+        HighWater::new(self, thresholds, callback)
+    }
+    fn tracked(self) -> Tracked<Self>
+    where
+        Self: Sized,
+    {
+        Tracked::new(self)
+    }
+    fn route_by<F: Policy, B: Allocator>(self, policy: F, if_false: B) -> RouteBy<F, Self, B>
+    where
+        Self: Sized,
+    {
+        RouteBy {
+            policy,
+            if_true: self,
+            if_false,
+        }
+    }
+    fn on_oom<F: Fn(Layout) -> OomAction>(self, handler: F) -> OnOom<Self, F>
+    where
+        Self: Sized,
+    {
+        OnOom {
+            inner: self,
+            handler,
+        }
+    }
+    #[cfg(feature = "alloc")]
+    fn or_die(self) -> OrDie<Self>
+    where
+        Self: Sized,
+    {
+        OrDie::new(self)
+    }
+    fn trim_on_fail(self) -> TrimOnFail<Self>
+    where
+        Self: Sized,
+    {
+        TrimOnFail { inner: self }
     }
 }
 impl<A> AllocatorExt for A where A: Allocator {}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn by_ref() {
+    let mut buffer = [core::mem::MaybeUninit::uninit(); 8];
+    let a = Region::new(&mut buffer);
+    let b = a.by_ref().or(Malloc);
+    let it = allocator_api2::boxed::Box::new_in(1u64, &b);
+    assert!(a.owns(NonNull::from(&*it).cast(), Layout::new::<u64>()));
+    assert!(a
+        .by_ref()
+        .owns(NonNull::from(&*it).cast(), Layout::new::<u64>()));
+}
+
+#[cfg(all(feature = "nightly", feature = "alloc", feature = "malloc"))]
+#[test]
+fn nightly() {
+    let a = Malloc.limit_count(1).stats();
+    let mut v = alloc::vec::Vec::<u8, _>::new_in(&a);
+    v.push(1);
+    assert!(alloc::boxed::Box::try_new_in(1u8, &a).is_err());
+    drop(v);
+    assert_eq!(a.snapshot().live, 0);
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn statics() {
+    static A: Stats<Zero<SizeLimit<Malloc>>> =
+        Stats::new(Zero::new(SizeLimit::<Malloc>::new(Malloc, 64)));
+    let it = allocator_api2::boxed::Box::new_in([1u8; 64], &A);
+    allocator_api2::boxed::Box::try_new_in(1u8, &A).unwrap_err();
+    drop(it);
+    assert_eq!(A.snapshot().live, 0);
+}
+
+/// Panics on [`Allocator::allocate`], so combinators must forward the specialised methods.
+#[cfg(all(test, feature = "malloc"))]
+struct FastPathsOnly;
+
+#[cfg(all(test, feature = "malloc"))]
+unsafe impl Allocator for FastPathsOnly {
+    fn allocate(&self, _: Layout) -> Result<NonNull<[u8]>, allocator_api2::alloc::AllocError> {
+        panic!("fell back to Allocator::allocate")
+    }
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        Malloc.deallocate(ptr, layout)
+    }
+    fn allocate_zeroed(
+        &self,
+        layout: Layout,
+    ) -> Result<NonNull<[u8]>, allocator_api2::alloc::AllocError> {
+        Malloc.allocate_zeroed(layout)
+    }
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, allocator_api2::alloc::AllocError> {
+        Malloc.grow(ptr, old_layout, new_layout)
+    }
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, allocator_api2::alloc::AllocError> {
+        Malloc.grow_zeroed(ptr, old_layout, new_layout)
+    }
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, allocator_api2::alloc::AllocError> {
+        Malloc.shrink(ptr, old_layout, new_layout)
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn fast_paths() {
+    fn check(a: impl Allocator) {
+        let small = Layout::new::<u32>();
+        let large = Layout::new::<[u32; 8]>();
+        unsafe {
+            let ptr = a.allocate_zeroed(small).unwrap().cast();
+            let ptr = a.grow(ptr, small, large).unwrap().cast();
+            let ptr = a.shrink(ptr, large, small).unwrap().cast();
+            let ptr = a.grow_zeroed(ptr, small, large).unwrap().cast();
+            a.deallocate(ptr, large);
+        }
+    }
+    check(FastPathsOnly.limit_size(64));
+    check(FastPathsOnly.limit_count(1));
+    check(FastPathsOnly.limit_size_unsync(64));
+    check(FastPathsOnly.limit_count_unsync(1));
+    check(FastPathsOnly.guard(1u8, 2u16));
+    check(FastPathsOnly.random_guard(0xDEADBEEF));
+    check(Affix {
+        inner: FastPathsOnly,
+        prefix: PhantomData::<fn() -> u8>,
+        suffix: PhantomData::<fn() -> u64>,
+    });
+    check(Null.or(FastPathsOnly));
+    check(FastPathsOnly.stats());
+    check(FastPathsOnly.zero());
+}
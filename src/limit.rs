@@ -1,34 +1,241 @@
 use crate::prelude::*;
-use core::sync::atomic::{AtomicUsize, Ordering};
+use core::{cell::Cell, sync::atomic::AtomicUsize};
 
-/// An [`Allocator`] which allows `A` to allocate at most [`limit`](Self::limit) bytes.
+/// How much of a limit is in use, shared by [`SizeLimit`] and [`CountLimit`].
 #[derive(Debug)]
-pub struct SizeLimit<A> {
+struct Usage<C> {
+    limit: C,
+    used: C,
+    peak: C,
+}
+
+impl Usage<AtomicUsize> {
+    const fn new(limit: usize) -> Self {
+        Self {
+            limit: AtomicUsize::new(limit),
+            used: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl Usage<Cell<usize>> {
+    const fn new(limit: usize) -> Self {
+        Self {
+            limit: Cell::new(limit),
+            used: Cell::new(0),
+            peak: Cell::new(0),
+        }
+    }
+}
+
+impl<C> Usage<C>
+where
+    C: Counter,
+{
+    fn with_counter(limit: usize) -> Self {
+        Self {
+            limit: limit.into(),
+            used: 0.into(),
+            peak: 0.into(),
+        }
+    }
+    #[inline(always)]
+    fn remaining(&self) -> usize {
+        self.limit.get().saturating_sub(self.used.get())
+    }
+    /// Add `n` to the usage, failing if it would exceed the limit.
+    #[inline(always)]
+    fn charge(&self, n: usize) -> Result<(), AllocError> {
+        let limit = self.limit.get();
+        let prev = self
+            .used
+            .try_update(|it| it.checked_add(n).filter(|it| *it <= limit))
+            .map_err(|_| AllocError)?;
+        let used = prev + n;
+        let _ = self.peak.try_update(|it| (used > it).then_some(used));
+        Ok(())
+    }
+    /// Call `f` after charging `n`, refunding it if `f` fails.
+    #[inline(always)]
+    fn charged(
+        &self,
+        n: usize,
+        f: impl FnOnce() -> Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.charge(n)?;
+        let res = f();
+        if res.is_err() {
+            self.used.sub(n);
+        }
+        res
+    }
+}
+
+/// An [`Allocator`] which allows `A` to have at most [`limit`](Self::limit) bytes allocated.
+///
+/// See [`UnsyncSizeLimit`] to avoid atomic operations.
+#[derive(Debug)]
+pub struct SizeLimit<A, C = AtomicUsize> {
     pub inner: A,
-    pub limit: AtomicUsize,
+    usage: Usage<C>,
 }
-unsafe impl<A> Allocator for SizeLimit<A>
+
+/// A [`SizeLimit`] which isn't [`Sync`], but doesn't pay for atomic operations.
+pub type UnsyncSizeLimit<A> = SizeLimit<A, Cell<usize>>;
+
+unsafe impl<A, C> Allocator for SizeLimit<A, C>
 where
     A: Allocator,
+    C: Counter,
 {
     #[inline(always)]
     fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
-        match self
-            .limit
-            .fetch_update(Ordering::Release, Ordering::Acquire, |it| {
-                it.checked_sub(layout.size())
-            }) {
-            Ok(_) => self.inner.allocate(layout),
-            Err(_) => Err(AllocError),
-        }
+        self.usage
+            .charged(layout.size(), || self.inner.allocate(layout))
     }
     #[inline(always)]
     unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
-        self.limit.fetch_sub(layout.size(), Ordering::Release);
+        self.usage.used.sub(layout.size());
         self.inner.deallocate(ptr, layout)
     }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.usage
+            .charged(layout.size(), || self.inner.allocate_zeroed(layout))
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.usage
+            .charged(new_layout.size() - old_layout.size(), || {
+                self.inner.grow(ptr, old_layout, new_layout)
+            })
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.usage
+            .charged(new_layout.size() - old_layout.size(), || {
+                self.inner.grow_zeroed(ptr, old_layout, new_layout)
+            })
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.shrink(ptr, old_layout, new_layout);
+        if res.is_ok() {
+            self.usage.used.sub(old_layout.size() - new_layout.size());
+        }
+        res
+    }
 }
-unsafe impl<A> Owns for SizeLimit<A>
+
+unsafe impl<A, C> AllocatesZeroed for SizeLimit<A, C>
+where
+    A: AllocatesZeroed,
+    C: Counter,
+{
+}
+
+impl<A> SizeLimit<A> {
+    pub const fn new(inner: A, limit: usize) -> Self {
+        Self {
+            inner,
+            usage: Usage::<AtomicUsize>::new(limit),
+        }
+    }
+}
+
+impl<A> SizeLimit<A, Cell<usize>> {
+    pub const fn new(inner: A, limit: usize) -> Self {
+        Self {
+            inner,
+            usage: Usage::<Cell<usize>>::new(limit),
+        }
+    }
+}
+
+impl<A, C> SizeLimit<A, C>
+where
+    C: Counter,
+{
+    /// Like `new`, but for any [`Counter`].
+    pub fn with_counter(inner: A, limit: usize) -> Self {
+        Self {
+            inner,
+            usage: Usage::with_counter(limit),
+        }
+    }
+    /// The maximum number of bytes.
+    pub fn limit(&self) -> usize {
+        self.usage.limit.get()
+    }
+    /// Change [`Self::limit`].
+    ///
+    /// If this is below [`Self::used`], new allocations and growth will fail until enough are freed.
+    pub fn set_limit(&self, limit: usize) {
+        self.usage.limit.set(limit)
+    }
+    /// The number of bytes currently in use.
+    pub fn used(&self) -> usize {
+        self.usage.used.get()
+    }
+    /// [`Self::limit`] less [`Self::used`].
+    pub fn remaining(&self) -> usize {
+        self.usage.remaining()
+    }
+    /// The maximum of [`Self::used`].
+    pub fn peak(&self) -> usize {
+        self.usage.peak.get()
+    }
+}
+
+impl<A, C> DeallocateAll for SizeLimit<A, C>
+where
+    A: DeallocateAll,
+    C: Counter,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all();
+        self.usage.used.set(0)
+    }
+}
+
+/// Rewinding restores the usage from when the checkpoint was taken,
+/// so older allocations freed in the meantime are not credited.
+impl<A, C> Rewind for SizeLimit<A, C>
+where
+    A: Rewind,
+    C: Counter,
+{
+    type Checkpoint = (A::Checkpoint, usize);
+    #[inline(always)]
+    fn checkpoint(&self) -> Self::Checkpoint {
+        (self.inner.checkpoint(), self.usage.used.get())
+    }
+    #[inline(always)]
+    unsafe fn rewind(&self, checkpoint: Self::Checkpoint) {
+        let (inner, used) = checkpoint;
+        self.inner.rewind(inner);
+        self.usage.used.set(used)
+    }
+}
+
+unsafe impl<A, C> Owns for SizeLimit<A, C>
 where
     A: Owns,
 {
@@ -38,6 +245,16 @@ where
     }
 }
 
+impl<A, C> Trim for SizeLimit<A, C>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
 #[cfg(feature = "malloc")]
 #[test]
 fn limit() {
@@ -46,37 +263,200 @@ fn limit() {
     Box::try_new_in(1u8, &a).unwrap_err();
     drop(occupied);
     let _ = Box::new_in(1u8, &a);
+    let a = Malloc.limit_size_unsync(1);
+    let occupied = Box::new_in(1u8, &a);
+    Box::try_new_in(1u8, &a).unwrap_err();
+    drop(occupied);
+    let _ = Box::new_in(1u8, &a);
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn deallocate() {
+    // returns the bytes to the limit, rather than taking them again
+    let a = Malloc.limit_size(8);
+    let b = Malloc.limit_size_unsync(8);
+    for _ in 0..4 {
+        drop(Box::new_in([0u8; 8], &a));
+        drop(Box::new_in([0u8; 8], &b));
+        assert_eq!((a.remaining(), b.remaining()), (8, 8));
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn resize() {
+    let a = Malloc.limit_size(8);
+    let mut v = allocator_api2::vec::Vec::<u8, _>::with_capacity_in(4, &a);
+    v.extend([0; 4]);
+    v.try_reserve_exact(4).unwrap();
+    assert_eq!(a.remaining(), 0);
+    v.try_reserve_exact(5).unwrap_err();
+    assert_eq!(a.remaining(), 0);
+    v.shrink_to_fit();
+    assert_eq!(a.remaining(), 4);
+    drop(v);
+    assert_eq!(a.remaining(), 8);
+    assert_eq!((a.used(), a.peak()), (0, 8));
+    let a = Malloc.limit_count(1);
+    let mut v = allocator_api2::vec::Vec::<u8, _>::with_capacity_in(1, &a);
+    v.extend(0..=255);
+    assert_eq!(a.remaining(), 0);
 }
 
 #[derive(Debug)]
-/// An [`Allocator`] which allows `A` to allocate at most [`limit`](Self::limit) times.
-pub struct CountLimit<A> {
+/// An [`Allocator`] which allows `A` to have at most [`limit`](Self::limit) live allocations.
+///
+/// See [`UnsyncCountLimit`] to avoid atomic operations.
+pub struct CountLimit<A, C = AtomicUsize> {
     pub inner: A,
-    pub limit: AtomicUsize,
+    usage: Usage<C>,
 }
 
-unsafe impl<A> Allocator for CountLimit<A>
+/// A [`CountLimit`] which isn't [`Sync`], but doesn't pay for atomic operations.
+pub type UnsyncCountLimit<A> = CountLimit<A, Cell<usize>>;
+
+unsafe impl<A, C> Allocator for CountLimit<A, C>
 where
     A: Allocator,
+    C: Counter,
 {
     #[inline(always)]
     fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
-        match self
-            .limit
-            .fetch_update(Ordering::Release, Ordering::Acquire, |it| it.checked_sub(1))
-        {
-            Ok(_) => self.inner.allocate(layout),
-            Err(_) => Err(AllocError),
-        }
+        self.usage.charged(1, || self.inner.allocate(layout))
     }
     #[inline(always)]
     unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
-        self.limit.fetch_add(1, Ordering::Release);
+        self.usage.used.sub(1);
         self.inner.deallocate(ptr, layout)
     }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.usage.charged(1, || self.inner.allocate_zeroed(layout))
+    }
+    /// Resizing doesn't change the number of allocations.
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.grow(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.grow_zeroed(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.shrink(ptr, old_layout, new_layout)
+    }
+}
+
+unsafe impl<A, C> AllocatesZeroed for CountLimit<A, C>
+where
+    A: AllocatesZeroed,
+    C: Counter,
+{
+}
+
+impl<A> CountLimit<A> {
+    pub const fn new(inner: A, limit: usize) -> Self {
+        Self {
+            inner,
+            usage: Usage::<AtomicUsize>::new(limit),
+        }
+    }
+}
+
+impl<A> CountLimit<A, Cell<usize>> {
+    pub const fn new(inner: A, limit: usize) -> Self {
+        Self {
+            inner,
+            usage: Usage::<Cell<usize>>::new(limit),
+        }
+    }
+}
+
+impl<A, C> CountLimit<A, C>
+where
+    C: Counter,
+{
+    /// Like `new`, but for any [`Counter`].
+    pub fn with_counter(inner: A, limit: usize) -> Self {
+        Self {
+            inner,
+            usage: Usage::with_counter(limit),
+        }
+    }
+    /// The maximum number of allocations.
+    pub fn limit(&self) -> usize {
+        self.usage.limit.get()
+    }
+    /// Change [`Self::limit`].
+    ///
+    /// If this is below [`Self::used`], new allocations will fail until enough are freed.
+    pub fn set_limit(&self, limit: usize) {
+        self.usage.limit.set(limit)
+    }
+    /// The number of allocations currently in use.
+    pub fn used(&self) -> usize {
+        self.usage.used.get()
+    }
+    /// [`Self::limit`] less [`Self::used`].
+    pub fn remaining(&self) -> usize {
+        self.usage.remaining()
+    }
+    /// The maximum of [`Self::used`].
+    pub fn peak(&self) -> usize {
+        self.usage.peak.get()
+    }
 }
 
-unsafe impl<A> Owns for CountLimit<A>
+impl<A, C> DeallocateAll for CountLimit<A, C>
+where
+    A: DeallocateAll,
+    C: Counter,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all();
+        self.usage.used.set(0)
+    }
+}
+
+/// Rewinding restores the usage from when the checkpoint was taken,
+/// so older allocations freed in the meantime are not credited.
+impl<A, C> Rewind for CountLimit<A, C>
+where
+    A: Rewind,
+    C: Counter,
+{
+    type Checkpoint = (A::Checkpoint, usize);
+    #[inline(always)]
+    fn checkpoint(&self) -> Self::Checkpoint {
+        (self.inner.checkpoint(), self.usage.used.get())
+    }
+    #[inline(always)]
+    unsafe fn rewind(&self, checkpoint: Self::Checkpoint) {
+        let (inner, used) = checkpoint;
+        self.inner.rewind(inner);
+        self.usage.used.set(used)
+    }
+}
+
+unsafe impl<A, C> Owns for CountLimit<A, C>
 where
     A: Owns,
 {
@@ -86,6 +466,26 @@ where
     }
 }
 
+impl<A, C> UsableSize for CountLimit<A, C>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A, C> Trim for CountLimit<A, C>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
 #[cfg(feature = "malloc")]
 #[test]
 fn count() {
@@ -94,4 +494,54 @@ fn count() {
     Box::try_new_in(1, &a).unwrap_err();
     drop(occupied);
     let _ = Box::new_in(1, &a);
+    let a = Malloc.limit_count_unsync(1);
+    let occupied = Box::new_in(1, &a);
+    Box::try_new_in(1, &a).unwrap_err();
+    drop(occupied);
+    let _ = Box::new_in(1, &a);
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn set_limit() {
+    let a = Malloc.limit_size(8);
+    let first = Box::new_in(1u32, &a);
+    a.set_limit(2);
+    assert_eq!((a.limit(), a.used(), a.remaining()), (2, 4, 0));
+    Box::try_new_in(1u8, &a).unwrap_err();
+    drop(first);
+    let _second = Box::new_in(1u16, &a);
+    assert_eq!((a.used(), a.peak()), (2, 4));
+}
+
+#[test]
+fn deallocate_all() {
+    let inline = Inline::<8>::new();
+    let a = (&inline).limit_size(4).stats();
+    let first = a.allocate(Layout::new::<u32>()).unwrap();
+    a.allocate(Layout::new::<u8>()).unwrap_err();
+    unsafe { a.deallocate_all() };
+    assert_eq!(a.snapshot().live, 0);
+    // the limit itself is untouched, only the usage is restored
+    let limit = &a.inner;
+    assert_eq!((limit.limit(), limit.used(), limit.peak()), (4, 0, 4));
+    let second = Box::new_in(1u32, &a);
+    assert_eq!(first.cast::<u32>(), NonNull::from(&*second));
+    let inline = Inline::<8>::new();
+    let a = (&inline).limit_count(1);
+    a.allocate(Layout::new::<u8>()).unwrap();
+    unsafe { a.deallocate_all() };
+    assert_eq!((a.limit(), a.used(), a.remaining()), (1, 0, 1));
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn rewind() {
+    let a = Malloc.arena(64).limit_size(8);
+    let _first = Box::new_in(1u32, &a);
+    let checkpoint = a.checkpoint();
+    a.allocate(Layout::new::<u32>()).unwrap();
+    a.allocate(Layout::new::<u8>()).unwrap_err();
+    unsafe { a.rewind(checkpoint) };
+    a.allocate(Layout::new::<u32>()).unwrap();
 }
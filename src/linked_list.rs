@@ -0,0 +1,116 @@
+use crate::{prelude::*, Locked};
+use core::{cell::UnsafeCell, fmt, mem::MaybeUninit};
+use linked_list_allocator::Heap;
+
+/// An [`Allocator`] using a [`linked_list_allocator::Heap`],
+/// a first-fit free list over a fixed region of memory.
+///
+/// This is not [`Sync`] - use [`LockedLinkedListHeap`],
+/// or a [`Critical`](crate::Critical) on single-core embedded targets,
+/// and reach the [`Heap`] with [`Critical::with_mut`](crate::Critical::with_mut):
+/// ```
+/// # use composable_allocators::*;
+/// # use core::mem::MaybeUninit;
+/// # struct Interrupts;
+/// # unsafe impl CriticalSection for Interrupts {
+/// #     fn with<R>(&self, f: impl FnOnce() -> R) -> R { f() }
+/// # }
+/// static mut MEMORY: [MaybeUninit<u8>; 1024] = [MaybeUninit::uninit(); 1024];
+/// static HEAP: Critical<LinkedListHeap, Interrupts> =
+///     Critical::new(LinkedListHeap::empty(), Interrupts);
+///
+/// HEAP.with_mut(|it| it.heap_mut().init_from_slice(unsafe { &mut *core::ptr::addr_of_mut!(MEMORY) }));
+/// let _ = allocator_api2::boxed::Box::new_in(1, &HEAP);
+/// ```
+pub struct LinkedListHeap {
+    heap: UnsafeCell<Heap>,
+}
+
+/// A [`LinkedListHeap`] behind a spinlock.
+pub type LockedLinkedListHeap = Locked<LinkedListHeap>;
+
+impl LinkedListHeap {
+    /// A heap with no memory, which fails every allocation until initialized.
+    pub const fn empty() -> Self {
+        Self {
+            heap: UnsafeCell::new(Heap::empty()),
+        }
+    }
+    pub fn from_slice(mem: &'static mut [MaybeUninit<u8>]) -> Self {
+        Self::from_heap(Heap::from_slice(mem))
+    }
+    pub const fn from_heap(heap: Heap) -> Self {
+        Self {
+            heap: UnsafeCell::new(heap),
+        }
+    }
+    /// To initialize or extend the heap.
+    pub fn heap_mut(&mut self) -> &mut Heap {
+        self.heap.get_mut()
+    }
+    pub fn into_inner(self) -> Heap {
+        self.heap.into_inner()
+    }
+    #[inline(always)]
+    fn with<R>(&self, f: impl FnOnce(&mut Heap) -> R) -> R {
+        // not Sync, and the heap never calls back into us
+        f(unsafe { &mut *self.heap.get() })
+    }
+}
+
+impl Default for LinkedListHeap {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl fmt::Debug for LinkedListHeap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.with(|heap| {
+            f.debug_struct("LinkedListHeap")
+                .field("bottom", &heap.bottom())
+                .field("size", &heap.size())
+                .field("used", &heap.used())
+                .finish()
+        })
+    }
+}
+
+unsafe impl Allocator for LinkedListHeap {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self
+            .with(|it| it.allocate_first_fit(layout))
+            .map_err(|()| AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.with(|it| it.deallocate(ptr, layout))
+    }
+}
+
+unsafe impl Owns for LinkedListHeap {
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        let ptr = ptr.as_ptr() as usize;
+        self.with(|it| {
+            it.bottom() as usize <= ptr && ptr.saturating_add(layout.size()) <= it.top() as usize
+        })
+    }
+}
+
+#[test]
+fn linked_list_heap() {
+    let mut mem = [MaybeUninit::<u8>::uninit(); 1024];
+    let heap = unsafe { Heap::new(mem.as_mut_ptr().cast(), mem.len()) };
+    let a = LockedLinkedListHeap::new(LinkedListHeap::from_heap(heap));
+    let mut v = allocator_api2::vec::Vec::<u64, _>::new_in(&a);
+    v.extend(0..64);
+    assert!(a.owns(NonNull::from(&v[0]).cast(), Layout::new::<u64>()));
+    assert!(v.try_reserve(1024).is_err());
+    drop(v);
+    let mut a = a.into_inner();
+    assert_eq!(a.heap_mut().used(), 0);
+    assert!(!a.owns(NonNull::dangling(), Layout::new::<u64>()));
+}
@@ -0,0 +1,133 @@
+use crate::{prelude::*, spin::Spin};
+
+/// An [`Allocator`] which holds a spinlock around every call to `A`.
+///
+/// This makes single-threaded allocators like [`Arena`] and [`Pool`] [`Sync`],
+/// so they can be shared between threads or used as globals.
+#[derive(Debug, Default)]
+pub struct Locked<A> {
+    inner: Spin<A>,
+}
+
+impl<A> Locked<A> {
+    pub const fn new(inner: A) -> Self {
+        Self {
+            inner: Spin::new(inner),
+        }
+    }
+    pub fn into_inner(self) -> A {
+        self.inner.into_inner()
+    }
+}
+
+unsafe impl<A> Allocator for Locked<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.lock().allocate(layout)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.inner.lock().deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.lock().allocate_zeroed(layout)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.lock().grow(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.lock().grow_zeroed(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.lock().shrink(ptr, old_layout, new_layout)
+    }
+}
+
+unsafe impl<A> AllocatesZeroed for Locked<A> where A: AllocatesZeroed {}
+
+impl<A> DeallocateAll for Locked<A>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.lock().deallocate_all()
+    }
+}
+
+impl<A> Rewind for Locked<A>
+where
+    A: Rewind,
+{
+    type Checkpoint = A::Checkpoint;
+    #[inline(always)]
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.inner.lock().checkpoint()
+    }
+    #[inline(always)]
+    unsafe fn rewind(&self, checkpoint: Self::Checkpoint) {
+        self.inner.lock().rewind(checkpoint)
+    }
+}
+
+unsafe impl<A> Owns for Locked<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.lock().owns(ptr, layout)
+    }
+}
+
+impl<A> UsableSize for Locked<A>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.lock().usable_size(ptr, layout)
+    }
+}
+
+impl<A> Trim for Locked<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.lock().trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn locked() {
+    fn assert_sync<T: Sync>(_: &T) {}
+    let a = Malloc.arena(64).locked();
+    assert_sync(&a);
+    let _ = Box::new_in(1, &a);
+    assert!(a.owns(NonNull::from(&*Box::new_in(1u8, &a)), Layout::new::<u8>()));
+}
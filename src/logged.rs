@@ -0,0 +1,141 @@
+use crate::prelude::*;
+
+/// An [`Allocator`] which emits a [`log`] record for every call to `A`,
+/// labelled with [`Self::name`].
+///
+/// Successes are logged at [`log::Level::Trace`], and failures at [`log::Level::Debug`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Logged<A> {
+    pub inner: A,
+    pub name: &'static str,
+}
+
+impl<A> Logged<A> {
+    pub const fn new(inner: A, name: &'static str) -> Self {
+        Self { inner, name }
+    }
+    #[inline(always)]
+    fn log(
+        &self,
+        method: &str,
+        old_layout: Option<Layout>,
+        layout: Layout,
+        res: Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let Self { name, .. } = self;
+        let (size, align) = (layout.size(), layout.align());
+        match (res, old_layout) {
+            (Ok(ptr), None) => log::trace!(
+                "{name}: {method}(size={size}, align={align}) -> {:p}",
+                ptr.cast::<u8>()
+            ),
+            (Ok(ptr), Some(old)) => log::trace!(
+                "{name}: {method}(old_size={}, size={size}, align={align}) -> {:p}",
+                old.size(),
+                ptr.cast::<u8>()
+            ),
+            (Err(AllocError), None) => {
+                log::debug!("{name}: {method}(size={size}, align={align}) failed")
+            }
+            (Err(AllocError), Some(old)) => log::debug!(
+                "{name}: {method}(old_size={}, size={size}, align={align}) failed",
+                old.size()
+            ),
+        }
+        res
+    }
+}
+
+unsafe impl<A> Allocator for Logged<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.log("allocate", None, layout, self.inner.allocate(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        log::trace!(
+            "{}: deallocate({ptr:p}, size={}, align={})",
+            self.name,
+            layout.size(),
+            layout.align()
+        );
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.allocate_zeroed(layout);
+        self.log("allocate_zeroed", None, layout, res)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.grow(ptr, old_layout, new_layout);
+        self.log("grow", Some(old_layout), new_layout, res)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.grow_zeroed(ptr, old_layout, new_layout);
+        self.log("grow_zeroed", Some(old_layout), new_layout, res)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.shrink(ptr, old_layout, new_layout);
+        self.log("shrink", Some(old_layout), new_layout, res)
+    }
+}
+
+unsafe impl<A> AllocatesZeroed for Logged<A> where A: AllocatesZeroed {}
+
+unsafe impl<A> Owns for Logged<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A> UsableSize for Logged<A>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A> Trim for Logged<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn logged() {
+    let _ = Box::new_in(1, Malloc.logged("malloc"));
+    Box::try_new_in(1, Null.logged("null")).unwrap_err();
+}
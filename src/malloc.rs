@@ -1,11 +1,48 @@
-use crate::prelude::*;
+use crate::{prelude::*, segregate::relocate};
 use core::{cmp, mem, ptr};
 use libc::c_void;
 
+/// The alignment that `malloc` and `realloc` guarantee for allocations at least this large.
+const MIN_ALIGN: usize = 2 * mem::size_of::<usize>();
+
 /// An allocator using the OS-provided [`malloc`](https://man7.org/linux/man-pages/man3/malloc.3.html).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Malloc;
 
+impl Malloc {
+    /// Resize with `realloc`, which may extend the allocation in place,
+    /// or fall back to allocating, copying and freeing if it can't guarantee the alignment.
+    ///
+    /// # Safety
+    /// - As for [`Allocator::grow`] or [`Allocator::shrink`].
+    #[inline(always)]
+    unsafe fn realloc(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        zeroed: bool,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        // `realloc` may free on zero size, and only aligns to the smaller of `MIN_ALIGN` and the size
+        if new_layout.size() == 0
+            || new_layout.align() > MIN_ALIGN
+            || new_layout.align() > new_layout.size()
+        {
+            return relocate(self, self, ptr, old_layout, new_layout, zeroed);
+        }
+        let new = libc::realloc(ptr.as_ptr().cast::<c_void>(), new_layout.size());
+        let new = NonNull::new(new.cast::<u8>()).ok_or(AllocError)?;
+        if zeroed && new_layout.size() > old_layout.size() {
+            ptr::write_bytes(
+                new.as_ptr().add(old_layout.size()),
+                0,
+                new_layout.size() - old_layout.size(),
+            );
+        }
+        Ok(NonNull::slice_from_raw_parts(new, new_layout.size()))
+    }
+}
+
 unsafe impl Allocator for Malloc {
     #[inline(always)]
     fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
@@ -27,13 +64,116 @@ unsafe impl Allocator for Malloc {
         }
     }
 
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        // `calloc` can skip zeroing fresh pages from the OS
+        if layout.size() == 0 || layout.align() > MIN_ALIGN || layout.align() > layout.size() {
+            let ptr = self.allocate(layout)?;
+            unsafe { ptr.cast::<u8>().as_ptr().write_bytes(0, layout.size()) };
+            return Ok(ptr);
+        }
+        match NonNull::new(unsafe { libc::calloc(1, layout.size()) }.cast::<u8>()) {
+            Some(calloc) => Ok(NonNull::slice_from_raw_parts(calloc, layout.size())),
+            None => Err(AllocError),
+        }
+    }
+
     #[inline(always)]
     unsafe fn deallocate(&self, free: NonNull<u8>, _: Layout) {
         libc::free(free.as_ptr().cast::<c_void>())
     }
+
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.realloc(ptr, old_layout, new_layout, false)
+    }
+
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.realloc(ptr, old_layout, new_layout, true)
+    }
+
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.realloc(ptr, old_layout, new_layout, false)
+    }
+}
+
+impl UsableSize for Malloc {
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        let size = libc::malloc_usable_size(ptr.as_ptr().cast::<c_void>());
+        #[cfg(target_vendor = "apple")]
+        let size = libc::malloc_size(ptr.as_ptr().cast::<c_void>());
+        #[cfg(not(any(target_os = "linux", target_os = "android", target_vendor = "apple")))]
+        let size = {
+            let _ = ptr;
+            0
+        };
+        cmp::max(size, layout.size())
+    }
+}
+
+/// Only glibc can release free memory, with `malloc_trim`.
+impl Trim for Malloc {
+    fn trim(&self) {
+        #[cfg(all(target_os = "linux", target_env = "gnu"))]
+        unsafe {
+            libc::malloc_trim(0);
+        }
+    }
 }
 
 #[test]
 fn should_succeed() {
     let _ = Box::new_in(1, Malloc);
+    let layout = Layout::new::<[u8; 3]>();
+    let ptr = Malloc.allocate(layout).unwrap().cast();
+    assert!(unsafe { Malloc.usable_size(ptr, layout) } >= 3);
+    unsafe { Malloc.deallocate(ptr, layout) };
+}
+
+#[test]
+fn calloc() {
+    for layout in [
+        Layout::new::<[u64; 512]>(),
+        Layout::from_size_align(3, 64).unwrap(),
+        Layout::new::<()>(),
+    ] {
+        let ptr = Malloc.allocate_zeroed(layout).unwrap();
+        assert_eq!(ptr.cast::<u8>().as_ptr() as usize % layout.align(), 0);
+        assert!(unsafe { ptr.as_ref() }.iter().all(|it| *it == 0));
+        unsafe { Malloc.deallocate(ptr.cast(), layout) };
+    }
+}
+
+#[test]
+fn realloc() {
+    let mut v = allocator_api2::vec::Vec::<u8, _>::new_in(Malloc);
+    v.extend(0..=255);
+    v.truncate(3);
+    v.shrink_to_fit();
+    assert_eq!(v, [0, 1, 2]);
+    let mut v = allocator_api2::vec::Vec::<CacheLine, _>::with_capacity_in(1, Malloc);
+    v.extend([CacheLine([1; 64]), CacheLine([2; 64])]);
+    assert_eq!(v.as_ptr() as usize % 64, 0);
+    assert_eq!(v[0].0, [1; 64]);
+    #[repr(align(64))]
+    struct CacheLine([u8; 64]);
 }
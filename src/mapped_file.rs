@@ -0,0 +1,157 @@
+use crate::{pages::page_size, prelude::*};
+use core::ffi::c_int;
+
+/// What [`MappedFile`] does with the pages of freed allocations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum OnFree {
+    /// Leave them in the file.
+    #[default]
+    Keep,
+    /// Release the whole pages in each allocation with `fallocate(FALLOC_FL_PUNCH_HOLE)`,
+    /// so the file stays sparse.
+    PunchHole,
+}
+
+/// An [`Allocator`] which bump-allocates out of a file mapped with `mmap`,
+/// for arenas which persist between runs, or are larger than RAM.
+///
+/// Bookkeeping is stored in the file like a [`SharedMem`],
+/// so a file may be reopened with [`Self::open`],
+/// and allocations found again with [`Offset`]s.
+///
+/// Like [`Region`], deallocating the most recent allocation reuses its space.
+/// Other space is only reused after [`DeallocateAll`],
+/// but see [`Self::on_free`].
+#[derive(Debug)]
+pub struct MappedFile {
+    shm: SharedMem,
+    pub on_free: OnFree,
+}
+
+impl MappedFile {
+    /// Resize the file `fd` to `len` bytes and map it, discarding its contents.
+    ///
+    /// # Safety
+    /// - `fd` must be a regular file opened for reading and writing,
+    ///   which is owned by the result, or closed on failure.
+    /// - the file must not be truncated while it is mapped.
+    pub unsafe fn create(fd: c_int, len: usize) -> Result<Self, OsError> {
+        if libc::ftruncate(fd, 0) != 0 {
+            let e = OsError::last();
+            libc::close(fd);
+            return Err(e);
+        }
+        Ok(Self {
+            shm: SharedMem::init(fd, len)?,
+            on_free: OnFree::Keep,
+        })
+    }
+    /// Map the file `fd`, which must have been set up by [`Self::create`],
+    /// keeping its allocations.
+    ///
+    /// Fails with `EINVAL` if it wasn't.
+    ///
+    /// # Safety
+    /// - As for [`Self::create`].
+    pub unsafe fn open(fd: c_int) -> Result<Self, OsError> {
+        Ok(Self {
+            shm: SharedMem::attach(fd)?,
+            on_free: OnFree::Keep,
+        })
+    }
+    /// The file descriptor, which stays open until this is dropped.
+    pub fn fd(&self) -> c_int {
+        self.shm.fd()
+    }
+    /// The mapping, e.g to convert pointers to and from [`Offset`]s.
+    pub fn shared_mem(&self) -> &SharedMem {
+        &self.shm
+    }
+    /// Write changes back to the file with `msync`, blocking until they are durable.
+    pub fn flush(&self) -> Result<(), OsError> {
+        let base = self.shm.base();
+        match unsafe { libc::msync(base.as_ptr().cast(), self.shm.len(), libc::MS_SYNC) } {
+            0 => Ok(()),
+            _ => Err(OsError::last()),
+        }
+    }
+    #[inline(always)]
+    unsafe fn punch_hole(&self, ptr: NonNull<u8>, layout: Layout) {
+        let page = page_size();
+        let offset = ptr.as_ptr() as usize - self.shm.base().as_ptr() as usize;
+        // only whole pages, since neighbours may share the partial ones
+        let (Some(start), end) = (
+            offset.checked_next_multiple_of(page),
+            (offset + layout.size()) / page * page,
+        ) else {
+            return;
+        };
+        if start < end {
+            // best effort, since the file system may not support it
+            libc::fallocate(
+                self.fd(),
+                libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE,
+                start as libc::off_t,
+                (end - start) as libc::off_t,
+            );
+        }
+    }
+}
+
+unsafe impl Allocator for MappedFile {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.shm.allocate(layout)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if self.on_free == OnFree::PunchHole {
+            self.punch_hole(ptr, layout)
+        }
+        self.shm.deallocate(ptr, layout)
+    }
+}
+
+/// Freed space is not punched out, regardless of [`MappedFile::on_free`].
+impl DeallocateAll for MappedFile {
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.shm.deallocate_all()
+    }
+}
+
+unsafe impl Owns for MappedFile {
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.shm.owns(ptr, layout)
+    }
+}
+
+#[test]
+fn mapped_file() {
+    let blocks = |fd| unsafe {
+        let mut stat = core::mem::MaybeUninit::<libc::stat>::uninit();
+        assert_eq!(libc::fstat(fd, stat.as_mut_ptr()), 0);
+        stat.assume_init().st_blocks
+    };
+    let fd = unsafe { libc::open(c"/tmp".as_ptr(), libc::O_TMPFILE | libc::O_RDWR, 0o600) };
+    assert!(fd >= 0);
+    let page = page_size();
+    let mut a = unsafe { MappedFile::create(libc::dup(fd), 4 * page) }.unwrap();
+    a.on_free = OnFree::PunchHole;
+    let kept = Box::new_in(7u64, &a);
+    let offset = a.shared_mem().offset_of(NonNull::from(&*kept)).unwrap();
+    let mut big = allocator_api2::vec::Vec::<u8, _>::with_capacity_in(2 * page, &a);
+    big.resize(2 * page, 1);
+    let before = blocks(fd);
+    drop(big);
+    assert!(blocks(fd) < before);
+    a.flush().unwrap();
+    core::mem::forget(kept);
+    drop(a);
+    let a = unsafe { MappedFile::open(fd) }.unwrap();
+    assert_eq!(
+        unsafe { *a.shared_mem().resolve(offset).unwrap().as_ptr() },
+        7
+    );
+}
@@ -0,0 +1,143 @@
+use crate::prelude::*;
+
+/// An [`Allocator`] which fails any single allocation of more than [`Self::max`] bytes,
+/// regardless of how much is currently allocated.
+///
+/// This guards `A` against pathological requests, such as attacker-controlled lengths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MaxAllocSize<A> {
+    pub inner: A,
+    pub max: usize,
+}
+
+impl<A> MaxAllocSize<A> {
+    pub const fn new(inner: A, max: usize) -> Self {
+        Self { inner, max }
+    }
+    #[inline(always)]
+    fn check(&self, layout: Layout) -> Result<(), AllocError> {
+        match layout.size() <= self.max {
+            true => Ok(()),
+            false => Err(AllocError),
+        }
+    }
+}
+
+unsafe impl<A> Allocator for MaxAllocSize<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.check(layout)?;
+        self.inner.allocate(layout)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.check(layout)?;
+        self.inner.allocate_zeroed(layout)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.check(new_layout)?;
+        self.inner.grow(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.check(new_layout)?;
+        self.inner.grow_zeroed(ptr, old_layout, new_layout)
+    }
+    /// Shrinking is always passed through to `A`.
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.shrink(ptr, old_layout, new_layout)
+    }
+}
+
+unsafe impl<A> AllocatesZeroed for MaxAllocSize<A> where A: AllocatesZeroed {}
+
+impl<A> DeallocateAll for MaxAllocSize<A>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all()
+    }
+}
+
+impl<A> Rewind for MaxAllocSize<A>
+where
+    A: Rewind,
+{
+    type Checkpoint = A::Checkpoint;
+    #[inline(always)]
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.inner.checkpoint()
+    }
+    #[inline(always)]
+    unsafe fn rewind(&self, checkpoint: Self::Checkpoint) {
+        self.inner.rewind(checkpoint)
+    }
+}
+
+unsafe impl<A> Owns for MaxAllocSize<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A> UsableSize for MaxAllocSize<A>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A> Trim for MaxAllocSize<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn max_alloc_size() {
+    let a = Malloc.max_alloc_size(4);
+    let _first = Box::new_in(1u32, &a);
+    let _second = Box::new_in(1u32, &a);
+    Box::try_new_in(1u64, &a).unwrap_err();
+    let mut v = allocator_api2::vec::Vec::<u8, _>::with_capacity_in(2, &a);
+    v.try_reserve_exact(4).unwrap();
+    v.try_reserve_exact(5).unwrap_err();
+}
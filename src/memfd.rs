@@ -0,0 +1,140 @@
+use crate::prelude::*;
+use core::ffi::{c_int, CStr};
+
+/// An [`Allocator`] which bump-allocates out of an anonymous file from `memfd_create`,
+/// like a [`SharedMem`] without a name.
+///
+/// The file descriptor may be passed to other processes, e.g over a Unix socket,
+/// which can map it with [`Self::from_fd`].
+/// It is created with `F_SEAL_SHRINK` so that it can't be truncated under a mapping,
+/// and further seals may be added with [`Self::seal`].
+///
+/// `F_SEAL_WRITE` can't be added while the file is mapped writable,
+/// so use [`Self::into_fd`] to unmap it first:
+/// ```
+/// # use composable_allocators::*;
+/// let memfd = Memfd::create(c"buffers", 4096).unwrap();
+/// let buf = allocator_api2::boxed::Box::new_in(*b"sealed", &memfd);
+/// drop(buf);
+/// let fd = memfd.into_fd();
+/// assert_eq!(unsafe { libc::fcntl(fd, libc::F_ADD_SEALS, libc::F_SEAL_WRITE) }, 0);
+/// # unsafe { libc::close(fd) };
+/// ```
+#[derive(Debug)]
+pub struct Memfd {
+    shm: SharedMem,
+}
+
+impl Memfd {
+    /// Create and map an anonymous file of `len` bytes, including a small header.
+    ///
+    /// `name` is only for debugging, and appears in `/proc/self/fd`.
+    pub fn create(name: &CStr, len: usize) -> Result<Self, OsError> {
+        let fd = unsafe {
+            libc::memfd_create(name.as_ptr(), libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING)
+        };
+        if fd < 0 {
+            return Err(OsError::last());
+        }
+        let this = Self {
+            shm: unsafe { SharedMem::init(fd, len) }?,
+        };
+        this.seal(libc::F_SEAL_SHRINK)?;
+        Ok(this)
+    }
+    /// Map a file descriptor from [`Self::fd`] or [`Self::into_fd`],
+    /// e.g one received from another process.
+    ///
+    /// Fails with `EINVAL` if it wasn't created by [`Self::create`],
+    /// or `EPERM` if it has been sealed against writing.
+    ///
+    /// # Safety
+    /// - `fd` must be open, and is owned by the result, or closed on failure.
+    pub unsafe fn from_fd(fd: c_int) -> Result<Self, OsError> {
+        Ok(Self {
+            shm: SharedMem::attach(fd)?,
+        })
+    }
+    /// The file descriptor, which stays open until this is dropped.
+    pub fn fd(&self) -> c_int {
+        self.shm.fd()
+    }
+    /// Unmap the file, returning its descriptor, which the caller must close.
+    ///
+    /// Every allocation must have been freed.
+    pub fn into_fd(self) -> c_int {
+        self.shm.into_fd()
+    }
+    /// Add `seals`, e.g `F_SEAL_GROW | F_SEAL_FUTURE_WRITE`.
+    pub fn seal(&self, seals: c_int) -> Result<(), OsError> {
+        match unsafe { libc::fcntl(self.fd(), libc::F_ADD_SEALS, seals) } {
+            0 => Ok(()),
+            _ => Err(OsError::last()),
+        }
+    }
+    /// The seals which have been added.
+    pub fn seals(&self) -> Result<c_int, OsError> {
+        match unsafe { libc::fcntl(self.fd(), libc::F_GET_SEALS) } {
+            -1 => Err(OsError::last()),
+            seals => Ok(seals),
+        }
+    }
+    /// The mapping, e.g to convert pointers to [`Offset`]s for other processes.
+    pub fn shared_mem(&self) -> &SharedMem {
+        &self.shm
+    }
+}
+
+unsafe impl Allocator for Memfd {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.shm.allocate(layout)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.shm.deallocate(ptr, layout)
+    }
+}
+
+impl DeallocateAll for Memfd {
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.shm.deallocate_all()
+    }
+}
+
+unsafe impl Owns for Memfd {
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.shm.owns(ptr, layout)
+    }
+}
+
+#[test]
+fn memfd() {
+    let here = Memfd::create(c"test", 64).unwrap();
+    let dup = unsafe { libc::dup(here.fd()) };
+    let there = unsafe { Memfd::from_fd(dup) }.unwrap();
+    let it = Box::new_in(1u64, &here);
+    let offset = here.shared_mem().offset_of(NonNull::from(&*it)).unwrap();
+    assert_eq!(
+        unsafe { *there.shared_mem().resolve(offset).unwrap().as_ptr() },
+        1
+    );
+    here.seal(libc::F_SEAL_GROW).unwrap();
+    assert_eq!(
+        here.seals().unwrap(),
+        libc::F_SEAL_SHRINK | libc::F_SEAL_GROW
+    );
+    assert_eq!(here.seal(libc::F_SEAL_WRITE), Err(OsError(libc::EBUSY)));
+    drop((it, there));
+    let fd = here.into_fd();
+    assert_eq!(
+        unsafe { libc::fcntl(fd, libc::F_ADD_SEALS, libc::F_SEAL_WRITE) },
+        0
+    );
+    assert_eq!(
+        unsafe { Memfd::from_fd(fd) }.unwrap_err(),
+        OsError(libc::EPERM)
+    );
+}
@@ -0,0 +1,231 @@
+use crate::prelude::*;
+
+/// An [`Allocator`] which reports calls to `A` through the [`metrics`] facade,
+/// labelled with `allocator="<name>"`.
+///
+/// The metric names match those rendered by `prometheus` (feature `std`):
+/// - `allocator_allocations_total`
+/// - `allocator_deallocations_total`
+/// - `allocator_reallocations_total`
+/// - `allocator_failures_total`
+/// - `allocator_allocated_bytes_total`
+/// - `allocator_live_bytes`
+///
+/// If the installed recorder allocates, it must not do so through this allocator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Metered<A> {
+    pub inner: A,
+    pub name: &'static str,
+}
+
+impl<A> Metered<A> {
+    pub const fn new(inner: A, name: &'static str) -> Self {
+        Self { inner, name }
+    }
+    #[inline(always)]
+    fn charge(&self, size: usize) {
+        let name = self.name;
+        metrics::counter!("allocator_allocated_bytes_total", "allocator" => name)
+            .increment(size as u64);
+        metrics::gauge!("allocator_live_bytes", "allocator" => name).increment(size as f64);
+    }
+    #[inline(always)]
+    fn refund(&self, size: usize) {
+        metrics::gauge!("allocator_live_bytes", "allocator" => self.name).decrement(size as f64);
+    }
+    #[inline(always)]
+    fn fail(&self) {
+        metrics::counter!("allocator_failures_total", "allocator" => self.name).increment(1);
+    }
+    #[inline(always)]
+    fn allocated(
+        &self,
+        layout: Layout,
+        res: Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        match res {
+            Ok(_) => {
+                metrics::counter!("allocator_allocations_total", "allocator" => self.name)
+                    .increment(1);
+                self.charge(layout.size());
+            }
+            Err(_) => self.fail(),
+        }
+        res
+    }
+    #[inline(always)]
+    fn reallocated(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        res: Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        match res {
+            Ok(_) => {
+                metrics::counter!("allocator_reallocations_total", "allocator" => self.name)
+                    .increment(1);
+                match new_layout.size() >= old_layout.size() {
+                    true => self.charge(new_layout.size() - old_layout.size()),
+                    false => self.refund(old_layout.size() - new_layout.size()),
+                }
+            }
+            Err(_) => self.fail(),
+        }
+        res
+    }
+}
+
+unsafe impl<A> Allocator for Metered<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocated(layout, self.inner.allocate(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        metrics::counter!("allocator_deallocations_total", "allocator" => self.name).increment(1);
+        self.refund(layout.size());
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocated(layout, self.inner.allocate_zeroed(layout))
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.grow(ptr, old_layout, new_layout);
+        self.reallocated(old_layout, new_layout, res)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.grow_zeroed(ptr, old_layout, new_layout);
+        self.reallocated(old_layout, new_layout, res)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.shrink(ptr, old_layout, new_layout);
+        self.reallocated(old_layout, new_layout, res)
+    }
+}
+
+unsafe impl<A> AllocatesZeroed for Metered<A> where A: AllocatesZeroed {}
+
+impl<A> DeallocateAll for Metered<A>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all();
+        metrics::gauge!("allocator_live_bytes", "allocator" => self.name).set(0.0)
+    }
+}
+
+unsafe impl<A> Owns for Metered<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A> Trim for Metered<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[cfg(all(feature = "malloc", feature = "std"))]
+#[test]
+fn metered() {
+    use metrics::{Counter, Gauge, Histogram, Key, KeyName, Metadata, SharedString, Unit};
+    use std::{
+        string::String,
+        sync::{atomic::AtomicU64, atomic::Ordering, Arc, Mutex},
+        vec::Vec,
+    };
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<(String, Arc<AtomicU64>)>>);
+    impl Recorder {
+        fn get(&self, name: &str, allocator: &str) -> Arc<AtomicU64> {
+            let key = std::format!("{name}{{{allocator}}}");
+            let mut metrics = self.0.lock().unwrap();
+            match metrics.iter().find(|(it, _)| *it == key) {
+                Some((_, it)) => it.clone(),
+                None => {
+                    metrics.push((key, Arc::default()));
+                    metrics.last().unwrap().1.clone()
+                }
+            }
+        }
+        fn register(&self, key: &Key) -> Arc<AtomicU64> {
+            let allocator = key.labels().find(|it| it.key() == "allocator").unwrap();
+            self.get(key.name(), allocator.value())
+        }
+        fn counter(&self, name: &str, allocator: &str) -> u64 {
+            self.get(name, allocator).load(Ordering::Relaxed)
+        }
+        fn gauge(&self, name: &str, allocator: &str) -> f64 {
+            f64::from_bits(self.get(name, allocator).load(Ordering::Relaxed))
+        }
+    }
+    impl metrics::Recorder for Recorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::from_arc(self.register(key))
+        }
+        fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::from_arc(self.register(key))
+        }
+        fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    let recorder = Recorder::default();
+    metrics::with_local_recorder(&recorder, || {
+        let a = Malloc.metered("malloc");
+        let mut v = allocator_api2::vec::Vec::<u8, _>::with_capacity_in(4, &a);
+        v.extend_from_slice(&[1u8; 16]);
+        assert_eq!(recorder.gauge("allocator_live_bytes", "malloc"), 16.0);
+        drop(v);
+        Box::try_new_in(1, Null.metered("null")).unwrap_err();
+    });
+    for (name, malloc, null) in [
+        ("allocator_allocations_total", 1, 0),
+        ("allocator_reallocations_total", 1, 0),
+        ("allocator_deallocations_total", 1, 0),
+        ("allocator_failures_total", 0, 1),
+        ("allocator_allocated_bytes_total", 16, 0),
+    ] {
+        assert_eq!(recorder.counter(name, "malloc"), malloc, "{name}");
+        assert_eq!(recorder.counter(name, "null"), null, "{name}");
+    }
+    assert_eq!(recorder.gauge("allocator_live_bytes", "malloc"), 0.0);
+}
@@ -1,15 +1,52 @@
 use crate::prelude::*;
-use core::ffi::c_void;
+use core::{ffi::c_void, ptr};
 
 /// An allocator using [`mimalloc`](https://github.com/microsoft/mimalloc).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Mimalloc;
 
+impl Mimalloc {
+    /// Try and resize in place with `mi_expand`, falling back to `mi_realloc_aligned`.
+    ///
+    /// # Safety
+    /// - As for [`Allocator::grow`] or [`Allocator::shrink`].
+    #[inline(always)]
+    unsafe fn resize(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        zeroed: bool,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let (p, size, align) = (
+            ptr.as_ptr().cast::<c_void>(),
+            new_layout.size(),
+            new_layout.align(),
+        );
+        let in_place = (ptr.as_ptr() as usize).is_multiple_of(align)
+            && !libmimalloc_sys::mi_expand(p, size).is_null();
+        let new = match in_place {
+            true => ptr,
+            false => NonNull::new(libmimalloc_sys::mi_realloc_aligned(p, size, align).cast::<u8>())
+                .ok_or(AllocError)?,
+        };
+        if zeroed && new_layout.size() > old_layout.size() {
+            ptr::write_bytes(
+                new.as_ptr().add(old_layout.size()),
+                0,
+                new_layout.size() - old_layout.size(),
+            );
+        }
+        Ok(NonNull::slice_from_raw_parts(new, new_layout.size()))
+    }
+}
+
 unsafe impl Allocator for Mimalloc {
     #[inline(always)]
     fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        // unlike `mi_aligned_alloc`, this has a fast path for small sizes and alignments
         match NonNull::new(unsafe {
-            libmimalloc_sys::mi_aligned_alloc(layout.align(), layout.size())
+            libmimalloc_sys::mi_malloc_aligned(layout.size(), layout.align())
         }) {
             Some(it) => Ok(NonNull::slice_from_raw_parts(
                 it.cast::<u8>(),
@@ -23,6 +60,49 @@ unsafe impl Allocator for Mimalloc {
     unsafe fn deallocate(&self, ptr: NonNull<u8>, _: Layout) {
         libmimalloc_sys::mi_free(ptr.as_ptr().cast::<c_void>())
     }
+
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        match NonNull::new(unsafe {
+            libmimalloc_sys::mi_zalloc_aligned(layout.size(), layout.align())
+        }) {
+            Some(it) => Ok(NonNull::slice_from_raw_parts(
+                it.cast::<u8>(),
+                layout.size(),
+            )),
+            None => Err(AllocError),
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, false)
+    }
+
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, true)
+    }
+
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, false)
+    }
 }
 
 unsafe impl Owns for Mimalloc {
@@ -33,7 +113,90 @@ unsafe impl Owns for Mimalloc {
     }
 }
 
+impl UsableSize for Mimalloc {
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, _: Layout) -> usize {
+        libmimalloc_sys::mi_usable_size(ptr.as_ptr().cast::<c_void>())
+    }
+}
+
+/// Collects the heap of the calling thread, and any abandoned by exited threads.
+impl Trim for Mimalloc {
+    fn trim(&self) {
+        unsafe { libmimalloc_sys::mi_collect(true) }
+    }
+}
+
+/// Measured for the whole process, with [`libmimalloc_sys::mi_process_info`].
+impl BackendStats for Mimalloc {
+    /// Not tracked.
+    fn allocated(&self) -> Option<usize> {
+        None
+    }
+    /// Precise on Windows and macOS, estimated from [`Self::mapped`] elsewhere.
+    fn resident(&self) -> Option<usize> {
+        let mut current_rss = 0;
+        unsafe {
+            libmimalloc_sys::mi_process_info(
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                &mut current_rss,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        Some(current_rss)
+    }
+    /// Memory committed by mimalloc.
+    fn mapped(&self) -> Option<usize> {
+        let mut current_commit = 0;
+        unsafe {
+            libmimalloc_sys::mi_process_info(
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                &mut current_commit,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        Some(current_commit)
+    }
+}
+
 #[test]
 fn should_succeed() {
     let _ = Box::new_in(1, Mimalloc);
+    let layout = Layout::new::<[u8; 3]>();
+    let ptr = Mimalloc.allocate(layout).unwrap().cast();
+    assert!(unsafe { Mimalloc.usable_size(ptr, layout) } >= 3);
+    unsafe { Mimalloc.deallocate(ptr, layout) };
+}
+
+#[test]
+fn resize() {
+    let mut v = allocator_api2::vec::Vec::<u8, _>::new_in(Mimalloc);
+    v.extend(0..=255);
+    v.truncate(3);
+    v.shrink_to_fit();
+    assert_eq!(v, [0, 1, 2]);
+    let layout = Layout::from_size_align(8, 64).unwrap();
+    let ptr = Mimalloc.allocate_zeroed(layout).unwrap().cast::<u8>();
+    let grown = Layout::from_size_align(1 << 20, 64).unwrap();
+    let ptr = unsafe { Mimalloc.grow_zeroed(ptr, layout, grown) }.unwrap();
+    assert_eq!(ptr.cast::<u8>().as_ptr() as usize % 64, 0);
+    assert!(unsafe { ptr.as_ref() }.iter().all(|it| *it == 0));
+    unsafe { Mimalloc.deallocate(ptr.cast(), grown) };
+}
+
+#[test]
+fn stats() {
+    let _it = Box::new_in([1u8; 4096], Mimalloc);
+    assert!(Mimalloc.mapped().unwrap() > 0);
+    assert!(Mimalloc.resident().unwrap() > 0);
 }
@@ -0,0 +1,354 @@
+use crate::{prelude::*, spin::lock};
+use core::ptr;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Mutex, MutexGuard},
+    vec::Vec,
+};
+
+/// An [`Allocator`] method, as recorded by [`Mock`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Method {
+    Allocate,
+    AllocateZeroed,
+    Deallocate,
+    Grow,
+    GrowZeroed,
+    Shrink,
+}
+
+/// A call to a [`Mock`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Call {
+    pub method: Method,
+    /// The previous layout, if resizing.
+    pub old_layout: Option<Layout>,
+    pub layout: Layout,
+    /// The pointer passed in, if any.
+    pub ptr: Option<NonNull<u8>>,
+    /// The block returned, if the call succeeded.
+    pub returned: Option<NonNull<[u8]>>,
+}
+
+/// How a [`Mock`] responds to an allocating call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Response {
+    /// Pass the call on to the inner allocator.
+    #[default]
+    Succeed,
+    /// Fail with [`AllocError`].
+    Fail,
+    /// Pass the call on to the inner allocator,
+    /// returning a block with this many bytes more than requested.
+    Slack(usize),
+}
+
+#[derive(Debug, Default)]
+struct State {
+    calls: Vec<Call>,
+    script: VecDeque<Response>,
+    /// The layout actually allocated from `A`, by address.
+    live: BTreeMap<usize, Layout>,
+}
+
+/// An [`Allocator`] for tests, which records every [`Call`]
+/// and responds to allocating calls according to a script.
+///
+/// ```
+/// # use composable_allocators::*;
+/// let mock = Mock::new(Malloc);
+/// mock.script([Response::Succeed, Response::Fail]);
+/// let a = (&mock).limit_count(8);
+/// let _ = allocator_api2::boxed::Box::new_in(1u8, &a);
+/// allocator_api2::boxed::Box::try_new_in(1u8, &a).unwrap_err();
+/// mock.assert_allocations(2);
+/// mock.assert_deallocations(1);
+/// ```
+#[derive(Debug)]
+pub struct Mock<A> {
+    pub inner: A,
+    state: Mutex<State>,
+}
+
+impl<A> Mock<A> {
+    pub const fn new(inner: A) -> Self {
+        Self {
+            inner,
+            state: Mutex::new(State {
+                calls: Vec::new(),
+                script: VecDeque::new(),
+                live: BTreeMap::new(),
+            }),
+        }
+    }
+    #[inline(always)]
+    fn state(&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+    /// Queue responses to the following allocating calls.
+    ///
+    /// Once the script runs out, calls [succeed](Response::Succeed).
+    pub fn script(&self, responses: impl IntoIterator<Item = Response>) {
+        self.state().script.extend(responses)
+    }
+    /// Every call so far, oldest first.
+    pub fn calls(&self) -> Vec<Call> {
+        self.state().calls.clone()
+    }
+    /// The number of calls to `method` so far.
+    pub fn count(&self, method: Method) -> usize {
+        let state = self.state();
+        state.calls.iter().filter(|it| it.method == method).count()
+    }
+    /// Forget the calls so far.
+    pub fn clear(&self) {
+        self.state().calls.clear()
+    }
+    /// Assert the number of calls to [`Allocator::allocate`] or [`Allocator::allocate_zeroed`].
+    #[track_caller]
+    pub fn assert_allocations(&self, expected: usize) {
+        let actual = self.count(Method::Allocate) + self.count(Method::AllocateZeroed);
+        assert_eq!(actual, expected, "unexpected number of allocations")
+    }
+    /// Assert the number of calls to [`Allocator::deallocate`].
+    #[track_caller]
+    pub fn assert_deallocations(&self, expected: usize) {
+        let actual = self.count(Method::Deallocate);
+        assert_eq!(actual, expected, "unexpected number of deallocations")
+    }
+    /// Assert the sequence of methods called.
+    #[track_caller]
+    pub fn assert_methods(&self, expected: &[Method]) {
+        let actual = self
+            .state()
+            .calls
+            .iter()
+            .map(|it| it.method)
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected, "unexpected calls")
+    }
+}
+
+impl<A> Mock<A>
+where
+    A: Allocator,
+{
+    /// Record a call, calling `f` with the size of slack to add unless it should fail.
+    #[inline(always)]
+    fn respond(
+        &self,
+        method: Method,
+        ptr: Option<NonNull<u8>>,
+        old_layout: Option<Layout>,
+        layout: Layout,
+        f: impl FnOnce(Option<Layout>, Layout) -> Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let mut state = self.state();
+        let res = match state.script.pop_front().unwrap_or_default() {
+            Response::Fail => Err(AllocError),
+            Response::Succeed => Ok(0),
+            Response::Slack(it) => Ok(it),
+        }
+        .and_then(|slack| {
+            let actual = Layout::from_size_align(layout.size() + slack, layout.align())
+                .map_err(|_| AllocError)?;
+            let old_actual = ptr.map(|it| state.live[&(it.as_ptr() as usize)]);
+            let block = f(old_actual, actual)?;
+            if let Some(it) = ptr {
+                state.live.remove(&(it.as_ptr() as usize));
+            }
+            state
+                .live
+                .insert(block.cast::<u8>().as_ptr() as usize, actual);
+            Ok(block)
+        });
+        state.calls.push(Call {
+            method,
+            old_layout,
+            layout,
+            ptr,
+            returned: res.ok(),
+        });
+        res
+    }
+}
+
+unsafe impl<A> Allocator for Mock<A>
+where
+    A: Allocator,
+{
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.respond(Method::Allocate, None, None, layout, |_, actual| {
+            self.inner.allocate(actual)
+        })
+    }
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let mut state = self.state();
+        let actual = state.live.remove(&(ptr.as_ptr() as usize));
+        state.calls.push(Call {
+            method: Method::Deallocate,
+            old_layout: None,
+            layout,
+            ptr: Some(ptr),
+            returned: None,
+        });
+        drop(state);
+        self.inner.deallocate(ptr, actual.unwrap_or(layout))
+    }
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.respond(Method::AllocateZeroed, None, None, layout, |_, actual| {
+            self.inner.allocate_zeroed(actual)
+        })
+    }
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let method = Method::Grow;
+        self.respond(
+            method,
+            Some(ptr),
+            Some(old_layout),
+            new_layout,
+            |old, new| self.resize(ptr, old_layout, old.unwrap_unchecked(), new, false),
+        )
+    }
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let method = Method::GrowZeroed;
+        self.respond(
+            method,
+            Some(ptr),
+            Some(old_layout),
+            new_layout,
+            |old, new| self.resize(ptr, old_layout, old.unwrap_unchecked(), new, true),
+        )
+    }
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let method = Method::Shrink;
+        self.respond(
+            method,
+            Some(ptr),
+            Some(old_layout),
+            new_layout,
+            |old, new| self.resize(ptr, old_layout, old.unwrap_unchecked(), new, false),
+        )
+    }
+}
+
+impl<A> Mock<A>
+where
+    A: Allocator,
+{
+    /// Slack may turn growing into shrinking for `A`, or vice-versa.
+    ///
+    /// `requested` is the old layout the caller knows about,
+    /// whose size is all the caller expects to keep when `zeroed`.
+    unsafe fn resize(
+        &self,
+        ptr: NonNull<u8>,
+        requested: Layout,
+        old: Layout,
+        new: Layout,
+        zeroed: bool,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let block = match (new.size() >= old.size(), zeroed) {
+            (true, true) => self.inner.grow_zeroed(ptr, old, new),
+            (true, false) => self.inner.grow(ptr, old, new),
+            (false, _) => self.inner.shrink(ptr, old, new),
+        }?;
+        if zeroed {
+            // `A` only zeroes past the old slack, if at all
+            let end = old.size().min(block.len());
+            ptr::write_bytes(
+                block.cast::<u8>().as_ptr().add(requested.size()),
+                0,
+                end.saturating_sub(requested.size()),
+            )
+        }
+        Ok(block)
+    }
+}
+
+/// Asks `A` about the layout it actually allocated, which may include slack.
+unsafe impl<A> Owns for Mock<A>
+where
+    A: Owns,
+{
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        let actual = self.state().live.get(&(ptr.as_ptr() as usize)).copied();
+        self.inner.owns(ptr, actual.unwrap_or(layout))
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn mock() {
+    let mock = Mock::new(Malloc);
+    mock.script([Response::Slack(4), Response::Fail]);
+    let mut v = allocator_api2::vec::Vec::<u8, _>::with_capacity_in(4, &mock);
+    assert_eq!(v.capacity(), 4);
+    v.extend([0; 4]);
+    v.try_reserve_exact(4).unwrap_err();
+    v.try_reserve_exact(4).unwrap();
+    drop(v);
+    mock.assert_methods(&[
+        Method::Allocate,
+        Method::Grow,
+        Method::Grow,
+        Method::Deallocate,
+    ]);
+    let calls = mock.calls();
+    assert_eq!(calls[1].returned, None);
+    assert_eq!(calls[2].old_layout, Some(Layout::new::<[u8; 4]>()));
+    assert_eq!(calls[3].layout, Layout::new::<[u8; 8]>());
+    mock.assert_allocations(1);
+    mock.assert_deallocations(1);
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn owns() {
+    let a = Mock::new(Malloc.tracked()).or(Malloc);
+    a.primary.script([Response::Slack(8)]);
+    let mut v = allocator_api2::vec::Vec::<u8, _>::with_capacity_in(8, &a);
+    v.extend([0; 8]);
+    let layout = Layout::new::<[u8; 8]>();
+    assert!(a
+        .primary
+        .owns(NonNull::new(v.as_mut_ptr()).unwrap(), layout));
+    let theirs = a.fallback.allocate(layout).unwrap().cast();
+    assert!(!a.primary.owns(theirs, layout));
+    unsafe { a.deallocate(theirs, layout) };
+    drop(v);
+    a.primary.assert_deallocations(1);
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn grow_zeroed_slack() {
+    let mock = Mock::new(Malloc);
+    let old = Layout::new::<[u8; 4]>();
+    for response in [Response::Slack(12), Response::Slack(0)] {
+        mock.script([Response::Slack(8), response]);
+        unsafe {
+            let ptr = mock.allocate(old).unwrap().cast::<u8>();
+            ptr.write_bytes(0xAA, 12);
+            let new = Layout::new::<[u8; 8]>();
+            let block = mock.grow_zeroed(ptr, old, new).unwrap();
+            assert!(block.as_ref()[4..].iter().all(|it| *it == 0));
+            mock.deallocate(block.cast(), new)
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{prelude::*, segregate::relocate};
+
+/// An [`Allocator`] which never resizes in place:
+/// [`Allocator::grow`] and [`Allocator::shrink`] always allocate a new block,
+/// copy, and free the old one.
+///
+/// Use this in tests to catch code which assumes pointers are stable across reallocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct NeverInPlace<A> {
+    pub inner: A,
+}
+
+impl<A> NeverInPlace<A> {
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+unsafe impl<A> Allocator for NeverInPlace<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.allocate(layout)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.allocate_zeroed(layout)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        relocate(&self.inner, &self.inner, ptr, old_layout, new_layout, false)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        relocate(&self.inner, &self.inner, ptr, old_layout, new_layout, true)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        relocate(&self.inner, &self.inner, ptr, old_layout, new_layout, false)
+    }
+}
+
+unsafe impl<A> AllocatesZeroed for NeverInPlace<A> where A: AllocatesZeroed {}
+
+impl<A> DeallocateAll for NeverInPlace<A>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all()
+    }
+}
+
+impl<A> Rewind for NeverInPlace<A>
+where
+    A: Rewind,
+{
+    type Checkpoint = A::Checkpoint;
+    #[inline(always)]
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.inner.checkpoint()
+    }
+    #[inline(always)]
+    unsafe fn rewind(&self, checkpoint: Self::Checkpoint) {
+        self.inner.rewind(checkpoint)
+    }
+}
+
+unsafe impl<A> Owns for NeverInPlace<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A> UsableSize for NeverInPlace<A>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A> Trim for NeverInPlace<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn never_in_place() {
+    let a = Malloc.never_in_place();
+    let small = Layout::new::<[u8; 8]>();
+    let large = Layout::new::<[u8; 16]>();
+    let ptr = a.allocate_zeroed(small).unwrap().cast::<u8>();
+    let grown = unsafe { a.grow_zeroed(ptr, small, large) }.unwrap();
+    assert_ne!(grown.cast::<u8>(), ptr);
+    assert_eq!(unsafe { grown.as_ref() }, [0; 16]);
+    let shrunk = unsafe { a.shrink(grown.cast(), large, small) }.unwrap();
+    assert_ne!(shrunk.cast::<u8>(), grown.cast::<u8>());
+    unsafe { a.deallocate(shrunk.cast(), small) };
+}
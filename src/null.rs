@@ -16,8 +16,42 @@ unsafe impl Allocator for Null {
     unsafe fn deallocate(&self, _: NonNull<u8>, _: Layout) {
         panic!("Null allocator should never be asked to deallocate")
     }
+    #[inline(always)]
+    fn allocate_zeroed(&self, _: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        Err(AllocError)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        _: NonNull<u8>,
+        _: Layout,
+        _: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        panic!("Null allocator should never be asked to grow")
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        _: NonNull<u8>,
+        _: Layout,
+        _: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        panic!("Null allocator should never be asked to grow")
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        _: NonNull<u8>,
+        _: Layout,
+        _: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        panic!("Null allocator should never be asked to shrink")
+    }
 }
 
+/// Vacuously, since it never allocates.
+unsafe impl crate::AllocatesZeroed for Null {}
+
 unsafe impl crate::Owns for Null {
     #[inline(always)]
     fn owns(&self, _: NonNull<u8>, _: Layout) -> bool {
@@ -0,0 +1,191 @@
+use crate::prelude::*;
+
+/// Returned by the handler in [`OnOom`] to decide what happens to a failed allocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OomAction {
+    /// Try the allocation again, e.g after evicting a cache.
+    Retry,
+    /// Return [`AllocError`].
+    Fail,
+    /// Panic, with the size and alignment of the failed [`Layout`].
+    Panic,
+}
+
+/// An [`Allocator`] which calls [`Self::handler`] with the requested [`Layout`]
+/// whenever `A` fails, and retries, fails or panics as it directs.
+///
+/// This is a place for back-pressure, logging, or emergency eviction.
+/// The handler is called again each time a retry fails,
+/// so it must eventually stop returning [`OomAction::Retry`]:
+/// ```
+/// # use composable_allocators::*;
+/// # use core::cell::Cell;
+/// let attempts = Cell::new(0);
+/// let a = Null.on_oom(|_| {
+///     attempts.set(attempts.get() + 1);
+///     match attempts.get() < 3 {
+///         true => OomAction::Retry,
+///         false => OomAction::Fail,
+///     }
+/// });
+/// allocator_api2::boxed::Box::try_new_in(1u8, &a).unwrap_err();
+/// assert_eq!(attempts.get(), 3);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OnOom<A, F> {
+    pub inner: A,
+    pub handler: F,
+}
+
+impl<A, F> OnOom<A, F> {
+    pub const fn new(inner: A, handler: F) -> Self {
+        Self { inner, handler }
+    }
+}
+
+impl<A, F> OnOom<A, F>
+where
+    F: Fn(Layout) -> OomAction,
+{
+    #[inline(always)]
+    fn retry<T>(
+        &self,
+        layout: Layout,
+        f: impl Fn() -> Result<T, AllocError>,
+    ) -> Result<T, AllocError> {
+        loop {
+            match f() {
+                Ok(it) => return Ok(it),
+                Err(AllocError) => match (self.handler)(layout) {
+                    OomAction::Retry => continue,
+                    OomAction::Fail => return Err(AllocError),
+                    OomAction::Panic => panic!(
+                        "allocation of {} bytes aligned to {} failed",
+                        layout.size(),
+                        layout.align()
+                    ),
+                },
+            }
+        }
+    }
+}
+
+unsafe impl<A, F> Allocator for OnOom<A, F>
+where
+    A: Allocator,
+    F: Fn(Layout) -> OomAction,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.retry(layout, || self.inner.allocate(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.retry(layout, || self.inner.allocate_zeroed(layout))
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        // a failed resize leaves the old block in place
+        self.retry(new_layout, || self.inner.grow(ptr, old_layout, new_layout))
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.retry(new_layout, || {
+            self.inner.grow_zeroed(ptr, old_layout, new_layout)
+        })
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.retry(new_layout, || {
+            self.inner.shrink(ptr, old_layout, new_layout)
+        })
+    }
+}
+
+unsafe impl<A, F> AllocatesZeroed for OnOom<A, F>
+where
+    A: AllocatesZeroed,
+    F: Fn(Layout) -> OomAction,
+{
+}
+
+impl<A, F> DeallocateAll for OnOom<A, F>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all()
+    }
+}
+
+unsafe impl<A, F> Owns for OnOom<A, F>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A, F> UsableSize for OnOom<A, F>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A, F> Trim for OnOom<A, F>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn on_oom() {
+    use core::cell::Cell;
+    let failed = Cell::new(None);
+    let a = Malloc.fail_every(2).on_oom(|layout| {
+        failed.set(Some(layout));
+        OomAction::Retry
+    });
+    for i in 0..4u64 {
+        drop(Box::new_in(i, &a));
+    }
+    assert_eq!(failed.get(), Some(Layout::new::<u64>()));
+}
+
+#[test]
+#[should_panic = "allocation of 2 bytes aligned to 2 failed"]
+fn panic() {
+    let _ = Box::try_new_in(1u16, Null.on_oom(|_| OomAction::Panic));
+}
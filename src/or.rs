@@ -7,6 +7,12 @@ pub struct Or<PrimaryT, FallbackT> {
     pub fallback: FallbackT,
 }
 
+impl<PrimaryT, FallbackT> Or<PrimaryT, FallbackT> {
+    pub const fn new(primary: PrimaryT, fallback: FallbackT) -> Self {
+        Self { primary, fallback }
+    }
+}
+
 unsafe impl<PrimaryT, FallbackT> Allocator for Or<PrimaryT, FallbackT>
 where
     PrimaryT: Allocator + Owns,
@@ -73,6 +79,25 @@ where
     }
 }
 
+unsafe impl<PrimaryT, FallbackT> AllocatesZeroed for Or<PrimaryT, FallbackT>
+where
+    PrimaryT: AllocatesZeroed + Owns,
+    FallbackT: AllocatesZeroed,
+{
+}
+
+impl<PrimaryT, FallbackT> DeallocateAll for Or<PrimaryT, FallbackT>
+where
+    PrimaryT: DeallocateAll,
+    FallbackT: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.primary.deallocate_all();
+        self.fallback.deallocate_all()
+    }
+}
+
 unsafe impl<PrimaryT, FallbackT> Owns for Or<PrimaryT, FallbackT>
 where
     PrimaryT: Owns,
@@ -84,6 +109,33 @@ where
     }
 }
 
+impl<PrimaryT, FallbackT> UsableSize for Or<PrimaryT, FallbackT>
+where
+    PrimaryT: UsableSize + Owns,
+    FallbackT: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        if self.primary.owns(ptr, layout) {
+            self.primary.usable_size(ptr, layout)
+        } else {
+            self.fallback.usable_size(ptr, layout)
+        }
+    }
+}
+
+impl<PrimaryT, FallbackT> Trim for Or<PrimaryT, FallbackT>
+where
+    PrimaryT: Trim,
+    FallbackT: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.primary.trim();
+        self.fallback.trim()
+    }
+}
+
 #[test]
 fn test() {
     Box::try_new_in(1, Null.or(Null)).unwrap_err();
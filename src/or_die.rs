@@ -0,0 +1,133 @@
+use crate::prelude::*;
+
+/// An [`Allocator`] which never returns [`AllocError`]:
+/// if `A` fails, [`Self::handler`] is called with the requested [`Layout`], and must diverge.
+///
+/// This suits code which would rather abort than handle failure,
+/// over an otherwise fallible stack.
+#[derive(Debug, Clone, Copy)]
+pub struct OrDie<A> {
+    pub inner: A,
+    pub handler: fn(Layout) -> !,
+}
+
+impl<A> OrDie<A> {
+    /// Fail with the global [`handle_alloc_error`](alloc::alloc::handle_alloc_error),
+    /// which aborts by default.
+    #[cfg(feature = "alloc")]
+    pub const fn new(inner: A) -> Self {
+        Self {
+            inner,
+            handler: alloc::alloc::handle_alloc_error,
+        }
+    }
+    pub const fn with_handler(inner: A, handler: fn(Layout) -> !) -> Self {
+        Self { inner, handler }
+    }
+    #[inline(always)]
+    fn or_handle(&self, layout: Layout, res: Result<NonNull<[u8]>, AllocError>) -> NonNull<[u8]> {
+        match res {
+            Ok(it) => it,
+            Err(AllocError) => (self.handler)(layout),
+        }
+    }
+}
+
+unsafe impl<A> Allocator for OrDie<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        Ok(self.or_handle(layout, self.inner.allocate(layout)))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        Ok(self.or_handle(layout, self.inner.allocate_zeroed(layout)))
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        Ok(self.or_handle(new_layout, self.inner.grow(ptr, old_layout, new_layout)))
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        Ok(self.or_handle(
+            new_layout,
+            self.inner.grow_zeroed(ptr, old_layout, new_layout),
+        ))
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        Ok(self.or_handle(new_layout, self.inner.shrink(ptr, old_layout, new_layout)))
+    }
+}
+
+unsafe impl<A> AllocatesZeroed for OrDie<A> where A: AllocatesZeroed {}
+
+impl<A> DeallocateAll for OrDie<A>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all()
+    }
+}
+
+unsafe impl<A> Owns for OrDie<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A> UsableSize for OrDie<A>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A> Trim for OrDie<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[test]
+#[should_panic = "out of memory allocating 8 bytes"]
+fn handler() {
+    fn die(layout: Layout) -> ! {
+        panic!("out of memory allocating {} bytes", layout.size())
+    }
+    let _ = Box::new_in(1u64, OrDie::with_handler(Null, die));
+}
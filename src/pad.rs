@@ -0,0 +1,59 @@
+use crate::{prelude::*, Rounded, SizeClasses};
+
+/// The size of a cache line, including adjacent lines which are prefetched together.
+#[cfg(any(
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    target_arch = "powerpc64"
+))]
+pub const CACHE_LINE: usize = 128;
+/// The size of a cache line, including adjacent lines which are prefetched together.
+#[cfg(not(any(
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    target_arch = "powerpc64"
+)))]
+pub const CACHE_LINE: usize = 64;
+
+/// [`SizeClasses`] which align each allocation to a [`CACHE_LINE`],
+/// and pad it to a multiple of one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct CacheLines;
+
+impl SizeClasses for CacheLines {
+    #[inline(always)]
+    fn class(&self, size: usize) -> Option<usize> {
+        size.checked_next_multiple_of(CACHE_LINE)
+    }
+    #[inline(always)]
+    fn round(&self, layout: Layout) -> Option<Layout> {
+        Some(layout.align_to(CACHE_LINE).ok()?.pad_to_align())
+    }
+}
+
+/// An [`Allocator`] which aligns each allocation to a [`CACHE_LINE`],
+/// and pads it to a multiple of one.
+///
+/// This prevents false sharing between adjacent allocations,
+/// e.g for per-thread state in concurrent data structures.
+/// The whole padded block is returned.
+pub type PadToCacheLine<A> = Rounded<A, CacheLines>;
+
+#[cfg(feature = "malloc")]
+#[test]
+fn pad_to_cache_line() {
+    let a = Malloc.stats().pad_to_cache_line();
+    let first = Box::new_in(1u8, &a);
+    let second = Box::new_in(2u8, &a);
+    for it in [&first, &second] {
+        assert_eq!(NonNull::from(&**it).as_ptr() as usize % CACHE_LINE, 0);
+    }
+    assert_eq!(a.inner.snapshot().live, 2 * CACHE_LINE);
+    drop((first, second));
+    assert_eq!(a.inner.snapshot().live, 0);
+    let inline = Inline::<{ 4 * CACHE_LINE }>::new();
+    let a = (&inline).pad_to_cache_line();
+    let it = Box::new_in(1u32, &a);
+    assert!(a.owns(NonNull::from(&*it).cast(), Layout::new::<u32>()));
+    assert_eq!(CacheLines.class(CACHE_LINE + 1), Some(2 * CACHE_LINE));
+}
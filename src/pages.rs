@@ -0,0 +1,925 @@
+use crate::{prelude::*, spin::Spin};
+use core::{
+    cmp, mem, ptr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+#[cfg(unix)]
+mod sys {
+    use core::ptr::{self, NonNull};
+
+    pub fn page_size() -> usize {
+        unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+    }
+    pub unsafe fn map(len: usize) -> Option<NonNull<u8>> {
+        map_with_flags(len, 0)
+    }
+    pub unsafe fn map_with_flags(len: usize, flags: libc::c_int) -> Option<NonNull<u8>> {
+        match libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | flags,
+            -1,
+            0,
+        ) {
+            libc::MAP_FAILED => None,
+            it => NonNull::new(it.cast()),
+        }
+    }
+    pub unsafe fn unmap(base: NonNull<u8>, len: usize) {
+        libc::munmap(base.as_ptr().cast(), len);
+    }
+    /// Unmapping also unlocks.
+    pub unsafe fn lock(base: NonNull<u8>, len: usize) -> bool {
+        libc::mlock(base.as_ptr().cast(), len) == 0
+    }
+    pub unsafe fn protect_none(base: NonNull<u8>, len: usize) -> bool {
+        libc::mprotect(base.as_ptr().cast(), len, libc::PROT_NONE) == 0
+    }
+    pub unsafe fn decommit(base: NonNull<u8>, len: usize, decommit: super::Decommit) {
+        let advice = match decommit {
+            super::Decommit::Eager => libc::MADV_DONTNEED,
+            super::Decommit::Lazy => libc::MADV_FREE,
+            super::Decommit::Never => return,
+        };
+        // `MADV_FREE` isn't supported by older kernels
+        if libc::madvise(base.as_ptr().cast(), len, advice) != 0 {
+            libc::madvise(base.as_ptr().cast(), len, libc::MADV_DONTNEED);
+        }
+    }
+    /// Decommitted pages are faulted back in on access.
+    pub unsafe fn recommit(_: NonNull<u8>, _: usize) {}
+    pub unsafe fn advise_thp(base: NonNull<u8>, len: usize, hint: super::ThpHint) {
+        #[cfg(target_os = "linux")]
+        let advice = match hint {
+            super::ThpHint::Unset => return,
+            super::ThpHint::Huge => libc::MADV_HUGEPAGE,
+            super::ThpHint::NoHuge => libc::MADV_NOHUGEPAGE,
+        };
+        #[cfg(not(target_os = "linux"))]
+        let _ = (base, len, hint);
+        // only a hint, so failure is fine
+        #[cfg(target_os = "linux")]
+        libc::madvise(base.as_ptr().cast(), len, advice);
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use core::{mem::MaybeUninit, ptr::NonNull};
+    use windows_sys::Win32::System::{
+        Memory::{
+            VirtualAlloc, VirtualFree, VirtualLock, VirtualProtect, MEM_COMMIT, MEM_DECOMMIT,
+            MEM_RELEASE, MEM_RESERVE, MEM_RESET, PAGE_NOACCESS, PAGE_READWRITE,
+        },
+        SystemInformation::GetSystemInfo,
+    };
+
+    pub fn page_size() -> usize {
+        let mut info = MaybeUninit::uninit();
+        unsafe { GetSystemInfo(info.as_mut_ptr()) };
+        unsafe { info.assume_init() }.dwPageSize as usize
+    }
+    pub unsafe fn map(len: usize) -> Option<NonNull<u8>> {
+        NonNull::new(
+            VirtualAlloc(
+                core::ptr::null(),
+                len,
+                MEM_COMMIT | MEM_RESERVE,
+                PAGE_READWRITE,
+            )
+            .cast(),
+        )
+    }
+    pub unsafe fn unmap(base: NonNull<u8>, _: usize) {
+        VirtualFree(base.as_ptr().cast(), 0, MEM_RELEASE);
+    }
+    /// Unmapping also unlocks.
+    pub unsafe fn lock(base: NonNull<u8>, len: usize) -> bool {
+        VirtualLock(base.as_ptr().cast(), len) != 0
+    }
+    pub unsafe fn protect_none(base: NonNull<u8>, len: usize) -> bool {
+        let mut old = 0;
+        VirtualProtect(base.as_ptr().cast(), len, PAGE_NOACCESS, &mut old) != 0
+    }
+    pub unsafe fn decommit(base: NonNull<u8>, len: usize, decommit: super::Decommit) {
+        match decommit {
+            super::Decommit::Eager => {
+                VirtualFree(base.as_ptr().cast(), len, MEM_DECOMMIT);
+            }
+            super::Decommit::Lazy => {
+                VirtualAlloc(base.as_ptr().cast(), len, MEM_RESET, PAGE_READWRITE);
+            }
+            super::Decommit::Never => {}
+        }
+    }
+    pub unsafe fn recommit(base: NonNull<u8>, len: usize) {
+        VirtualAlloc(base.as_ptr().cast(), len, MEM_COMMIT, PAGE_READWRITE);
+    }
+    /// Windows doesn't have transparent huge pages.
+    pub unsafe fn advise_thp(_: NonNull<u8>, _: usize, _: super::ThpHint) {}
+}
+
+pub(crate) use sys::page_size;
+
+/// Written after the body of each mapping.
+struct Node {
+    prev: Option<NonNull<Node>>,
+    next: Option<NonNull<Node>>,
+    base: NonNull<u8>,
+    len: usize,
+}
+
+/// The offset of the [`Node`] from the start of the body.
+#[inline(always)]
+fn node_offset(layout: Layout) -> Option<usize> {
+    layout
+        .size()
+        .checked_next_multiple_of(mem::align_of::<Node>())
+}
+
+/// How a [retaining](Pages::retaining) [`Pages`] returns the memory of freed mappings to the OS.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Decommit {
+    /// Immediately, with `MADV_DONTNEED` on Unix and `MEM_DECOMMIT` on Windows,
+    /// so resident memory drops straight away.
+    Eager,
+    /// When the OS is under memory pressure, with `MADV_FREE` on Unix and `MEM_RESET` on Windows,
+    /// which is cheaper if the memory is soon reused.
+    Lazy,
+    /// Keep the memory resident.
+    Never,
+}
+
+/// Whether [`Pages`] asks for its mappings to be backed by transparent huge pages,
+/// with `madvise` on Linux.
+///
+/// This is ignored on other platforms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum ThpHint {
+    /// Leave it to the system-wide setting.
+    #[default]
+    Unset,
+    /// `MADV_HUGEPAGE`.
+    Huge,
+    /// `MADV_NOHUGEPAGE`.
+    NoHuge,
+}
+
+/// An allocator which maps whole pages directly from the OS,
+/// using `mmap` on Unix and `VirtualAlloc` on Windows.
+///
+/// Each allocation gets its own mapping, rounded up to the [page size](Self::page_size).
+/// Live mappings are tracked to implement [`Owns`].
+///
+/// By default, freed mappings are unmapped.
+/// A [retaining](Self::retaining) [`Pages`] instead keeps them for reuse by allocations
+/// which need the same number of pages, returning their memory to the OS according to a [`Decommit`] policy,
+/// until they are unmapped by [`Self::purge`] or drop.
+/// The last page of each retained mapping holds bookkeeping, so is never decommitted.
+///
+/// Large mappings, e.g for the chunks of an [`Arena`], may benefit from [`Self::with_thp`]:
+/// ```
+/// # use composable_allocators::*;
+/// let arena = Arena::new(Pages::new().with_thp(ThpHint::Huge), 4 << 20);
+/// let _ = allocator_api2::boxed::Box::new_in(1u8, &arena);
+/// ```
+#[derive(Debug, Default)]
+pub struct Pages {
+    mapped: Spin<Option<NonNull<Node>>>,
+    /// Singly linked through [`Node::next`].
+    retained: Spin<Option<NonNull<Node>>>,
+    decommit: Option<Decommit>,
+    thp: ThpHint,
+}
+
+unsafe impl Send for Pages {}
+unsafe impl Sync for Pages {}
+
+impl Pages {
+    pub const fn new() -> Self {
+        Self {
+            mapped: Spin::new(None),
+            retained: Spin::new(None),
+            decommit: None,
+            thp: ThpHint::Unset,
+        }
+    }
+    /// Keep freed mappings for reuse, see [`Decommit`].
+    pub const fn retaining(decommit: Decommit) -> Self {
+        Self {
+            mapped: Spin::new(None),
+            retained: Spin::new(None),
+            decommit: Some(decommit),
+            thp: ThpHint::Unset,
+        }
+    }
+    /// Apply `hint` to new mappings.
+    pub fn with_thp(mut self, hint: ThpHint) -> Self {
+        self.thp = hint;
+        self
+    }
+    pub fn thp(&self) -> ThpHint {
+        self.thp
+    }
+    pub fn page_size() -> usize {
+        page_size()
+    }
+    /// Unmap every retained mapping.
+    pub fn purge(&self) {
+        let mut retained = self.retained.lock();
+        while let Some(node) = *retained {
+            let Node {
+                next, base, len, ..
+            } = unsafe { ptr::read(node.as_ptr()) };
+            *retained = next;
+            unsafe { sys::unmap(base, len) };
+        }
+    }
+    /// Keep a mapping for reuse, decommitting all but its last page.
+    ///
+    /// # Safety
+    /// - `base` and `len` must be an untracked mapping.
+    #[inline(always)]
+    unsafe fn retain(&self, base: NonNull<u8>, len: usize, decommit: Decommit) {
+        let page = page_size();
+        sys::decommit(base, len - page, decommit);
+        let node = Self::retained_node(base, len);
+        let mut retained = self.retained.lock();
+        node.as_ptr().write(Node {
+            prev: None,
+            next: *retained,
+            base,
+            len,
+        });
+        *retained = Some(node);
+    }
+    /// Take a retained mapping of exactly `len` bytes.
+    #[inline(always)]
+    fn take_retained(&self, len: usize) -> Option<NonNull<u8>> {
+        let mut retained = self.retained.lock();
+        let mut link: *mut Option<NonNull<Node>> = &mut *retained;
+        unsafe {
+            while let Some(node) = *link {
+                if (*node.as_ptr()).len == len {
+                    *link = (*node.as_ptr()).next;
+                    let base = (*node.as_ptr()).base;
+                    sys::recommit(base, len);
+                    return Some(base);
+                }
+                link = &mut (*node.as_ptr()).next;
+            }
+        }
+        None
+    }
+    #[inline(always)]
+    unsafe fn retained_node(base: NonNull<u8>, len: usize) -> NonNull<Node> {
+        NonNull::new_unchecked(base.as_ptr().add(len - mem::size_of::<Node>()).cast())
+    }
+    /// Returns the allocation, and whether it reused a retained mapping.
+    #[inline(always)]
+    fn allocate_reusing(&self, layout: Layout) -> Result<(NonNull<[u8]>, bool), AllocError> {
+        let mut reused = false;
+        let res = self.map_with(layout, page_size(), |len| unsafe {
+            if self.decommit.is_some() {
+                if let Some(base) = self.take_retained(len) {
+                    reused = true;
+                    return Some(base);
+                }
+            }
+            let base = sys::map(len)?;
+            sys::advise_thp(base, len, self.thp);
+            Some(base)
+        })?;
+        Ok((res, reused))
+    }
+    /// Map a multiple of `granule` bytes using `map`, and track it.
+    #[inline(always)]
+    fn map_with(
+        &self,
+        layout: Layout,
+        granule: usize,
+        map: impl FnOnce(usize) -> Option<NonNull<u8>>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let node_offset = node_offset(layout).ok_or(AllocError)?;
+        let padding = layout.align().saturating_sub(granule);
+        let len = node_offset
+            .checked_add(mem::size_of::<Node>())
+            .and_then(|it| it.checked_add(padding))
+            .and_then(|it| it.checked_next_multiple_of(granule))
+            .ok_or(AllocError)?;
+        let base = map(len).ok_or(AllocError)?;
+        let body = unsafe {
+            NonNull::new_unchecked(
+                base.as_ptr()
+                    .add(base.as_ptr().align_offset(layout.align())),
+            )
+        };
+        let node = unsafe { Self::node(body, layout) };
+        let mut mapped = self.mapped.lock();
+        unsafe {
+            node.as_ptr().write(Node {
+                prev: None,
+                next: *mapped,
+                base,
+                len,
+            });
+            if let Some(next) = *mapped {
+                (*next.as_ptr()).prev = Some(node);
+            }
+        }
+        *mapped = Some(node);
+        Ok(NonNull::slice_from_raw_parts(body, node_offset))
+    }
+    /// Stop tracking an allocation, returning its mapping.
+    ///
+    /// # Safety
+    /// - `ptr` and `layout` must be from [`Self::map_with`].
+    #[inline(always)]
+    unsafe fn untrack(&self, ptr: NonNull<u8>, layout: Layout) -> (NonNull<u8>, usize) {
+        let Node {
+            prev,
+            next,
+            base,
+            len,
+        } = ptr::read(Self::node(ptr, layout).as_ptr());
+        let mut mapped = self.mapped.lock();
+        match prev {
+            Some(prev) => (*prev.as_ptr()).next = next,
+            None => *mapped = next,
+        }
+        if let Some(next) = next {
+            (*next.as_ptr()).prev = prev;
+        }
+        (base, len)
+    }
+    /// Find the [`Node`] for an allocation.
+    #[inline(always)]
+    unsafe fn node(ptr: NonNull<u8>, layout: Layout) -> NonNull<Node> {
+        let offset = node_offset(layout).unwrap_unchecked();
+        NonNull::new_unchecked(ptr.as_ptr().add(offset).cast())
+    }
+}
+
+unsafe impl Allocator for Pages {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        Ok(self.allocate_reusing(layout)?.0)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let (base, len) = self.untrack(ptr, layout);
+        match self.decommit {
+            Some(decommit) => self.retain(base, len, decommit),
+            None => sys::unmap(base, len),
+        }
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let (ptr, reused) = self.allocate_reusing(layout)?;
+        // fresh pages are always zeroed
+        if reused {
+            unsafe { ptr.cast::<u8>().as_ptr().write_bytes(0, ptr.len()) }
+        }
+        Ok(ptr)
+    }
+}
+
+impl Drop for Pages {
+    fn drop(&mut self) {
+        self.purge()
+    }
+}
+
+unsafe impl Owns for Pages {
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        let start = ptr.as_ptr() as usize;
+        let Some(end) = start.checked_add(layout.size()) else {
+            return false;
+        };
+        let mapped = self.mapped.lock();
+        let mut node = *mapped;
+        while let Some(it) = node {
+            let Node {
+                next, base, len, ..
+            } = unsafe { ptr::read(it.as_ptr()) };
+            let base = base.as_ptr() as usize;
+            if base <= start && end <= base + len {
+                return true;
+            }
+            node = next;
+        }
+        false
+    }
+}
+
+/// Unmaps retained mappings, see [`Pages::purge`].
+impl Trim for Pages {
+    fn trim(&self) {
+        self.purge()
+    }
+}
+
+impl UsableSize for Pages {
+    /// The rest of the mapping is used for tracking.
+    #[inline(always)]
+    unsafe fn usable_size(&self, _: NonNull<u8>, layout: Layout) -> usize {
+        node_offset(layout).unwrap_unchecked()
+    }
+}
+
+/// Like [`Pages`], but each mapping is locked into memory with `mlock` on Unix
+/// and `VirtualLock` on Windows, so secrets aren't swapped to disk.
+///
+/// Locking fails if it would exceed the process's limit (e.g `RLIMIT_MEMLOCK`).
+/// By default the allocation is still returned, unlocked, and counted in [`Self::lock_failures`].
+/// Use [`Self::strict`] to fail the allocation instead.
+///
+/// Freed memory is returned to the OS, which zeroes it before reuse,
+/// but combine with [`WipeOnFree`] to also wipe it immediately.
+#[derive(Debug, Default)]
+pub struct Secure {
+    pages: Pages,
+    strict: bool,
+    lock_failures: AtomicUsize,
+}
+
+impl Secure {
+    pub const fn new() -> Self {
+        Self {
+            pages: Pages::new(),
+            strict: false,
+            lock_failures: AtomicUsize::new(0),
+        }
+    }
+    /// Fail allocations which can't be locked.
+    pub const fn strict() -> Self {
+        Self {
+            pages: Pages::new(),
+            strict: true,
+            lock_failures: AtomicUsize::new(0),
+        }
+    }
+    /// The number of allocations which couldn't be locked.
+    pub fn lock_failures(&self) -> usize {
+        self.lock_failures.load(Ordering::Acquire)
+    }
+}
+
+unsafe impl Allocator for Secure {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.pages.map_with(layout, page_size(), |len| unsafe {
+            let base = sys::map(len)?;
+            if !sys::lock(base, len) {
+                self.lock_failures.fetch_add(1, Ordering::AcqRel);
+                if self.strict {
+                    sys::unmap(base, len);
+                    return None;
+                }
+            }
+            Some(base)
+        })
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.pages.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        // fresh pages are always zeroed
+        self.allocate(layout)
+    }
+}
+
+unsafe impl AllocatesZeroed for Secure {}
+
+unsafe impl Owns for Secure {
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.pages.owns(ptr, layout)
+    }
+}
+
+impl UsableSize for Secure {
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.pages.usable_size(ptr, layout)
+    }
+}
+
+/// Page-aligned memory which is locked into RAM, with `mlock` on Unix and `VirtualLock` on Windows,
+/// so it may be handed to devices for DMA, registered as pinned for GPU transfers,
+/// or used for `io_uring` fixed buffers.
+///
+/// Unlike [`Secure`], allocations which can't be locked always fail.
+/// Locked memory is a scarce resource, so cap it with a [`SizeLimit`]:
+/// ```
+/// # use composable_allocators::*;
+/// let a = Pinned::new().limit_size(1 << 20);
+/// if let Ok(buf) = allocator_api2::boxed::Box::try_new_in([0u8; 4096], &a) {
+///     assert_eq!(buf.as_ptr() as usize % Pages::page_size(), 0);
+///     assert!(a.inner.locked_bytes() >= 4096);
+/// };
+/// ```
+/// The limit counts requested bytes,
+/// while [`Self::locked_bytes`] counts whole pages, including tracking overhead.
+#[derive(Debug, Default)]
+pub struct Pinned {
+    pages: Pages,
+    locked: AtomicUsize,
+}
+
+impl Pinned {
+    pub const fn new() -> Self {
+        Self {
+            pages: Pages::new(),
+            locked: AtomicUsize::new(0),
+        }
+    }
+    /// Bytes currently locked into memory.
+    pub fn locked_bytes(&self) -> usize {
+        self.locked.load(Ordering::Acquire)
+    }
+}
+
+unsafe impl Allocator for Pinned {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.pages.map_with(layout, page_size(), |len| unsafe {
+            let base = sys::map(len)?;
+            if !sys::lock(base, len) {
+                sys::unmap(base, len);
+                return None;
+            }
+            self.locked.fetch_add(len, Ordering::AcqRel);
+            Some(base)
+        })
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let (base, len) = self.pages.untrack(ptr, layout);
+        self.locked.fetch_sub(len, Ordering::AcqRel);
+        sys::unmap(base, len)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        // fresh pages are always zeroed
+        self.allocate(layout)
+    }
+}
+
+unsafe impl AllocatesZeroed for Pinned {}
+
+unsafe impl Owns for Pinned {
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.pages.owns(ptr, layout)
+    }
+}
+
+impl UsableSize for Pinned {
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.pages.usable_size(ptr, layout)
+    }
+}
+
+/// Which side of each allocation [`Fenced`] puts its guard page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum FenceSide {
+    /// Catch overflows.
+    #[default]
+    After,
+    /// Catch underflows.
+    Before,
+}
+
+/// An [`Allocator`] which maps each allocation on its own pages,
+/// with an inaccessible guard page on one side,
+/// so that an overflow (or underflow) faults at the offending instruction.
+///
+/// This is the heavyweight sibling of [`Guard`], which only notices corruption on free,
+/// and costs at least two pages per allocation.
+///
+/// With [`FenceSide::After`], the body ends as close to the guard page as its alignment allows.
+/// Alignments larger than the page size are not supported.
+///
+/// Mappings aren't tracked, so this doesn't implement [`Owns`],
+/// but see [`Tracked`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Fenced {
+    pub side: FenceSide,
+}
+
+impl Fenced {
+    pub const fn new(side: FenceSide) -> Self {
+        Self { side }
+    }
+    /// Returns `(data, body_offset)`, where `data` is the length of the accessible pages,
+    /// and `body_offset` is from the start of the mapping.
+    #[inline(always)]
+    fn geometry(&self, layout: Layout, page: usize) -> Option<(usize, usize)> {
+        if layout.align() > page {
+            return None;
+        }
+        let data = cmp::max(layout.size().checked_next_multiple_of(page)?, page);
+        Some(match self.side {
+            FenceSide::After => (data, (data - layout.size()) & !(layout.align() - 1)),
+            FenceSide::Before => (data, page),
+        })
+    }
+}
+
+unsafe impl Allocator for Fenced {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let page = page_size();
+        let (data, body_offset) = self.geometry(layout, page).ok_or(AllocError)?;
+        let len = data.checked_add(page).ok_or(AllocError)?;
+        let base = unsafe { sys::map(len) }.ok_or(AllocError)?;
+        let guard = match self.side {
+            FenceSide::After => unsafe { NonNull::new_unchecked(base.as_ptr().add(data)) },
+            FenceSide::Before => base,
+        };
+        if !unsafe { sys::protect_none(guard, page) } {
+            unsafe { sys::unmap(base, len) };
+            return Err(AllocError);
+        }
+        let body = unsafe { NonNull::new_unchecked(base.as_ptr().add(body_offset)) };
+        Ok(NonNull::slice_from_raw_parts(body, layout.size()))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let page = page_size();
+        let (data, body_offset) = self.geometry(layout, page).unwrap_unchecked();
+        let base = NonNull::new_unchecked(ptr.as_ptr().sub(body_offset));
+        sys::unmap(base, data + page)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        // fresh pages are always zeroed
+        self.allocate(layout)
+    }
+}
+
+unsafe impl AllocatesZeroed for Fenced {}
+
+/// The size of pages to request from [`HugePages`].
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum HugePageSize {
+    #[default]
+    TwoMiB,
+    OneGiB,
+}
+
+#[cfg(target_os = "linux")]
+impl HugePageSize {
+    pub const fn bytes(&self) -> usize {
+        match self {
+            HugePageSize::TwoMiB => 2 << 20,
+            HugePageSize::OneGiB => 1 << 30,
+        }
+    }
+}
+
+/// Like [`Pages`], but requests huge pages with `MAP_HUGETLB`,
+/// falling back to normal pages if none are available.
+///
+/// Allocations are rounded up to [`Self::size`],
+/// or to the normal page size if they fall back.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Default)]
+pub struct HugePages {
+    pages: Pages,
+    size: HugePageSize,
+}
+
+#[cfg(target_os = "linux")]
+impl HugePages {
+    pub const fn new(size: HugePageSize) -> Self {
+        Self {
+            pages: Pages::new(),
+            size,
+        }
+    }
+    pub fn size(&self) -> HugePageSize {
+        self.size
+    }
+}
+
+#[cfg(target_os = "linux")]
+unsafe impl Allocator for HugePages {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        const MAP_HUGE_SHIFT: libc::c_int = 26;
+        let bytes = self.size.bytes();
+        let flags = libc::MAP_HUGETLB | (bytes.trailing_zeros() as libc::c_int) << MAP_HUGE_SHIFT;
+        self.pages
+            .map_with(layout, bytes, |len| unsafe {
+                sys::map_with_flags(len, flags)
+            })
+            .or_else(|_| self.pages.allocate(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.pages.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        // fresh pages are always zeroed
+        self.allocate(layout)
+    }
+}
+
+#[cfg(target_os = "linux")]
+unsafe impl AllocatesZeroed for HugePages {}
+
+#[cfg(target_os = "linux")]
+unsafe impl Owns for HugePages {
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.pages.owns(ptr, layout)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl UsableSize for HugePages {
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.pages.usable_size(ptr, layout)
+    }
+}
+
+#[test]
+fn pages() {
+    let a = Pages::new();
+    let small = Box::new_in(1u8, &a);
+    assert_eq!(
+        NonNull::from(&*small).as_ptr() as usize % Pages::page_size(),
+        0
+    );
+    let big = Box::new_in([1u8; 8192], &a);
+    assert!(a.owns(NonNull::from(&*small).cast(), Layout::new::<u8>()));
+    assert!(a.owns(NonNull::from(&*big).cast(), Layout::new::<[u8; 8192]>()));
+    let ptr = NonNull::from(&*small).cast();
+    drop(small);
+    assert!(!a.owns(ptr, Layout::new::<u8>()));
+    let _ = Box::new_in(1u8, Pages::new().or(Null));
+}
+
+#[test]
+fn retaining() {
+    for decommit in [Decommit::Eager, Decommit::Lazy, Decommit::Never] {
+        let a = Pages::retaining(decommit);
+        let first = Box::new_in([1u8; 8192], &a);
+        let ptr = NonNull::from(&*first);
+        drop(first);
+        let second = Box::<[u8; 8192], _>::new_zeroed_in(&a);
+        assert_eq!(NonNull::from(&*second).cast(), ptr);
+        assert!(unsafe { second.assume_init() }.iter().all(|it| *it == 0));
+        let small = Box::new_in(1u8, &a);
+        assert_ne!(NonNull::from(&*small).cast(), ptr);
+        a.purge();
+    }
+}
+
+#[test]
+fn retaining_zeroed() {
+    let a = Pages::retaining(Decommit::Never);
+    let layout = Layout::new::<u8>();
+    unsafe {
+        let first = a.allocate(layout).unwrap();
+        first.cast::<u8>().write_bytes(1, first.len());
+        a.deallocate(first.cast(), layout);
+        let second = a.allocate_zeroed(layout).unwrap();
+        assert_eq!(second, first);
+        assert!(second.as_ref().iter().all(|it| *it == 0));
+        a.deallocate(second.cast(), layout);
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "std"))]
+#[test]
+fn thp() {
+    for (hint, flag) in [(ThpHint::Huge, "hg"), (ThpHint::NoHuge, "nh")] {
+        let a = Pages::new().with_thp(hint);
+        assert_eq!(a.thp(), hint);
+        let it = Box::new_in([1u8; 8192], &a);
+        // kernels with transparent huge pages record the advice in the mapping's flags
+        if std::path::Path::new("/sys/kernel/mm/transparent_hugepage").exists() {
+            let addr = NonNull::from(&*it).as_ptr() as usize;
+            let smaps = std::fs::read_to_string("/proc/self/smaps").unwrap();
+            let mut contains = false;
+            let flags = smaps.lines().find_map(|line| {
+                if let Some((start, end)) = line
+                    .split_whitespace()
+                    .next()
+                    .and_then(|range| range.split_once('-'))
+                {
+                    if let (Ok(start), Ok(end)) = (
+                        usize::from_str_radix(start, 16),
+                        usize::from_str_radix(end, 16),
+                    ) {
+                        contains = (start..end).contains(&addr);
+                    }
+                }
+                line.strip_prefix("VmFlags:").filter(|_| contains)
+            });
+            assert!(flags.unwrap().split_whitespace().any(|it| it == flag));
+        }
+        drop(it);
+    }
+}
+
+#[test]
+fn secure() {
+    let a = Secure::new().wipe_on_free();
+    let it = Box::new_in([1u8; 16], &a);
+    assert!(a.owns(NonNull::from(&*it).cast(), Layout::new::<[u8; 16]>()));
+    let strict = Secure::strict();
+    let locked = Box::try_new_in(1u8, &strict).is_ok();
+    assert_eq!(strict.lock_failures(), usize::from(!locked));
+}
+
+#[test]
+fn pinned() {
+    let a = Pinned::new();
+    // locking may be forbidden by `RLIMIT_MEMLOCK`
+    if let Ok(it) = Box::try_new_in([1u8; 16], &a) {
+        assert_eq!(
+            NonNull::from(&*it).as_ptr() as usize % Pages::page_size(),
+            0
+        );
+        assert_eq!(a.locked_bytes(), Pages::page_size());
+        drop(it);
+    }
+    assert_eq!(a.locked_bytes(), 0);
+}
+
+#[test]
+fn fenced() {
+    let page = Pages::page_size();
+    let after = Fenced::new(FenceSide::After);
+    let it = Box::new_in([1u8; 24], after);
+    assert_eq!((NonNull::from(&*it).as_ptr() as usize + 24) % page, 0);
+    let before = Fenced::new(FenceSide::Before);
+    let it = Box::new_in(1u64, before);
+    assert_eq!(NonNull::from(&*it).as_ptr() as usize % page, 0);
+    before
+        .allocate(Layout::from_size_align(1, page * 2).unwrap())
+        .unwrap_err();
+}
+
+#[test]
+fn allocates_zeroed() {
+    fn check(a: impl AllocatesZeroed) {
+        let it = Box::<[u64; 1024], _>::new_uninit_in(a);
+        assert!(unsafe { it.assume_init() }.iter().all(|it| *it == 0));
+    }
+    let a = Fenced::default().limit_size(1 << 16).stats();
+    check(&a);
+    check(&a);
+    check(Secure::new().wipe_on_free().or(Null));
+}
+
+#[cfg(unix)]
+#[test]
+fn fenced_overflow_faults() {
+    let it = Box::new_in([0u8; 8], Fenced::default());
+    let ptr = NonNull::from(&*it).cast::<u8>().as_ptr();
+    unsafe {
+        match libc::fork() {
+            0 => {
+                ptr.add(8).write_volatile(1);
+                libc::_exit(0)
+            }
+            child => {
+                let mut status = 0;
+                assert_eq!(libc::waitpid(child, &mut status, 0), child);
+                assert!(libc::WIFSIGNALED(status));
+                let signal = libc::WTERMSIG(status);
+                assert!(signal == libc::SIGSEGV || signal == libc::SIGBUS);
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn huge_pages() {
+    let a = HugePages::new(HugePageSize::TwoMiB);
+    let it = Box::new_in(1u8, &a);
+    assert!(a.owns(NonNull::from(&*it).cast(), Layout::new::<u8>()));
+    // whether or not huge pages are available, alignment is respected
+    let layout = Layout::from_size_align(8, 1 << 16).unwrap();
+    let ptr = a.allocate(layout).unwrap();
+    assert_eq!(ptr.cast::<u8>().as_ptr() as usize % layout.align(), 0);
+    unsafe {
+        ptr.cast::<u8>().write_bytes(1, ptr.len());
+        a.deallocate(ptr.cast(), layout);
+    }
+}
@@ -0,0 +1,498 @@
+use crate::{
+    arena::Checkpoint,
+    prelude::*,
+    recycle::{class_of, layout_of, Free, CLASSES},
+    spin::Spin,
+};
+use core::mem::{self, MaybeUninit};
+
+/// The size of the first chunk requested from upstream, if there's no initial buffer.
+const INITIAL_SIZE: usize = 1024;
+
+/// An [`Allocator`] which bump-allocates from an optional initial buffer,
+/// and then from chunks requested from `A`, each twice the size of the last.
+///
+/// Deallocation does nothing, and memory is only returned to `A`
+/// by [`Self::release`] or when this is dropped,
+/// like C++'s `std::pmr::monotonic_buffer_resource`.
+///
+/// # Porting from `std::pmr`
+/// | C++                                      | Rust                        |
+/// | ---------------------------------------- | --------------------------- |
+/// | `std::pmr::memory_resource*`             | `&impl` [`Allocator`]       |
+/// | `std::pmr::monotonic_buffer_resource`    | [`MonotonicBufferResource`] |
+/// | `std::pmr::new_delete_resource()`        | `Malloc`                    |
+/// | `std::pmr::unsynchronized_pool_resource` | [`UnsyncPoolResource`]      |
+/// | `std::pmr::synchronized_pool_resource`   | [`SyncPoolResource`]        |
+/// | `std::pmr::null_memory_resource()`       | [`Null`]                    |
+/// | `upstream_resource()`                    | `upstream()`                |
+/// | `do_is_equal`                            | [`Owns`]                    |
+///
+/// Unlike C++, deallocation needs the [`Layout`] of the allocation,
+/// and the upstream allocator is owned rather than referenced,
+/// though `&A` may be used to share one.
+/// ```
+/// # use composable_allocators::*;
+/// # use core::mem::MaybeUninit;
+/// let mut buffer = [MaybeUninit::uninit(); 256];
+/// let mut resource = MonotonicBufferResource::with_buffer(&mut buffer, Malloc.stats());
+/// let mut v = allocator_api2::vec::Vec::new_in(&resource);
+/// v.extend(0..64u64);
+/// drop(v);
+/// assert_eq!(resource.upstream().snapshot().deallocations, 0);
+/// resource.release();
+/// assert_eq!(resource.upstream().snapshot().live, 0);
+/// ```
+#[derive(Debug)]
+pub struct MonotonicBufferResource<'a, A: Allocator> {
+    buffer: Region<'a>,
+    chunks: Arena<A>,
+}
+
+impl<'a, A: Allocator> MonotonicBufferResource<'a, A> {
+    pub const fn new(upstream: A) -> Self {
+        Self::with_initial_size(upstream, INITIAL_SIZE)
+    }
+    /// Request at least `initial_size` bytes from `upstream` the first time.
+    pub const fn with_initial_size(upstream: A, initial_size: usize) -> Self {
+        Self {
+            buffer: Region::new(&mut []),
+            chunks: Arena::growing(upstream, initial_size),
+        }
+    }
+    /// Allocate from `buffer` until it is exhausted,
+    /// and then request chunks from `upstream` of twice its size, and growing.
+    pub const fn with_buffer(buffer: &'a mut [MaybeUninit<u8>], upstream: A) -> Self {
+        let initial_size = match buffer.len() {
+            0 => INITIAL_SIZE,
+            len => len.saturating_mul(2),
+        };
+        Self {
+            buffer: Region::new(buffer),
+            chunks: Arena::growing(upstream, initial_size),
+        }
+    }
+    pub fn upstream(&self) -> &A {
+        self.chunks.inner()
+    }
+    /// Return every chunk to [`Self::upstream`],
+    /// and start allocating from the initial buffer again.
+    pub fn release(&mut self) {
+        unsafe {
+            self.buffer.deallocate_all();
+            self.chunks.rewind(Checkpoint::START)
+        }
+    }
+}
+
+unsafe impl<A> Allocator for MonotonicBufferResource<'_, A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.buffer
+            .allocate(layout)
+            .or_else(|_| self.chunks.allocate(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, _: NonNull<u8>, _: Layout) {}
+}
+
+unsafe impl<A> Owns for MonotonicBufferResource<'_, A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.buffer.owns(ptr, layout) || self.chunks.owns(ptr, layout)
+    }
+}
+
+/// An [`Allocator`] which serves allocations from a pool per power-of-two size class,
+/// up to [`Self::largest_block`], and sends larger allocations straight to `A`.
+///
+/// Each pool keeps freed blocks for reuse,
+/// and carves new blocks from chunks requested from `A`, each twice the size of the last.
+/// Memory is only returned to `A` by [`Self::release`] or when this is dropped,
+/// like C++'s `std::pmr::unsynchronized_pool_resource`.
+///
+/// Unlike C++, [`Self::release`] doesn't free larger allocations,
+/// which must be deallocated as usual.
+///
+/// This is not [`Sync`], see [`SyncPoolResource`].
+/// See [`MonotonicBufferResource`] for porting C++ code.
+/// ```
+/// # use composable_allocators::*;
+/// let mut resource = UnsyncPoolResource::new(Malloc.stats());
+/// for _ in 0..4 {
+///     let mut v = allocator_api2::vec::Vec::new_in(&resource);
+///     v.extend(0..64u64);
+/// }
+/// // the blocks for each size class are reused
+/// let chunks = resource.upstream().snapshot().allocations;
+/// drop(allocator_api2::vec![in &resource; 0u64; 64]);
+/// assert_eq!(resource.upstream().snapshot().allocations, chunks);
+/// resource.release();
+/// assert_eq!(resource.upstream().snapshot().live, 0);
+/// ```
+#[derive(Debug)]
+pub struct UnsyncPoolResource<A: Allocator> {
+    pools: Recycle<Arena<A>>,
+    largest_block: usize,
+}
+
+impl<A: Allocator> UnsyncPoolResource<A> {
+    pub const fn new(upstream: A) -> Self {
+        Self::with_largest_block(upstream, usize::MAX)
+    }
+    /// Pool allocations of at most `largest_block` bytes,
+    /// which is rounded up to a size class and limited to the largest size class.
+    pub const fn with_largest_block(upstream: A, largest_block: usize) -> Self {
+        let largest_class = layout_of(CLASSES - 1).size();
+        Self {
+            pools: Recycle::new(Arena::growing(upstream, INITIAL_SIZE), usize::MAX),
+            largest_block: match largest_block.checked_next_power_of_two() {
+                Some(it) if it < largest_class => it,
+                _ => largest_class,
+            },
+        }
+    }
+    /// The largest allocation which is pooled.
+    pub fn largest_block(&self) -> usize {
+        self.largest_block
+    }
+    pub fn upstream(&self) -> &A {
+        self.pools.inner().inner()
+    }
+    /// Return every pool's chunks to [`Self::upstream`].
+    pub fn release(&mut self) {
+        self.pools.purge();
+        unsafe { self.pools.inner().rewind(Checkpoint::START) }
+    }
+    #[inline(always)]
+    fn is_pooled(&self, layout: Layout) -> bool {
+        match class_of(layout) {
+            Some(class) => layout_of(class).size() <= self.largest_block,
+            None => false,
+        }
+    }
+}
+
+unsafe impl<A> Allocator for UnsyncPoolResource<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        match self.is_pooled(layout) {
+            true => self.pools.allocate(layout),
+            false => self.upstream().allocate(layout),
+        }
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        match self.is_pooled(layout) {
+            true => self.pools.deallocate(ptr, layout),
+            false => self.upstream().deallocate(ptr, layout),
+        }
+    }
+}
+
+unsafe impl<A> Owns for UnsyncPoolResource<A>
+where
+    A: Allocator + Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        match self.is_pooled(layout) {
+            true => self.pools.owns(ptr, layout),
+            false => self.upstream().owns(ptr, layout),
+        }
+    }
+}
+
+/// Written at the start of each chunk requested by a [`SyncPoolResource`].
+struct Chunk {
+    prev: Option<NonNull<Chunk>>,
+    layout: Layout,
+}
+
+/// The pool for one size class in a [`SyncPoolResource`].
+struct Class {
+    free: Option<NonNull<Free>>,
+    /// The next block to carve from the newest chunk, up to `end`.
+    cursor: usize,
+    end: usize,
+    chunks: Option<NonNull<Chunk>>,
+    next_chunk_blocks: usize,
+}
+
+unsafe impl Send for Class {}
+
+impl Class {
+    const EMPTY: Self = Self {
+        free: None,
+        cursor: 0,
+        end: 0,
+        chunks: None,
+        next_chunk_blocks: 0,
+    };
+    /// Request a chunk from `upstream`, with twice as many blocks as the last.
+    fn refill(&mut self, upstream: &impl Allocator, block: Layout) -> Result<(), AllocError> {
+        let blocks = match self.next_chunk_blocks {
+            0 => (INITIAL_SIZE / block.size()).max(1),
+            it => it,
+        };
+        let offset = mem::size_of::<Chunk>().next_multiple_of(block.align());
+        let layout = Layout::from_size_align(
+            block
+                .size()
+                .checked_mul(blocks)
+                .and_then(|it| it.checked_add(offset))
+                .ok_or(AllocError)?,
+            block.align().max(mem::align_of::<Chunk>()),
+        )
+        .map_err(|_| AllocError)?;
+        let chunk = upstream.allocate(layout)?.cast::<Chunk>();
+        unsafe {
+            chunk.as_ptr().write(Chunk {
+                prev: self.chunks,
+                layout,
+            })
+        };
+        self.chunks = Some(chunk);
+        self.cursor = chunk.as_ptr() as usize + offset;
+        self.end = chunk.as_ptr() as usize + layout.size();
+        self.next_chunk_blocks = blocks.saturating_mul(2);
+        Ok(())
+    }
+    #[inline(always)]
+    fn allocate(
+        &mut self,
+        upstream: &impl Allocator,
+        block: Layout,
+    ) -> Result<NonNull<u8>, AllocError> {
+        if let Some(it) = self.free {
+            self.free = unsafe { it.as_ptr().read() }.next;
+            return Ok(it.cast());
+        }
+        if self.cursor == self.end {
+            self.refill(upstream, block)?
+        }
+        let it = self.cursor;
+        self.cursor += block.size();
+        Ok(unsafe { NonNull::new_unchecked(it as *mut u8) })
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&mut self, ptr: NonNull<u8>) {
+        let free = ptr.cast::<Free>();
+        free.as_ptr().write(Free { next: self.free });
+        self.free = Some(free);
+    }
+    fn contains(&self, ptr: NonNull<u8>, block: Layout) -> bool {
+        let ptr = ptr.as_ptr() as usize;
+        let mut next = self.chunks;
+        while let Some(chunk) = next {
+            let Chunk { prev, layout } = unsafe { chunk.as_ptr().read() };
+            let start = chunk.as_ptr() as usize;
+            if (start..start + layout.size()).contains(&ptr) {
+                return ptr + block.size() <= start + layout.size();
+            }
+            next = prev;
+        }
+        false
+    }
+    /// Return every chunk to `upstream`.
+    fn release(&mut self, upstream: &impl Allocator) {
+        let mut next = self.chunks;
+        while let Some(chunk) = next {
+            let Chunk { prev, layout } = unsafe { chunk.as_ptr().read() };
+            unsafe { upstream.deallocate(chunk.cast(), layout) };
+            next = prev;
+        }
+        *self = Self::EMPTY;
+    }
+}
+
+/// A thread-safe [`Allocator`] which serves allocations from a pool per power-of-two size class,
+/// up to [`Self::largest_block`], and sends larger allocations straight to `A`.
+///
+/// Each pool has its own lock, so threads only contend when allocating the same size class,
+/// and carves new blocks from chunks requested from `A`, each twice the size of the last.
+/// Memory is only returned to `A` by [`Self::release`] or when this is dropped,
+/// like C++'s `std::pmr::synchronized_pool_resource`.
+///
+/// Unlike C++, [`Self::release`] doesn't free larger allocations,
+/// which must be deallocated as usual.
+///
+/// See [`UnsyncPoolResource`] for single-threaded use,
+/// and [`MonotonicBufferResource`] for porting C++ code.
+/// ```
+/// # use composable_allocators::*;
+/// static POOL: SyncPoolResource<Stats<Malloc>> = SyncPoolResource::new(Stats::new(Malloc));
+/// std::thread::scope(|s| {
+///     for _ in 0..4 {
+///         s.spawn(|| {
+///             let mut v = allocator_api2::vec::Vec::new_in(&POOL);
+///             v.extend(0..64u64);
+///         });
+///     }
+/// });
+/// assert!(POOL.upstream().snapshot().allocations > 0);
+/// ```
+#[derive(Debug)]
+pub struct SyncPoolResource<A: Allocator> {
+    upstream: A,
+    classes: [Spin<Class>; CLASSES],
+    largest_block: usize,
+}
+
+impl<A: Allocator> SyncPoolResource<A> {
+    pub const fn new(upstream: A) -> Self {
+        Self::with_largest_block(upstream, usize::MAX)
+    }
+    /// Pool allocations of at most `largest_block` bytes,
+    /// which is rounded up to a size class and limited to the largest size class.
+    pub const fn with_largest_block(upstream: A, largest_block: usize) -> Self {
+        let largest_class = layout_of(CLASSES - 1).size();
+        Self {
+            upstream,
+            classes: [const { Spin::new(Class::EMPTY) }; CLASSES],
+            largest_block: match largest_block.checked_next_power_of_two() {
+                Some(it) if it < largest_class => it,
+                _ => largest_class,
+            },
+        }
+    }
+    /// The largest allocation which is pooled.
+    pub fn largest_block(&self) -> usize {
+        self.largest_block
+    }
+    pub fn upstream(&self) -> &A {
+        &self.upstream
+    }
+    /// Return every pool's chunks to [`Self::upstream`].
+    pub fn release(&mut self) {
+        for class in &self.classes {
+            class.lock().release(&self.upstream)
+        }
+    }
+    /// The size class which serves `layout`, if it is pooled.
+    #[inline(always)]
+    fn class_of(&self, layout: Layout) -> Option<usize> {
+        class_of(layout).filter(|it| layout_of(*it).size() <= self.largest_block)
+    }
+}
+
+unsafe impl<A> Allocator for SyncPoolResource<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let Some(class) = self.class_of(layout) else {
+            return self.upstream.allocate(layout);
+        };
+        let block = layout_of(class);
+        let ptr = self.classes[class].lock().allocate(&self.upstream, block)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, block.size()))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        match self.class_of(layout) {
+            Some(class) => self.classes[class].lock().deallocate(ptr),
+            None => self.upstream.deallocate(ptr, layout),
+        }
+    }
+}
+
+unsafe impl<A> Owns for SyncPoolResource<A>
+where
+    A: Allocator + Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        match self.class_of(layout) {
+            Some(class) => self.classes[class].lock().contains(ptr, layout_of(class)),
+            None => self.upstream.owns(ptr, layout),
+        }
+    }
+}
+
+impl<A> Drop for SyncPoolResource<A>
+where
+    A: Allocator,
+{
+    fn drop(&mut self) {
+        self.release()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn monotonic_buffer_resource() {
+    let mut buffer = [MaybeUninit::uninit(); 16];
+    let mut resource = MonotonicBufferResource::with_buffer(&mut buffer, Malloc.stats());
+    for _ in 0..2 {
+        let before = resource.upstream().snapshot();
+        let chunks = |it: &Stats<Malloc>| it.diff(&before).allocations;
+        let _ = Box::new_in(1u64, &resource);
+        assert_eq!(chunks(resource.upstream()), 0);
+        // the first chunk has room for 32 bytes, and the second for 64
+        let _ = Box::new_in([1u64; 2], &resource);
+        let _ = Box::new_in([1u64; 2], &resource);
+        assert_eq!(chunks(resource.upstream()), 1);
+        let _ = Box::new_in([1u64; 8], &resource);
+        assert_eq!(chunks(resource.upstream()), 2);
+        let it = Box::new_in(1u8, &resource);
+        assert!(resource.owns(NonNull::from(&*it), Layout::new::<u8>()));
+        drop(it);
+        resource.release();
+        assert_eq!(resource.upstream().snapshot().live, 0);
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn unsync_pool_resource() {
+    let mut resource = UnsyncPoolResource::with_largest_block(Malloc.stats(), 100);
+    assert_eq!(resource.largest_block(), 128);
+    let first = NonNull::from(&*Box::new_in(1u64, &resource));
+    let second = Box::new_in(2u64, &resource);
+    assert_eq!(NonNull::from(&*second), first);
+    let chunks = resource.upstream().snapshot().allocations;
+    let large = Box::new_in([0u8; 256], &resource);
+    assert_eq!(resource.upstream().snapshot().allocations, chunks + 1);
+    drop((second, large));
+    resource.release();
+    assert_eq!(resource.upstream().snapshot().live, 0);
+    // pools start again from a fresh chunk
+    let _ = Box::new_in(3u64, &resource);
+    assert_eq!(resource.upstream().snapshot().allocations, chunks + 2);
+}
+
+#[cfg(all(feature = "malloc", feature = "std"))]
+#[test]
+fn sync_pool_resource() {
+    let mut resource = SyncPoolResource::with_largest_block(Malloc.stats(), 100);
+    assert_eq!(resource.largest_block(), 128);
+    let first = NonNull::from(&*Box::new_in(1u64, &resource));
+    let second = Box::new_in(2u64, &resource);
+    assert_eq!(NonNull::from(&*second), first);
+    // a chunk for each size class, and the large allocation
+    std::thread::scope(|s| {
+        let resource = &resource;
+        s.spawn(move || {
+            let _ = Box::new_in([0u8; 32], resource);
+        });
+        s.spawn(move || {
+            let _ = Box::new_in([0u8; 256], resource);
+        });
+    });
+    assert_eq!(resource.upstream().snapshot().allocations, 3);
+    assert_eq!(*second, 2);
+    drop(second);
+    resource.release();
+    assert_eq!(resource.upstream().snapshot().live, 0);
+}
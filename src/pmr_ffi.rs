@@ -0,0 +1,296 @@
+use crate::{global::wrap, prelude::*};
+use core::{ffi::c_void, marker::PhantomData, ptr};
+
+/// The functions a C++ `composable_allocators::rust_memory_resource` calls
+/// to reach a Rust [`Allocator`], matching `vtable` in `cpp/composable_allocators/pmr.hpp`.
+///
+/// Allocation returns null on failure, and both take a pointer to the allocator first.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct MemoryResourceVTable {
+    pub allocate: unsafe extern "C" fn(*const c_void, usize, usize) -> *mut c_void,
+    pub deallocate: unsafe extern "C" fn(*const c_void, *mut c_void, usize, usize),
+}
+
+struct VTableFor<A>(PhantomData<A>);
+
+impl<A> VTableFor<A>
+where
+    A: Allocator,
+{
+    const VTABLE: MemoryResourceVTable = MemoryResourceVTable {
+        allocate: Self::allocate,
+        deallocate: Self::deallocate,
+    };
+    unsafe extern "C" fn allocate(data: *const c_void, bytes: usize, align: usize) -> *mut c_void {
+        let Ok(layout) = Layout::from_size_align(bytes, align) else {
+            return ptr::null_mut();
+        };
+        (*data.cast::<A>())
+            .allocate(layout)
+            .map_or(ptr::null_mut(), |it| it.as_ptr().cast())
+    }
+    unsafe extern "C" fn deallocate(
+        data: *const c_void,
+        ptr: *mut c_void,
+        bytes: usize,
+        align: usize,
+    ) {
+        (*data.cast::<A>()).deallocate(
+            NonNull::new_unchecked(ptr.cast()),
+            Layout::from_size_align_unchecked(bytes, align),
+        )
+    }
+}
+
+/// A borrowed [`Allocator`], in a form which can be passed to C++
+/// and wrapped as a `std::pmr::memory_resource`.
+///
+/// The C++ side lives in the single header `cpp/composable_allocators/pmr.hpp`,
+/// which declares this as `composable_allocators::raw_resource`:
+/// ```cpp
+/// #include "composable_allocators/pmr.hpp"
+///
+/// extern "C" void run(composable_allocators::raw_resource raw) {
+///     composable_allocators::rust_memory_resource resource(raw);
+///     std::pmr::vector<int> v(&resource);
+///     v.push_back(1);
+/// }
+/// ```
+/// ```
+/// # use composable_allocators::*;
+/// # mod cpp {
+/// #     #[no_mangle]
+/// #     extern "C" fn run(_: composable_allocators::ToMemoryResource) {}
+/// # }
+/// extern "C" {
+///     fn run(raw: ToMemoryResource);
+/// }
+/// let allocator = Malloc.stats();
+/// unsafe { run(ToMemoryResource::new(&allocator)) };
+/// ```
+///
+/// Resources compare equal in C++ if they wrap the same allocator.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct ToMemoryResource<'a> {
+    pub data: *const c_void,
+    pub vtable: &'static MemoryResourceVTable,
+    _allocator: PhantomData<&'a ()>,
+}
+
+impl<'a> ToMemoryResource<'a> {
+    /// The resource must not be used by C++ after `allocator` is dropped.
+    pub fn new<A>(allocator: &'a A) -> Self
+    where
+        A: Allocator,
+    {
+        Self {
+            data: ptr::from_ref(allocator).cast(),
+            vtable: &VTableFor::<A>::VTABLE,
+            _allocator: PhantomData,
+        }
+    }
+}
+
+extern "C" {
+    fn composable_allocators_pmr_allocate(
+        resource: *mut c_void,
+        bytes: usize,
+        align: usize,
+    ) -> *mut c_void;
+    fn composable_allocators_pmr_deallocate(
+        resource: *mut c_void,
+        ptr: *mut c_void,
+        bytes: usize,
+        align: usize,
+    );
+}
+
+/// An [`Allocator`] which uses a C++ `std::pmr::memory_resource*`.
+///
+/// This calls `composable_allocators_pmr_allocate` and `composable_allocators_pmr_deallocate`,
+/// which are defined in `cpp/composable_allocators/pmr.hpp`
+/// when it is included with `COMPOSABLE_ALLOCATORS_PMR_IMPLEMENTATION` defined,
+/// in exactly one C++ source file linked into the program.
+///
+/// Exceptions thrown by the resource are caught on the C++ side, and reported as [`AllocError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FromMemoryResource {
+    resource: NonNull<c_void>,
+}
+
+impl FromMemoryResource {
+    /// # Safety
+    /// - `resource` must point to a `std::pmr::memory_resource`,
+    ///   which must outlive this and every allocation made from it.
+    pub const unsafe fn new(resource: NonNull<c_void>) -> Self {
+        Self { resource }
+    }
+    pub fn as_ptr(&self) -> NonNull<c_void> {
+        self.resource
+    }
+}
+
+unsafe impl Allocator for FromMemoryResource {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = unsafe {
+            composable_allocators_pmr_allocate(
+                self.resource.as_ptr(),
+                layout.size(),
+                layout.align(),
+            )
+        };
+        wrap(ptr.cast(), layout.size())
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        composable_allocators_pmr_deallocate(
+            self.resource.as_ptr(),
+            ptr.as_ptr().cast(),
+            layout.size(),
+            layout.align(),
+        )
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn to_memory_resource() {
+    let a = Malloc.stats();
+    let resource = ToMemoryResource::new(&a);
+    unsafe {
+        let ptr = (resource.vtable.allocate)(resource.data, 24, 8);
+        assert!(!ptr.is_null());
+        assert_eq!(a.snapshot().live, 24);
+        (resource.vtable.deallocate)(resource.data, ptr, 24, 8);
+        assert!((resource.vtable.allocate)(resource.data, 24, 3).is_null());
+    }
+    assert_eq!(a.snapshot().live, 0);
+}
+
+/// Builds `cpp/tests/round_trip.cpp` with `g++`, and forwards the functions
+/// [`FromMemoryResource`] links against to the library.
+#[cfg(all(test, feature = "malloc", feature = "std", target_os = "linux"))]
+mod round_trip {
+    use super::*;
+    use std::{ffi::CString, process::Command, sync::OnceLock};
+
+    type Allocate = unsafe extern "C" fn(*mut c_void, usize, usize) -> *mut c_void;
+    type Deallocate = unsafe extern "C" fn(*mut c_void, *mut c_void, usize, usize);
+
+    struct Library {
+        allocate: Allocate,
+        deallocate: Deallocate,
+        new: unsafe extern "C" fn(ToMemoryResource<'_>) -> *mut c_void,
+        delete: unsafe extern "C" fn(*mut c_void),
+        sum: unsafe extern "C" fn(*mut c_void, i32) -> i32,
+    }
+
+    /// [`None`] if `g++` isn't installed.
+    fn library() -> Option<&'static Library> {
+        static LIBRARY: OnceLock<Option<Library>> = OnceLock::new();
+        LIBRARY
+            .get_or_init(|| {
+                let cpp = concat!(env!("CARGO_MANIFEST_DIR"), "/cpp");
+                let out = std::env::temp_dir().join(std::format!(
+                    "composable-allocators-round-trip-{}.so",
+                    std::process::id()
+                ));
+                let status = Command::new("g++")
+                    .args([
+                        "-std=c++17",
+                        "-shared",
+                        "-fPIC",
+                        "-Wall",
+                        "-Werror",
+                        "-I",
+                        cpp,
+                    ])
+                    .arg(std::format!("{cpp}/tests/round_trip.cpp"))
+                    .arg("-o")
+                    .arg(&out)
+                    .status()
+                    .ok()?;
+                assert!(status.success(), "pmr.hpp failed to compile");
+                let path = CString::new(out.as_os_str().as_encoded_bytes()).unwrap();
+                unsafe {
+                    let handle = libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL);
+                    assert!(!handle.is_null());
+                    let _ = std::fs::remove_file(&out);
+                    let symbol = |name: &str| {
+                        let name = CString::new(name).unwrap();
+                        let it = libc::dlsym(handle, name.as_ptr());
+                        assert!(!it.is_null(), "missing {name:?}");
+                        it
+                    };
+                    Some(Library {
+                        allocate: core::mem::transmute::<*mut c_void, Allocate>(symbol(
+                            "composable_allocators_pmr_allocate",
+                        )),
+                        deallocate: core::mem::transmute::<*mut c_void, Deallocate>(symbol(
+                            "composable_allocators_pmr_deallocate",
+                        )),
+                        new: core::mem::transmute::<
+                            *mut c_void,
+                            unsafe extern "C" fn(ToMemoryResource<'_>) -> *mut c_void,
+                        >(symbol("round_trip_new")),
+                        delete: core::mem::transmute::<
+                            *mut c_void,
+                            unsafe extern "C" fn(*mut c_void),
+                        >(symbol("round_trip_delete")),
+                        sum: core::mem::transmute::<
+                            *mut c_void,
+                            unsafe extern "C" fn(*mut c_void, i32) -> i32,
+                        >(symbol("round_trip_sum")),
+                    })
+                }
+            })
+            .as_ref()
+    }
+
+    #[no_mangle]
+    unsafe extern "C" fn composable_allocators_pmr_allocate(
+        resource: *mut c_void,
+        bytes: usize,
+        align: usize,
+    ) -> *mut c_void {
+        (library().unwrap().allocate)(resource, bytes, align)
+    }
+
+    #[no_mangle]
+    unsafe extern "C" fn composable_allocators_pmr_deallocate(
+        resource: *mut c_void,
+        ptr: *mut c_void,
+        bytes: usize,
+        align: usize,
+    ) {
+        (library().unwrap().deallocate)(resource, ptr, bytes, align)
+    }
+
+    #[test]
+    fn round_trip() {
+        let Some(library) = library() else {
+            return;
+        };
+        let stats = Malloc.stats();
+        let limited = (&stats).limit_size(1024);
+        unsafe {
+            // Rust -> C++ -> Rust
+            let resource = (library.new)(ToMemoryResource::new(&limited));
+            assert_eq!((library.sum)(resource, 100), 4950);
+            assert!(stats.snapshot().allocations > 0);
+            assert_eq!(stats.snapshot().live, 0);
+            let a = FromMemoryResource::new(NonNull::new(resource).unwrap());
+            let mut v = allocator_api2::vec::Vec::<u64, _>::new_in(a);
+            v.extend(0..64);
+            assert_eq!(stats.snapshot().live, v.capacity() * 8);
+            // `bad_alloc` from the resource is caught
+            v.try_reserve_exact(1024).unwrap_err();
+            drop(v);
+            assert_eq!(stats.snapshot().live, 0);
+            (library.delete)(resource);
+        }
+    }
+}
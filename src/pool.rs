@@ -0,0 +1,231 @@
+use crate::prelude::*;
+use core::{cell::Cell, mem};
+
+/// Written into each free block.
+struct Free {
+    next: Option<NonNull<Free>>,
+}
+
+/// An [`Allocator`] which serves allocations that fit in [`Self::block`]
+/// from a region of [`capacity`](Self::capacity) blocks requested from `A` on first use.
+///
+/// Freed blocks are kept on a free list for reuse.
+/// Allocations which don't fit in a block fail,
+/// so put this in front of a general purpose allocator with [`Or`].
+#[derive(Debug)]
+pub struct Pool<A: Allocator> {
+    inner: A,
+    block: Layout,
+    capacity: usize,
+    region: Cell<Option<NonNull<u8>>>,
+    /// Blocks past this index have never been allocated.
+    fresh: Cell<usize>,
+    free: Cell<Option<NonNull<Free>>>,
+}
+
+unsafe impl<A: Allocator + Send> Send for Pool<A> {}
+
+impl<A: Allocator> Pool<A> {
+    pub const fn new(inner: A, block: Layout, capacity: usize) -> Self {
+        Self {
+            inner,
+            block,
+            capacity,
+            region: Cell::new(None),
+            fresh: Cell::new(0),
+            free: Cell::new(None),
+        }
+    }
+    /// The largest allocation this pool can serve.
+    pub fn block(&self) -> Layout {
+        self.block
+    }
+    /// The number of blocks in this pool.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+    /// The [`Layout`] of each block, including room for the free list.
+    #[inline(always)]
+    fn stride(&self) -> Layout {
+        let block = self.block;
+        unsafe {
+            Layout::from_size_align_unchecked(
+                block.size().max(mem::size_of::<Free>()),
+                block.align().max(mem::align_of::<Free>()),
+            )
+        }
+        .pad_to_align()
+    }
+    #[inline(always)]
+    fn region_layout(&self) -> Result<Layout, AllocError> {
+        let stride = self.stride();
+        Layout::from_size_align(
+            stride.size().checked_mul(self.capacity).ok_or(AllocError)?,
+            stride.align(),
+        )
+        .map_err(|_| AllocError)
+    }
+    #[inline(always)]
+    fn region(&self) -> Result<NonNull<u8>, AllocError> {
+        match self.region.get() {
+            Some(it) => Ok(it),
+            None => {
+                let it = self.inner.allocate(self.region_layout()?)?.cast::<u8>();
+                self.region.set(Some(it));
+                Ok(it)
+            }
+        }
+    }
+    /// The block at `index`, if it has ever been allocated.
+    #[inline(always)]
+    pub(crate) fn block_at(&self, index: usize) -> Option<NonNull<u8>> {
+        match (self.region.get(), index < self.fresh.get()) {
+            (Some(region), true) => Some(unsafe {
+                NonNull::new_unchecked(region.as_ptr().add(index * self.stride().size()))
+            }),
+            _ => None,
+        }
+    }
+    /// The index of `block`, which must be from this pool.
+    #[inline(always)]
+    pub(crate) fn index_of(&self, block: NonNull<u8>) -> usize {
+        let region = self.region.get().expect("block must be from this pool");
+        (block.as_ptr() as usize - region.as_ptr() as usize) / self.stride().size()
+    }
+    /// The number of blocks which have ever been allocated.
+    #[inline(always)]
+    pub(crate) fn fresh(&self) -> usize {
+        self.fresh.get()
+    }
+    #[inline(always)]
+    fn fits(&self, layout: Layout) -> bool {
+        layout.size() <= self.block.size() && layout.align() <= self.block.align()
+    }
+}
+
+unsafe impl<A> Allocator for Pool<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if !self.fits(layout) {
+            return Err(AllocError);
+        }
+        let block = match self.free.get() {
+            Some(free) => {
+                self.free.set(unsafe { free.as_ptr().read() }.next);
+                free.cast::<u8>()
+            }
+            None => {
+                let region = self.region()?;
+                let fresh = self.fresh.get();
+                if fresh == self.capacity {
+                    return Err(AllocError);
+                }
+                self.fresh.set(fresh + 1);
+                unsafe { NonNull::new_unchecked(region.as_ptr().add(fresh * self.stride().size())) }
+            }
+        };
+        Ok(NonNull::slice_from_raw_parts(block, self.block.size()))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, _: Layout) {
+        let free = ptr.cast::<Free>();
+        free.as_ptr().write(Free {
+            next: self.free.get(),
+        });
+        self.free.set(Some(free))
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        _: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        match self.fits(new_layout) {
+            true => Ok(NonNull::slice_from_raw_parts(ptr, self.block.size())),
+            false => Err(AllocError),
+        }
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        _: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        match self.fits(new_layout) {
+            true => Ok(NonNull::slice_from_raw_parts(ptr, self.block.size())),
+            false => Err(AllocError),
+        }
+    }
+}
+
+impl<A> DeallocateAll for Pool<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.fresh.set(0);
+        self.free.set(None)
+    }
+}
+
+unsafe impl<A> Owns for Pool<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        match (self.region.get(), self.region_layout()) {
+            (Some(region), Ok(region_layout)) => {
+                let start = region.as_ptr() as usize;
+                let ptr = ptr.as_ptr() as usize;
+                start <= ptr && ptr.saturating_add(layout.size()) <= start + region_layout.size()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<A> Drop for Pool<A>
+where
+    A: Allocator,
+{
+    fn drop(&mut self) {
+        if let (Some(region), Ok(layout)) = (self.region.get(), self.region_layout()) {
+            unsafe { self.inner.deallocate(region, layout) }
+        }
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn pool() {
+    let a = Malloc.pool(Layout::new::<u64>(), 2).or(Malloc);
+    let first = Box::new_in(1u64, &a);
+    let second = Box::new_in(2u8, &a);
+    let third = Box::new_in(3u64, &a);
+    let big = Box::new_in([1u64; 2], &a);
+    assert!(a
+        .primary
+        .owns(NonNull::from(&*first).cast(), Layout::new::<u64>()));
+    assert!(a
+        .primary
+        .owns(NonNull::from(&*second).cast(), Layout::new::<u8>()));
+    assert!(!a
+        .primary
+        .owns(NonNull::from(&*third).cast(), Layout::new::<u64>()));
+    assert!(!a
+        .primary
+        .owns(NonNull::from(&*big).cast(), Layout::new::<[u64; 2]>()));
+    let first_ptr = NonNull::from(&*first);
+    drop(first);
+    assert_eq!(first_ptr, NonNull::from(&*Box::new_in(4u64, &a)));
+}
@@ -0,0 +1,521 @@
+use crate::{prelude::*, spin::lock};
+use core::{
+    fmt::{self, Write as _},
+    panic::Location,
+};
+use std::{
+    collections::BTreeMap,
+    env, process,
+    string::String,
+    sync::{Mutex, MutexGuard},
+    time::Instant,
+    vec::Vec,
+};
+
+/// Statistics for the allocations made at one call site, as collected by [`Profiler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Site {
+    /// Bytes ever allocated.
+    pub total_bytes: usize,
+    /// Blocks ever allocated.
+    pub total_blocks: usize,
+    /// The sum of the lifetimes of freed blocks, in microseconds.
+    pub total_lifetimes: u128,
+    pub live_bytes: usize,
+    pub live_blocks: usize,
+    /// The maximum of [`Self::live_bytes`].
+    pub max_bytes: usize,
+    /// [`Self::live_blocks`] when [`Self::max_bytes`] was reached.
+    pub max_blocks: usize,
+    /// [`Self::live_bytes`] when the total across all sites peaked.
+    pub peak_bytes: usize,
+    /// [`Self::live_blocks`] when the total across all sites peaked.
+    pub peak_blocks: usize,
+    /// Allocations that `A` refused, such as by a [`SizeLimit`] being exhausted.
+    pub failures: usize,
+}
+
+#[derive(Debug)]
+struct Block {
+    location: &'static Location<'static>,
+    size: usize,
+    at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    sites: BTreeMap<&'static Location<'static>, Site>,
+    live: BTreeMap<usize, Block>,
+    live_bytes: usize,
+    peak_bytes: usize,
+    peak_at: Option<Instant>,
+}
+
+/// An [`Allocator`] which aggregates the allocations in `A` by call site,
+/// for inspection with [`Self::sites`], or with [dhat's viewer](https://nnethercote.github.io/dh_view/dh_view.html)
+/// by saving [`Self::to_dhat_json`] to a `dhat-heap.json` file.
+///
+/// Call sites are found with [`Location::caller`], so are only as precise as the
+/// chain of `#[track_caller]` above this allocator.
+/// That chain is broken by containers like `Box` and `Vec`,
+/// and by the implementation of [`Allocator`] for `&Profiler`,
+/// so everything allocated through `&a` is attributed to a single site in `allocator_api2`.
+/// Use [`Self::here`] to give each container its own site instead.
+/// Resizing a block counts as freeing it and allocating a new one at the resizing call site,
+/// which for a container made with [`Self::here`] is where that was called.
+///
+/// Placed above a [`SizeLimit`], this shows which code exhausts it:
+/// ```
+/// # use composable_allocators::*;
+/// let a = Malloc.limit_size(64).profiled();
+/// let _fits = allocator_api2::boxed::Box::new_in([0u8; 48], a.here());
+/// allocator_api2::boxed::Box::try_new_in([0u8; 32], a.here()).unwrap_err();
+/// assert_eq!(a.sites().len(), 2);
+/// let folded = a.to_folded(|site| site.failures);
+/// assert_eq!(folded.lines().count(), 1);
+/// assert!(folded.starts_with(file!()));
+/// ```
+#[derive(Debug)]
+pub struct Profiler<A> {
+    pub inner: A,
+    start: Instant,
+    state: Mutex<State>,
+}
+
+impl<A> Profiler<A> {
+    pub fn new(inner: A) -> Self {
+        Self {
+            inner,
+            start: Instant::now(),
+            state: Mutex::default(),
+        }
+    }
+    /// An [`Allocator`] which attributes everything allocated through it to the caller of this method.
+    #[track_caller]
+    pub fn here(&self) -> Attributed<'_, A> {
+        Attributed {
+            profiler: self,
+            location: Location::caller(),
+        }
+    }
+    /// Statistics for each call site, in location order.
+    pub fn sites(&self) -> Vec<(&'static Location<'static>, Site)> {
+        self.state().sites.iter().map(|(k, v)| (*k, *v)).collect()
+    }
+    /// Render the profile in the JSON format understood by `dh_view.html`.
+    pub fn to_dhat_json(&self) -> String {
+        let state = self.state();
+        let micros = |at: Instant| at.duration_since(self.start).as_micros();
+        let cmd = env::args().collect::<Vec<_>>().join(" ");
+        let mut out = String::new();
+        // writing to a String can't fail
+        let _ = write!(
+            out,
+            "{{\"dhatFileVersion\":2,\"mode\":\"rust-heap\",\"verb\":\"Allocated\",\
+            \"bklt\":true,\"bkacc\":false,\"tu\":\"µs\",\"Mtu\":\"s\",\"tuth\":10,\
+            \"cmd\":\"{}\",\"pid\":{},\"tg\":{},\"te\":{},\"pps\":[",
+            Escape(&cmd),
+            process::id(),
+            state.peak_at.map(micros).unwrap_or_default(),
+            micros(Instant::now()),
+        );
+        for (ix, site) in state.sites.values().enumerate() {
+            let _ = write!(
+                out,
+                "{}{{\"tb\":{},\"tbk\":{},\"tl\":{},\"mb\":{},\"mbk\":{},\
+                \"gb\":{},\"gbk\":{},\"eb\":{},\"ebk\":{},\"fs\":[{}]}}",
+                if ix == 0 { "" } else { "," },
+                site.total_bytes,
+                site.total_blocks,
+                site.total_lifetimes,
+                site.max_bytes,
+                site.max_blocks,
+                site.peak_bytes,
+                site.peak_blocks,
+                site.live_bytes,
+                site.live_blocks,
+                // frame 0 is the root
+                ix + 1,
+            );
+        }
+        out.push_str("],\"ftbl\":[\"[root]\"");
+        for location in state.sites.keys() {
+            let _ = write!(out, ",\"{}\"", Escape(&std::format!("{location}")));
+        }
+        out.push_str("]}");
+        out
+    }
+    /// Render the profile as folded stacks, one `location weight` line per call site,
+    /// for flamegraph tools like [`inferno`](https://docs.rs/inferno).
+    ///
+    /// Sites with zero weight are omitted.
+    pub fn to_folded(&self, weight: impl Fn(&Site) -> usize) -> String {
+        let mut out = String::new();
+        for (location, site) in &self.state().sites {
+            match weight(site) {
+                0 => {}
+                // `;` separates frames
+                weight => {
+                    let frame = std::format!("{location}").replace(';', ":");
+                    let _ = writeln!(out, "{frame} {weight}");
+                }
+            }
+        }
+        out
+    }
+    #[inline(always)]
+    fn state(&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+    #[inline(always)]
+    fn record(
+        &self,
+        location: &'static Location<'static>,
+        res: Result<NonNull<[u8]>, AllocError>,
+        layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let size = layout.size();
+        let mut state = self.state();
+        let state = &mut *state;
+        let site = state.sites.entry(location).or_default();
+        if let Ok(ptr) = res {
+            site.total_bytes += size;
+            site.total_blocks += 1;
+            site.live_bytes += size;
+            site.live_blocks += 1;
+            if site.live_bytes > site.max_bytes {
+                site.max_bytes = site.live_bytes;
+                site.max_blocks = site.live_blocks;
+            }
+            state.live_bytes += size;
+            if state.live_bytes > state.peak_bytes {
+                state.peak_bytes = state.live_bytes;
+                state.peak_at = Some(Instant::now());
+                for site in state.sites.values_mut() {
+                    site.peak_bytes = site.live_bytes;
+                    site.peak_blocks = site.live_blocks;
+                }
+            }
+            let block = Block {
+                location,
+                size,
+                at: Instant::now(),
+            };
+            state.live.insert(ptr.as_ptr().cast::<u8>() as usize, block);
+        } else {
+            site.failures += 1
+        }
+        res
+    }
+    /// Record a block moving from `ptr`, if resizing it succeeded.
+    #[inline(always)]
+    fn resized(
+        &self,
+        location: &'static Location<'static>,
+        ptr: NonNull<u8>,
+        res: Result<NonNull<[u8]>, AllocError>,
+        layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if res.is_ok() {
+            self.forget(ptr);
+        }
+        self.record(location, res, layout)
+    }
+    #[inline(always)]
+    fn forget(&self, ptr: NonNull<u8>) {
+        let mut state = self.state();
+        let Some(block) = state.live.remove(&(ptr.as_ptr() as usize)) else {
+            return;
+        };
+        state.live_bytes -= block.size;
+        if let Some(site) = state.sites.get_mut(block.location) {
+            site.live_bytes -= block.size;
+            site.live_blocks -= 1;
+            site.total_lifetimes += block.at.elapsed().as_micros();
+        }
+    }
+}
+
+/// Escapes a string for inclusion in JSON.
+struct Escape<'a>(&'a str);
+
+impl fmt::Display for Escape<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+unsafe impl<A> Allocator for Profiler<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    #[track_caller]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.record(Location::caller(), self.inner.allocate(layout), layout)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.forget(ptr);
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    #[track_caller]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.record(
+            Location::caller(),
+            self.inner.allocate_zeroed(layout),
+            layout,
+        )
+    }
+    #[inline(always)]
+    #[track_caller]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.grow(ptr, old_layout, new_layout);
+        self.resized(Location::caller(), ptr, res, new_layout)
+    }
+    #[inline(always)]
+    #[track_caller]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.grow_zeroed(ptr, old_layout, new_layout);
+        self.resized(Location::caller(), ptr, res, new_layout)
+    }
+    #[inline(always)]
+    #[track_caller]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.shrink(ptr, old_layout, new_layout);
+        self.resized(Location::caller(), ptr, res, new_layout)
+    }
+}
+
+/// A handle to a [`Profiler`], from [`Profiler::here`].
+pub struct Attributed<'a, A> {
+    profiler: &'a Profiler<A>,
+    location: &'static Location<'static>,
+}
+
+impl<A> Attributed<'_, A> {
+    /// The call site that allocations are attributed to.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+}
+
+impl<A> Clone for Attributed<'_, A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A> Copy for Attributed<'_, A> {}
+
+impl<A> fmt::Debug for Attributed<'_, A>
+where
+    A: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Attributed")
+            .field("profiler", &self.profiler)
+            .field("location", &self.location)
+            .finish()
+    }
+}
+
+unsafe impl<A> Allocator for Attributed<'_, A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let profiler = self.profiler;
+        profiler.record(self.location, profiler.inner.allocate(layout), layout)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.profiler.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let profiler = self.profiler;
+        profiler.record(
+            self.location,
+            profiler.inner.allocate_zeroed(layout),
+            layout,
+        )
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.profiler.inner.grow(ptr, old_layout, new_layout);
+        self.profiler.resized(self.location, ptr, res, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.profiler.inner.grow_zeroed(ptr, old_layout, new_layout);
+        self.profiler.resized(self.location, ptr, res, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.profiler.inner.shrink(ptr, old_layout, new_layout);
+        self.profiler.resized(self.location, ptr, res, new_layout)
+    }
+}
+
+/// Blocks freed this way don't contribute to [`Site::total_lifetimes`].
+impl<A> DeallocateAll for Profiler<A>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        let mut state = self.state();
+        self.inner.deallocate_all();
+        state.live.clear();
+        state.live_bytes = 0;
+        for site in state.sites.values_mut() {
+            site.live_bytes = 0;
+            site.live_blocks = 0;
+        }
+    }
+}
+
+unsafe impl<A> Owns for Profiler<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A> UsableSize for Profiler<A>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A> Trim for Profiler<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[test]
+fn profiler() {
+    let a = System.profiled();
+    let ptr = a.allocate(Layout::new::<[u8; 8]>()).unwrap();
+    for _ in 0..2 {
+        let it = a.allocate(Layout::new::<[u8; 4]>()).unwrap();
+        unsafe { a.deallocate(it.cast(), Layout::new::<[u8; 4]>()) };
+    }
+    let sites = a.sites();
+    assert_eq!(sites.len(), 2);
+    let (first, second) = (sites[0].1, sites[1].1);
+    assert_eq!(
+        (first.total_bytes, first.live_bytes, first.peak_bytes),
+        (8, 8, 8)
+    );
+    assert_eq!(
+        (second.total_bytes, second.total_blocks, second.live_blocks),
+        (8, 2, 0)
+    );
+    assert_eq!((second.max_bytes, second.peak_bytes), (4, 4));
+    let json = a.to_dhat_json();
+    assert!(json.starts_with("{\"dhatFileVersion\":2,"));
+    assert!(json.contains("{\"tb\":8,\"tbk\":2,"));
+    assert!(json.contains(file!()));
+    let folded = a.to_folded(|site| site.total_blocks);
+    assert!(folded.lines().all(|it| it.starts_with(file!())));
+    assert!(folded.ends_with(" 2\n"));
+    unsafe { a.deallocate(ptr.cast(), Layout::new::<[u8; 8]>()) };
+    assert_eq!(a.sites()[0].1.live_bytes, 0);
+}
+
+#[test]
+fn here() {
+    let a = System.profiled();
+    let (first, second) = (a.here(), a.here());
+    let _small = Box::new_in(1u8, first);
+    let _big = Box::new_in([1u8; 16], second);
+    let sites = a.sites();
+    assert_eq!(sites.len(), 2);
+    assert_eq!(
+        (sites[0].0, sites[1].0),
+        (first.location(), second.location())
+    );
+    assert_ne!(first.location(), second.location());
+    assert_eq!((sites[0].1.live_bytes, sites[1].1.live_bytes), (1, 16));
+    // containers borrowing the profiler share a site in allocator_api2
+    let _ = (Box::new_in(1u8, &a), Box::new_in(2u8, &a));
+    assert_eq!(a.sites().len(), 3);
+}
+
+#[test]
+fn here_resizing() {
+    let a = System.limit_size(1024).profiled();
+    let mut v = allocator_api2::vec::Vec::<u8, _>::new_in(a.here());
+    let other = Box::new_in([1u8; 8], a.here());
+    v.extend([1; 64]);
+    v.truncate(16);
+    v.shrink_to_fit();
+    allocator_api2::vec::Vec::<u8, _>::new_in(a.here())
+        .try_reserve_exact(2048)
+        .unwrap_err();
+    let sites = a.sites();
+    assert_eq!(sites.len(), 3);
+    let (vec, boxed, failed) = (sites[0].1, sites[1].1, sites[2].1);
+    assert_eq!((vec.live_bytes, vec.live_blocks), (16, 1));
+    assert!(vec.total_blocks > 1);
+    assert_eq!(vec.max_bytes, 64);
+    assert_eq!((boxed.total_bytes, boxed.live_bytes), (8, 8));
+    assert_eq!((failed.failures, failed.total_blocks), (1, 0));
+    drop((v, other));
+    assert!(a.sites().iter().all(|(_, site)| site.live_bytes == 0));
+}
@@ -0,0 +1,118 @@
+use crate::prelude::*;
+use core::fmt::Write as _;
+use std::{string::String, vec::Vec};
+
+struct Metric {
+    name: &'static str,
+    kind: &'static str,
+    help: &'static str,
+    get: fn(&Snapshot) -> usize,
+}
+
+const METRICS: &[Metric] = &[
+    Metric {
+        name: "allocator_allocations_total",
+        kind: "counter",
+        help: "Successful allocations.",
+        get: |it| it.allocations,
+    },
+    Metric {
+        name: "allocator_deallocations_total",
+        kind: "counter",
+        help: "Deallocations.",
+        get: |it| it.deallocations,
+    },
+    Metric {
+        name: "allocator_reallocations_total",
+        kind: "counter",
+        help: "Successful reallocations.",
+        get: |it| it.reallocations,
+    },
+    Metric {
+        name: "allocator_failures_total",
+        kind: "counter",
+        help: "Failed allocations or reallocations.",
+        get: |it| it.failures,
+    },
+    Metric {
+        name: "allocator_allocated_bytes_total",
+        kind: "counter",
+        help: "Bytes ever allocated, including growth.",
+        get: |it| it.cumulative,
+    },
+    Metric {
+        name: "allocator_live_bytes",
+        kind: "gauge",
+        help: "Bytes currently allocated.",
+        get: |it| it.live,
+    },
+    Metric {
+        name: "allocator_peak_bytes",
+        kind: "gauge",
+        help: "The maximum of allocator_live_bytes.",
+        get: |it| it.peak,
+    },
+];
+
+/// Render [`Snapshot`]s in the Prometheus text exposition format,
+/// labelling each with `allocator="<name>"`.
+///
+/// ```
+/// # use composable_allocators::{prometheus, AllocatorExt as _, Null};
+/// let heap = Null.stats();
+/// let text = prometheus([("heap", heap.snapshot())]);
+/// assert!(text.contains(r#"allocator_live_bytes{allocator="heap"} 0"#));
+/// ```
+pub fn prometheus<'a>(snapshots: impl IntoIterator<Item = (&'a str, Snapshot)>) -> String {
+    let snapshots = snapshots
+        .into_iter()
+        .map(|(name, snapshot)| (escape(name), snapshot))
+        .collect::<Vec<_>>();
+    let mut out = String::new();
+    for Metric {
+        name: metric,
+        kind,
+        help,
+        get,
+    } in METRICS
+    {
+        let _ = writeln!(out, "# HELP {metric} {help}");
+        let _ = writeln!(out, "# TYPE {metric} {kind}");
+        for (name, snapshot) in &snapshots {
+            let _ = writeln!(out, "{metric}{{allocator=\"{name}\"}} {}", get(snapshot));
+        }
+    }
+    out
+}
+
+/// Escape a label value.
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str(r"\\"),
+            '"' => out.push_str(r#"\""#),
+            '\n' => out.push_str(r"\n"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[test]
+fn render() {
+    let snapshot = Snapshot {
+        allocations: 2,
+        deallocations: 1,
+        reallocations: 0,
+        failures: 3,
+        live: 8,
+        peak: 16,
+        cumulative: 24,
+    };
+    let text = prometheus([("a", snapshot), ("b\"", Snapshot::default())]);
+    assert!(text.contains("# TYPE allocator_failures_total counter\n"));
+    assert!(text.contains("allocator_failures_total{allocator=\"a\"} 3\n"));
+    assert!(text.contains("allocator_peak_bytes{allocator=\"a\"} 16\n"));
+    assert!(text.contains("allocator_live_bytes{allocator=\"b\\\"\"} 0\n"));
+}
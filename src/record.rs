@@ -0,0 +1,575 @@
+use crate::{prelude::*, spin::lock};
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, MutexGuard},
+    vec::Vec,
+};
+
+/// An operation in a [`Trace`].
+///
+/// Allocations are identified by the order in which they were made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Record {
+    Allocate {
+        id: u64,
+        layout: Layout,
+        zeroed: bool,
+    },
+    Deallocate {
+        id: u64,
+    },
+    Grow {
+        id: u64,
+        layout: Layout,
+        zeroed: bool,
+    },
+    Shrink {
+        id: u64,
+        layout: Layout,
+    },
+}
+
+const ALLOCATE: u8 = 0;
+const ALLOCATE_ZEROED: u8 = 1;
+const DEALLOCATE: u8 = 2;
+const GROW: u8 = 3;
+const GROW_ZEROED: u8 = 4;
+const SHRINK: u8 = 5;
+
+/// A compact encoding of a sequence of [`Record`]s, from a [`Recorder`].
+///
+/// Each record is a tag byte followed by LEB128 integers,
+/// so a typical allocation takes a handful of bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Trace {
+    bytes: Vec<u8>,
+}
+
+impl Trace {
+    /// Decoding stops at the first malformed record.
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self { bytes }
+    }
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+    pub fn iter(&self) -> impl Iterator<Item = Record> + '_ {
+        let mut bytes = self.bytes.as_slice();
+        core::iter::from_fn(move || decode(&mut bytes))
+    }
+    fn push(&mut self, record: Record) {
+        let (tag, id, layout) = match record {
+            Record::Allocate { id, layout, zeroed } => (
+                [ALLOCATE, ALLOCATE_ZEROED][zeroed as usize],
+                id,
+                Some(layout),
+            ),
+            Record::Deallocate { id } => (DEALLOCATE, id, None),
+            Record::Grow { id, layout, zeroed } => {
+                ([GROW, GROW_ZEROED][zeroed as usize], id, Some(layout))
+            }
+            Record::Shrink { id, layout } => (SHRINK, id, Some(layout)),
+        };
+        self.bytes.push(tag);
+        leb128(&mut self.bytes, id);
+        if let Some(layout) = layout {
+            leb128(&mut self.bytes, layout.size() as u64);
+            self.bytes.push(layout.align().trailing_zeros() as u8);
+        }
+    }
+    /// Perform the operations in this trace against `allocator`,
+    /// returning the number which failed.
+    ///
+    /// See [`Replayer`], which this uses.
+    pub fn replay<A: Allocator>(&self, allocator: A) -> usize {
+        let mut replayer = Replayer::new(allocator);
+        replayer.replay(self);
+        replayer.failures()
+    }
+}
+
+/// Performs the operations in [`Trace`]s against `A`,
+/// e.g to benchmark it with a production allocation pattern.
+///
+/// Allocations stay live between calls to [`Self::replay`],
+/// so successive traces from [`Recorder::take`] can be replayed in turn.
+/// Outstanding allocations are deallocated when this is dropped.
+///
+/// Operations on allocations which failed are skipped.
+#[derive(Debug)]
+pub struct Replayer<A: Allocator> {
+    allocator: A,
+    live: BTreeMap<u64, (NonNull<u8>, Layout)>,
+    failures: usize,
+}
+
+impl<A> Replayer<A>
+where
+    A: Allocator,
+{
+    pub fn new(allocator: A) -> Self {
+        Self {
+            allocator,
+            live: BTreeMap::new(),
+            failures: 0,
+        }
+    }
+    pub fn allocator(&self) -> &A {
+        &self.allocator
+    }
+    /// The number of operations which have failed.
+    pub fn failures(&self) -> usize {
+        self.failures
+    }
+    /// The number of allocations which are live.
+    pub fn live(&self) -> usize {
+        self.live.len()
+    }
+    pub fn replay(&mut self, trace: &Trace) {
+        for record in trace.iter() {
+            self.step(record)
+        }
+    }
+    /// Perform a single operation.
+    pub fn step(&mut self, record: Record) {
+        let Self {
+            allocator, live, ..
+        } = self;
+        let res = unsafe {
+            match record {
+                Record::Allocate { id, layout, zeroed } => match zeroed {
+                    true => allocator.allocate_zeroed(layout),
+                    false => allocator.allocate(layout),
+                }
+                .map(|it| (id, layout, it)),
+                Record::Deallocate { id } => {
+                    if let Some((ptr, layout)) = live.remove(&id) {
+                        allocator.deallocate(ptr, layout)
+                    }
+                    return;
+                }
+                Record::Grow { id, layout, zeroed } => {
+                    let Some((ptr, old)) = live.remove(&id) else {
+                        return;
+                    };
+                    match zeroed {
+                        true => allocator.grow_zeroed(ptr, old, layout),
+                        false => allocator.grow(ptr, old, layout),
+                    }
+                    .map(|it| (id, layout, it))
+                    .inspect_err(|_| {
+                        live.insert(id, (ptr, old));
+                    })
+                }
+                Record::Shrink { id, layout } => {
+                    let Some((ptr, old)) = live.remove(&id) else {
+                        return;
+                    };
+                    allocator
+                        .shrink(ptr, old, layout)
+                        .map(|it| (id, layout, it))
+                        .inspect_err(|_| {
+                            live.insert(id, (ptr, old));
+                        })
+                }
+            }
+        };
+        match res {
+            Ok((id, layout, block)) => {
+                live.insert(id, (block.cast(), layout));
+            }
+            Err(AllocError) => self.failures += 1,
+        }
+    }
+}
+
+impl<A> Drop for Replayer<A>
+where
+    A: Allocator,
+{
+    fn drop(&mut self) {
+        for (ptr, layout) in core::mem::take(&mut self.live).into_values() {
+            unsafe { self.allocator.deallocate(ptr, layout) }
+        }
+    }
+}
+
+fn leb128(bytes: &mut Vec<u8>, mut n: u64) {
+    loop {
+        let byte = (n & 0x7F) as u8;
+        n >>= 7;
+        match n {
+            0 => return bytes.push(byte),
+            _ => bytes.push(byte | 0x80),
+        }
+    }
+}
+
+fn decode(bytes: &mut &[u8]) -> Option<Record> {
+    fn byte(bytes: &mut &[u8]) -> Option<u8> {
+        let (first, rest) = bytes.split_first()?;
+        *bytes = rest;
+        Some(*first)
+    }
+    fn int(bytes: &mut &[u8]) -> Option<u64> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let it = byte(bytes)?;
+            n |= u64::from(it & 0x7F) << shift;
+            if it & 0x80 == 0 {
+                return Some(n);
+            }
+        }
+        None
+    }
+    fn layout(bytes: &mut &[u8]) -> Option<Layout> {
+        let size = usize::try_from(int(bytes)?).ok()?;
+        let align = 1usize.checked_shl(u32::from(byte(bytes)?))?;
+        Layout::from_size_align(size, align).ok()
+    }
+    let tag = byte(bytes)?;
+    let id = int(bytes)?;
+    Some(match tag {
+        ALLOCATE | ALLOCATE_ZEROED => Record::Allocate {
+            id,
+            layout: layout(bytes)?,
+            zeroed: tag == ALLOCATE_ZEROED,
+        },
+        DEALLOCATE => Record::Deallocate { id },
+        GROW | GROW_ZEROED => Record::Grow {
+            id,
+            layout: layout(bytes)?,
+            zeroed: tag == GROW_ZEROED,
+        },
+        SHRINK => Record::Shrink {
+            id,
+            layout: layout(bytes)?,
+        },
+        _ => return None,
+    })
+}
+
+#[derive(Debug, Default)]
+struct State {
+    trace: Trace,
+    /// Live allocations by address.
+    ids: BTreeMap<usize, u64>,
+    next: u64,
+}
+
+/// An [`Allocator`] which records successful calls to `A` in a [`Trace`],
+/// which can be replayed against another allocator with a [`Replayer`].
+#[derive(Debug)]
+pub struct Recorder<A> {
+    pub inner: A,
+    state: Mutex<State>,
+}
+
+impl<A> Recorder<A> {
+    pub const fn new(inner: A) -> Self {
+        Self {
+            inner,
+            state: Mutex::new(State {
+                trace: Trace { bytes: Vec::new() },
+                ids: BTreeMap::new(),
+                next: 0,
+            }),
+        }
+    }
+    #[inline(always)]
+    fn state(&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+    /// A copy of the trace so far.
+    pub fn trace(&self) -> Trace {
+        self.state().trace.clone()
+    }
+    /// Take the trace so far, leaving an empty one.
+    ///
+    /// Operations on outstanding allocations continue to be recorded.
+    pub fn take(&self) -> Trace {
+        core::mem::take(&mut self.state().trace)
+    }
+    #[inline(always)]
+    fn allocated(
+        &self,
+        layout: Layout,
+        zeroed: bool,
+        res: Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        // zero-sized allocations may share an address, so aren't tracked
+        if layout.size() == 0 {
+            return res;
+        }
+        if let Ok(block) = res {
+            let mut state = self.state();
+            let id = state.next;
+            state.next += 1;
+            state.ids.insert(block.cast::<u8>().as_ptr() as usize, id);
+            state.trace.push(Record::Allocate { id, layout, zeroed });
+        }
+        res
+    }
+    /// Hold the lock while `A` resizes,
+    /// so that if it frees `ptr`, another thread can't be given it and record it
+    /// before its id moves to the new block.
+    ///
+    /// Since zero-sized allocations aren't tracked,
+    /// growing from one is recorded as an allocation, and shrinking to one as a deallocation.
+    #[inline(always)]
+    fn reallocated(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        record: impl FnOnce(u64) -> Record,
+        resize: impl FnOnce() -> Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let mut state = self.state();
+        let res = resize();
+        let Ok(block) = res else {
+            return res;
+        };
+        let (id, record) = match old_layout.size() {
+            0 if new_layout.size() == 0 => return res,
+            0 => {
+                let id = state.next;
+                state.next += 1;
+                let record = match record(id) {
+                    Record::Grow { id, layout, zeroed } => Record::Allocate { id, layout, zeroed },
+                    other => other,
+                };
+                (id, record)
+            }
+            _ => {
+                let Some(id) = state.ids.remove(&(ptr.as_ptr() as usize)) else {
+                    return res;
+                };
+                if new_layout.size() == 0 {
+                    state.trace.push(Record::Deallocate { id });
+                    return res;
+                }
+                (id, record(id))
+            }
+        };
+        state.ids.insert(block.cast::<u8>().as_ptr() as usize, id);
+        state.trace.push(record);
+        res
+    }
+}
+
+unsafe impl<A> Allocator for Recorder<A>
+where
+    A: Allocator,
+{
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocated(layout, false, self.inner.allocate(layout))
+    }
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            let mut state = self.state();
+            if let Some(id) = state.ids.remove(&(ptr.as_ptr() as usize)) {
+                state.trace.push(Record::Deallocate { id });
+            }
+        }
+        self.inner.deallocate(ptr, layout)
+    }
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocated(layout, true, self.inner.allocate_zeroed(layout))
+    }
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.reallocated(
+            ptr,
+            old_layout,
+            new_layout,
+            |id| Record::Grow {
+                id,
+                layout: new_layout,
+                zeroed: false,
+            },
+            || self.inner.grow(ptr, old_layout, new_layout),
+        )
+    }
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.reallocated(
+            ptr,
+            old_layout,
+            new_layout,
+            |id| Record::Grow {
+                id,
+                layout: new_layout,
+                zeroed: true,
+            },
+            || self.inner.grow_zeroed(ptr, old_layout, new_layout),
+        )
+    }
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let record = |id| Record::Shrink {
+            id,
+            layout: new_layout,
+        };
+        self.reallocated(ptr, old_layout, new_layout, record, || {
+            self.inner.shrink(ptr, old_layout, new_layout)
+        })
+    }
+}
+
+unsafe impl<A> Owns for Recorder<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn record() {
+    let a = Malloc.recorded();
+    let first = Box::new_in(1u32, &a);
+    let mut v = allocator_api2::vec::Vec::<u8, _>::with_capacity_in(200, &a);
+    v.extend([0; 200]);
+    v.reserve_exact(100);
+    drop(first);
+    v.truncate(1);
+    v.shrink_to_fit();
+    drop(v);
+    let trace = a.take();
+    assert_eq!(
+        trace.iter().collect::<Vec<_>>(),
+        [
+            Record::Allocate {
+                id: 0,
+                layout: Layout::new::<u32>(),
+                zeroed: false
+            },
+            Record::Allocate {
+                id: 1,
+                layout: Layout::new::<[u8; 200]>(),
+                zeroed: false
+            },
+            Record::Grow {
+                id: 1,
+                layout: Layout::new::<[u8; 300]>(),
+                zeroed: false
+            },
+            Record::Deallocate { id: 0 },
+            Record::Shrink {
+                id: 1,
+                layout: Layout::new::<u8>()
+            },
+            Record::Deallocate { id: 1 },
+        ]
+    );
+    assert_eq!(trace.as_bytes().len(), 22);
+    let replayed = Trace::from_bytes(trace.as_bytes().to_vec());
+    assert_eq!(replayed, trace);
+    let stats = Malloc.stats();
+    assert_eq!(trace.replay(&stats), 0);
+    assert_eq!(stats.snapshot().peak, 304);
+    assert_eq!(trace.replay(Malloc.limit_size(256)), 1);
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn threads() {
+    use std::collections::BTreeSet;
+    // reuse freed blocks immediately on any thread, and widen the window after a move
+    let a = Malloc
+        .recycle(16)
+        .locked()
+        .hooked(|event: Event| {
+            if let Event::Reallocated { .. } = event {
+                std::thread::yield_now()
+            }
+        })
+        .recorded();
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for _ in 0..2000 {
+                    let mut v = allocator_api2::vec::Vec::<u8, _>::new_in(&a);
+                    v.extend(0..64);
+                    v.truncate(1);
+                    v.shrink_to_fit();
+                }
+            });
+        }
+    });
+    let mut live = BTreeSet::new();
+    for record in a.take().iter() {
+        match record {
+            Record::Allocate { id, .. } => assert!(live.insert(id)),
+            Record::Grow { id, .. } | Record::Shrink { id, .. } => assert!(live.contains(&id)),
+            Record::Deallocate { id } => assert!(live.remove(&id)),
+        }
+    }
+    assert!(live.is_empty());
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn zero_sized() {
+    let a = Malloc.recorded();
+    let layout = Layout::from_size_align(0, 8).unwrap();
+    let [first, second] = [(); 2].map(|()| a.allocate(layout).unwrap().cast::<u8>());
+    let grown = unsafe { a.grow(second, layout, Layout::new::<u64>()) }.unwrap();
+    let shrunk = unsafe { a.shrink(grown.cast(), Layout::new::<u64>(), layout) }.unwrap();
+    unsafe {
+        a.deallocate(first, layout);
+        a.deallocate(shrunk.cast(), layout);
+    }
+    assert_eq!(
+        a.take().iter().collect::<Vec<_>>(),
+        [
+            Record::Allocate {
+                id: 0,
+                layout: Layout::new::<u64>(),
+                zeroed: false
+            },
+            Record::Deallocate { id: 0 },
+        ]
+    );
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn replayer() {
+    let a = Malloc.recorded();
+    let kept = Box::new_in(1u32, &a);
+    let first = a.take();
+    let mut v = allocator_api2::vec::Vec::<u8, _>::with_capacity_in(8, &a);
+    v.reserve_exact(16);
+    drop(kept);
+    drop(v);
+    let second = a.take();
+    let stats = Malloc.stats();
+    let mut replayer = Replayer::new(&stats);
+    replayer.replay(&first);
+    assert_eq!(replayer.live(), 1);
+    replayer.replay(&second);
+    assert_eq!(replayer.live(), 0);
+    assert_eq!(replayer.failures(), 0);
+    let mut replayer = Replayer::new(&stats);
+    replayer.replay(&first);
+    drop(replayer);
+    assert_eq!(stats.snapshot().live, 0);
+    assert_eq!(stats.snapshot().allocations, 3);
+}
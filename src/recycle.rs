@@ -0,0 +1,170 @@
+use crate::prelude::*;
+use core::{cell::Cell, mem};
+
+/// Written into each cached block.
+pub(crate) struct Free {
+    pub(crate) next: Option<NonNull<Free>>,
+}
+
+const MIN_LOG2: u32 = mem::size_of::<Free>().trailing_zeros();
+pub(crate) const CLASSES: usize = 16;
+
+/// The index of the size class which can hold `layout`, if any.
+#[inline(always)]
+pub(crate) fn class_of(layout: Layout) -> Option<usize> {
+    let size = layout
+        .size()
+        .max(layout.align())
+        .max(mem::size_of::<Free>())
+        .checked_next_power_of_two()?;
+    let class = (size.trailing_zeros() - MIN_LOG2) as usize;
+    (class < CLASSES).then_some(class)
+}
+
+/// Size classes are naturally aligned powers of two.
+#[inline(always)]
+pub(crate) const fn layout_of(class: usize) -> Layout {
+    let size = 1 << (class as u32 + MIN_LOG2);
+    unsafe { Layout::from_size_align_unchecked(size, size) }
+}
+
+/// An [`Allocator`] which caches up to [`capacity`](Self::capacity) freed blocks
+/// per power-of-two size class, serving allocations from the cache before asking `A`.
+///
+/// Cached blocks are returned to `A` on [`Self::purge`] or drop.
+#[derive(Debug)]
+pub struct Recycle<A: Allocator> {
+    inner: A,
+    capacity: usize,
+    heads: [Cell<Option<NonNull<Free>>>; CLASSES],
+    counts: [Cell<usize>; CLASSES],
+}
+
+unsafe impl<A: Allocator + Send> Send for Recycle<A> {}
+
+impl<A: Allocator> Recycle<A> {
+    pub const fn new(inner: A, capacity: usize) -> Self {
+        Self {
+            inner,
+            capacity,
+            heads: [const { Cell::new(None) }; CLASSES],
+            counts: [const { Cell::new(0) }; CLASSES],
+        }
+    }
+    /// The maximum number of cached blocks per size class.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+    /// Return all cached blocks to [`Self::inner`].
+    pub fn purge(&self) {
+        for (class, (head, count)) in self.heads.iter().zip(&self.counts).enumerate() {
+            let mut next = head.take();
+            while let Some(it) = next {
+                next = unsafe { it.as_ptr().read() }.next;
+                unsafe { self.inner.deallocate(it.cast(), layout_of(class)) }
+            }
+            count.set(0);
+        }
+    }
+}
+
+unsafe impl<A> Allocator for Recycle<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let Some(class) = class_of(layout) else {
+            return self.inner.allocate(layout);
+        };
+        match self.heads[class].get() {
+            Some(it) => {
+                self.heads[class].set(unsafe { it.as_ptr().read() }.next);
+                self.counts[class].set(self.counts[class].get() - 1);
+                Ok(NonNull::slice_from_raw_parts(
+                    it.cast(),
+                    layout_of(class).size(),
+                ))
+            }
+            None => self.inner.allocate(layout_of(class)),
+        }
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let Some(class) = class_of(layout) else {
+            return self.inner.deallocate(ptr, layout);
+        };
+        let count = self.counts[class].get();
+        if count == self.capacity {
+            return self.inner.deallocate(ptr, layout_of(class));
+        }
+        let free = ptr.cast::<Free>();
+        free.as_ptr().write(Free {
+            next: self.heads[class].get(),
+        });
+        self.heads[class].set(Some(free));
+        self.counts[class].set(count + 1);
+    }
+}
+
+unsafe impl<A> Owns for Recycle<A>
+where
+    A: Allocator + Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        match class_of(layout) {
+            Some(class) => self.inner.owns(ptr, layout_of(class)),
+            None => self.inner.owns(ptr, layout),
+        }
+    }
+}
+
+/// Purges the cache before trimming [`Recycle::inner`].
+impl<A> Trim for Recycle<A>
+where
+    A: Allocator + Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.purge();
+        self.inner.trim()
+    }
+}
+
+impl<A> Drop for Recycle<A>
+where
+    A: Allocator,
+{
+    fn drop(&mut self) {
+        self.purge()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn recycle() {
+    let a = Malloc.recycle(1);
+    let first = NonNull::from(&*Box::new_in(1u32, &a));
+    let second = Box::new_in(2u64, &a);
+    assert_eq!(first, NonNull::from(&*second).cast());
+    let third = Box::new_in(3u64, &a);
+    drop(second);
+    drop(third);
+    assert_eq!(a.counts[class_of(Layout::new::<u64>()).unwrap()].get(), 1);
+    a.purge();
+    assert_eq!(a.counts[class_of(Layout::new::<u64>()).unwrap()].get(), 0);
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn trim() {
+    let a = Malloc.stats().recycle(4);
+    drop(Box::new_in([0u8; 64], &a));
+    assert_eq!(a.inner().snapshot().live, 64);
+    a.trim();
+    assert_eq!(a.inner().snapshot().live, 0);
+}
@@ -0,0 +1,99 @@
+use crate::prelude::*;
+use core::{
+    marker::PhantomData,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// An [`Allocator`] which bump-allocates out of a caller-provided buffer.
+///
+/// Deallocating the most recent allocation reuses its space.
+///
+/// This can be constructed in a `static`:
+/// ```
+/// # use composable_allocators::Region;
+/// # use core::{mem::MaybeUninit, ptr};
+/// static mut BUFFER: [MaybeUninit<u8>; 1024] = [MaybeUninit::uninit(); 1024];
+/// static REGION: Region = Region::new(unsafe { &mut *ptr::addr_of_mut!(BUFFER) });
+/// ```
+#[derive(Debug)]
+pub struct Region<'a> {
+    start: *mut u8,
+    len: usize,
+    cursor: AtomicUsize,
+    _buffer: PhantomData<&'a mut [MaybeUninit<u8>]>,
+}
+
+unsafe impl Send for Region<'_> {}
+unsafe impl Sync for Region<'_> {}
+
+impl<'a> Region<'a> {
+    pub const fn new(buffer: &'a mut [MaybeUninit<u8>]) -> Self {
+        Self {
+            start: buffer.as_mut_ptr().cast(),
+            len: buffer.len(),
+            cursor: AtomicUsize::new(0),
+            _buffer: PhantomData,
+        }
+    }
+    /// The number of bytes in the buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+unsafe impl Allocator for Region<'_> {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let mut offset = 0;
+        self.cursor
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |cursor| {
+                let padding = self.start.wrapping_add(cursor).align_offset(layout.align());
+                offset = cursor.checked_add(padding)?;
+                let end = offset.checked_add(layout.size())?;
+                (end <= self.len).then_some(end)
+            })
+            .map_err(|_| AllocError)?;
+        let ptr = unsafe { NonNull::new_unchecked(self.start.add(offset)) };
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let offset = ptr.as_ptr() as usize - self.start as usize;
+        let _ = self.cursor.compare_exchange(
+            offset + layout.size(),
+            offset,
+            Ordering::AcqRel,
+            Ordering::Relaxed,
+        );
+    }
+}
+
+impl DeallocateAll for Region<'_> {
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.cursor.store(0, Ordering::Release)
+    }
+}
+
+unsafe impl Owns for Region<'_> {
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        let start = self.start as usize;
+        let ptr = ptr.as_ptr() as usize;
+        start <= ptr && ptr.saturating_add(layout.size()) <= start + self.len
+    }
+}
+
+#[test]
+fn region() {
+    static mut BUFFER: [MaybeUninit<u8>; 8] = [MaybeUninit::uninit(); 8];
+    static REGION: Region = Region::new(unsafe { &mut *core::ptr::addr_of_mut!(BUFFER) });
+    let first = Box::new_in([1u8; 4], &REGION);
+    let _second = Box::new_in([2u8; 4], &REGION);
+    Box::try_new_in(3u8, &REGION).unwrap_err();
+    assert!(REGION.owns(NonNull::from(&*first).cast(), Layout::new::<[u8; 4]>()));
+}
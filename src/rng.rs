@@ -0,0 +1,27 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// The [SplitMix64](https://prng.di.unimi.it/splitmix64.c) output function.
+#[inline(always)]
+pub(crate) const fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+/// A lock-free [SplitMix64](https://prng.di.unimi.it/splitmix64.c) generator.
+#[derive(Debug, Default)]
+pub(crate) struct SplitMix(AtomicU64);
+
+impl SplitMix {
+    pub const fn new(seed: u64) -> Self {
+        Self(AtomicU64::new(seed))
+    }
+    #[inline(always)]
+    pub fn next(&self) -> u64 {
+        const GAMMA: u64 = 0x9E3779B97F4A7C15;
+        mix(self
+            .0
+            .fetch_add(GAMMA, Ordering::Relaxed)
+            .wrapping_add(GAMMA))
+    }
+}
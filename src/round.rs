@@ -0,0 +1,274 @@
+use crate::prelude::*;
+use core::ptr;
+
+/// Decides which size a [`Rounded`] allocation is rounded up to.
+///
+/// Implemented for [`PowersOfTwo`], and for sorted tables of sizes like `[usize; N]`.
+pub trait SizeClasses {
+    /// The smallest class of at least `size` bytes, or [`None`] if there is no such class.
+    ///
+    /// Must be monotonic: any size between `size` and its class must be in the same class.
+    fn class(&self, size: usize) -> Option<usize>;
+    /// The layout to allocate for `layout`, or [`None`] if there is no such class.
+    ///
+    /// By default, this rounds up its size and leaves its alignment as it is.
+    #[inline(always)]
+    fn round(&self, layout: Layout) -> Option<Layout> {
+        Layout::from_size_align(self.class(layout.size())?, layout.align()).ok()
+    }
+}
+
+/// [`SizeClasses`] which round up to the next power of two.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct PowersOfTwo;
+
+impl SizeClasses for PowersOfTwo {
+    #[inline(always)]
+    fn class(&self, size: usize) -> Option<usize> {
+        size.checked_next_power_of_two()
+    }
+}
+
+/// Sizes larger than the last entry are left as they are.
+impl SizeClasses for [usize] {
+    #[inline(always)]
+    fn class(&self, size: usize) -> Option<usize> {
+        match self.get(self.partition_point(|it| *it < size)) {
+            Some(it) => Some(*it),
+            None => Some(size),
+        }
+    }
+}
+
+impl<const N: usize> SizeClasses for [usize; N] {
+    #[inline(always)]
+    fn class(&self, size: usize) -> Option<usize> {
+        self.as_slice().class(size)
+    }
+}
+
+impl<C> SizeClasses for &C
+where
+    C: SizeClasses + ?Sized,
+{
+    #[inline(always)]
+    fn class(&self, size: usize) -> Option<usize> {
+        (**self).class(size)
+    }
+    #[inline(always)]
+    fn round(&self, layout: Layout) -> Option<Layout> {
+        (**self).round(layout)
+    }
+}
+
+/// An [`Allocator`] which rounds the size of each allocation up to one of [`Self::classes`]
+/// before passing it to `A`, see [`SizeClasses::round`].
+///
+/// The whole block is returned, so containers can use the slack.
+/// Resizing within a class happens in place, without calling `A`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Rounded<A, C = PowersOfTwo> {
+    pub inner: A,
+    pub classes: C,
+}
+
+impl<A, C> Rounded<A, C> {
+    pub const fn new(inner: A, classes: C) -> Self {
+        Self { inner, classes }
+    }
+}
+
+impl<A, C> Rounded<A, C>
+where
+    C: SizeClasses,
+{
+    #[inline(always)]
+    fn round(&self, layout: Layout) -> Result<Layout, AllocError> {
+        self.classes.round(layout).ok_or(AllocError)
+    }
+    /// Resize in place if the class doesn't change.
+    ///
+    /// # Safety
+    /// - As for [`Allocator::grow`] or [`Allocator::shrink`].
+    #[inline(always)]
+    unsafe fn resize(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        zeroed: bool,
+    ) -> Result<NonNull<[u8]>, AllocError>
+    where
+        A: Allocator,
+    {
+        let old = self.round(old_layout).unwrap_unchecked();
+        let new = self.round(new_layout)?;
+        let res = match (old == new, new.size() >= old.size(), zeroed) {
+            (true, _, _) => NonNull::slice_from_raw_parts(ptr, new.size()),
+            (false, true, true) => self.inner.grow_zeroed(ptr, old, new)?,
+            (false, true, false) => self.inner.grow(ptr, old, new)?,
+            (false, false, _) => self.inner.shrink(ptr, old, new)?,
+        };
+        if zeroed {
+            // `A` only zeroes past the old class, but the caller may have used the slack
+            ptr::write_bytes(
+                res.cast::<u8>().as_ptr().add(old_layout.size()),
+                0,
+                old.size() - old_layout.size(),
+            );
+        }
+        Ok(res)
+    }
+}
+
+unsafe impl<A, C> Allocator for Rounded<A, C>
+where
+    A: Allocator,
+    C: SizeClasses,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.allocate(self.round(layout)?)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.inner
+            .deallocate(ptr, self.round(layout).unwrap_unchecked())
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.allocate_zeroed(self.round(layout)?)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, false)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, true)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, false)
+    }
+}
+
+unsafe impl<A, C> AllocatesZeroed for Rounded<A, C>
+where
+    A: AllocatesZeroed,
+    C: SizeClasses,
+{
+}
+
+impl<A, C> DeallocateAll for Rounded<A, C>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all()
+    }
+}
+
+impl<A, C> Rewind for Rounded<A, C>
+where
+    A: Rewind,
+{
+    type Checkpoint = A::Checkpoint;
+    #[inline(always)]
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.inner.checkpoint()
+    }
+    #[inline(always)]
+    unsafe fn rewind(&self, checkpoint: Self::Checkpoint) {
+        self.inner.rewind(checkpoint)
+    }
+}
+
+unsafe impl<A, C> Owns for Rounded<A, C>
+where
+    A: Owns,
+    C: SizeClasses,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        match self.round(layout) {
+            Ok(rounded) => self.inner.owns(ptr, rounded),
+            Err(_) => false,
+        }
+    }
+}
+
+impl<A, C> UsableSize for Rounded<A, C>
+where
+    A: UsableSize,
+    C: SizeClasses,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner
+            .usable_size(ptr, self.round(layout).unwrap_unchecked())
+    }
+}
+
+impl<A, C> Trim for Rounded<A, C>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[test]
+fn classes() {
+    assert_eq!(PowersOfTwo.class(0), Some(1));
+    assert_eq!(PowersOfTwo.class(5), Some(8));
+    assert_eq!(PowersOfTwo.class(usize::MAX), None);
+    let table = [16, 64, 256];
+    assert_eq!(table.class(1), Some(16));
+    assert_eq!(table.class(16), Some(16));
+    assert_eq!(table.class(17), Some(64));
+    assert_eq!(table.class(1000), Some(1000));
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn rounded() {
+    let a = Malloc.stats().round_to(PowersOfTwo);
+    let ptr = a.allocate(Layout::new::<[u8; 5]>()).unwrap();
+    assert_eq!(ptr.len(), 8);
+    assert_eq!(a.inner.snapshot().live, 8);
+    let mut v = allocator_api2::vec::Vec::<u8, _>::with_capacity_in(3, &a);
+    v.extend(0..=255);
+    v.truncate(3);
+    v.shrink_to_fit();
+    assert_eq!(v, [0, 1, 2]);
+    unsafe { a.deallocate(ptr.cast(), Layout::new::<[u8; 5]>()) };
+    drop(v);
+    assert_eq!(a.inner.snapshot().live, 0);
+    let zeroed = a.allocate_zeroed(Layout::new::<u8>()).unwrap().cast::<u8>();
+    let zeroed = unsafe {
+        zeroed.as_ptr().write(1);
+        a.grow_zeroed(zeroed, Layout::new::<u8>(), Layout::new::<[u8; 4]>())
+            .unwrap()
+    };
+    assert_eq!(unsafe { zeroed.as_ref() }, [1, 0, 0, 0]);
+    unsafe { a.deallocate(zeroed.cast(), Layout::new::<[u8; 4]>()) };
+}
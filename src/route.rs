@@ -0,0 +1,194 @@
+use crate::{prelude::*, segregate::relocate};
+
+/// Decides which side of a [`RouteBy`] handles a [`Layout`].
+///
+/// Implemented for closures, and for policy types like [`SizeAtMost`].
+pub trait Policy {
+    fn route(&self, layout: Layout) -> bool;
+}
+
+impl<F> Policy for F
+where
+    F: Fn(Layout) -> bool,
+{
+    #[inline(always)]
+    fn route(&self, layout: Layout) -> bool {
+        self(layout)
+    }
+}
+
+/// A [`Policy`] which routes layouts of at most `N` bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct SizeAtMost<const N: usize>;
+
+impl<const N: usize> Policy for SizeAtMost<N> {
+    #[inline(always)]
+    fn route(&self, layout: Layout) -> bool {
+        layout.size() <= N
+    }
+}
+
+/// A [`Policy`] which routes layouts aligned to at most `N` bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct AlignAtMost<const N: usize>;
+
+impl<const N: usize> Policy for AlignAtMost<N> {
+    #[inline(always)]
+    fn route(&self, layout: Layout) -> bool {
+        layout.align() <= N
+    }
+}
+
+/// An [`Allocator`] which sends a [`Layout`] to `A` if [`Self::policy`] returns `true`,
+/// and `B` otherwise.
+///
+/// Since the same decision is made on deallocation, neither needs to implement [`Owns`],
+/// but `F` must be deterministic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RouteBy<F, A, B> {
+    pub policy: F,
+    pub if_true: A,
+    pub if_false: B,
+}
+
+impl<F, A, B> RouteBy<F, A, B> {
+    pub const fn new(policy: F, if_true: A, if_false: B) -> Self {
+        Self {
+            policy,
+            if_true,
+            if_false,
+        }
+    }
+}
+
+unsafe impl<F, A, B> Allocator for RouteBy<F, A, B>
+where
+    F: Policy,
+    A: Allocator,
+    B: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        match self.policy.route(layout) {
+            true => self.if_true.allocate(layout),
+            false => self.if_false.allocate(layout),
+        }
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        match self.policy.route(layout) {
+            true => self.if_true.deallocate(ptr, layout),
+            false => self.if_false.deallocate(ptr, layout),
+        }
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        match self.policy.route(layout) {
+            true => self.if_true.allocate_zeroed(layout),
+            false => self.if_false.allocate_zeroed(layout),
+        }
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let (a, b) = (&self.if_true, &self.if_false);
+        match (self.policy.route(old_layout), self.policy.route(new_layout)) {
+            (true, true) => a.grow(ptr, old_layout, new_layout),
+            (false, false) => b.grow(ptr, old_layout, new_layout),
+            (true, false) => relocate(a, b, ptr, old_layout, new_layout, false),
+            (false, true) => relocate(b, a, ptr, old_layout, new_layout, false),
+        }
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let (a, b) = (&self.if_true, &self.if_false);
+        match (self.policy.route(old_layout), self.policy.route(new_layout)) {
+            (true, true) => a.grow_zeroed(ptr, old_layout, new_layout),
+            (false, false) => b.grow_zeroed(ptr, old_layout, new_layout),
+            (true, false) => relocate(a, b, ptr, old_layout, new_layout, true),
+            (false, true) => relocate(b, a, ptr, old_layout, new_layout, true),
+        }
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let (a, b) = (&self.if_true, &self.if_false);
+        match (self.policy.route(old_layout), self.policy.route(new_layout)) {
+            (true, true) => a.shrink(ptr, old_layout, new_layout),
+            (false, false) => b.shrink(ptr, old_layout, new_layout),
+            (true, false) => relocate(a, b, ptr, old_layout, new_layout, false),
+            (false, true) => relocate(b, a, ptr, old_layout, new_layout, false),
+        }
+    }
+}
+
+unsafe impl<F, A, B> AllocatesZeroed for RouteBy<F, A, B>
+where
+    F: Policy,
+    A: AllocatesZeroed,
+    B: AllocatesZeroed,
+{
+}
+
+impl<F, A, B> DeallocateAll for RouteBy<F, A, B>
+where
+    A: DeallocateAll,
+    B: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.if_true.deallocate_all();
+        self.if_false.deallocate_all()
+    }
+}
+
+unsafe impl<F, A, B> Owns for RouteBy<F, A, B>
+where
+    F: Policy,
+    A: Owns,
+    B: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        match self.policy.route(layout) {
+            true => self.if_true.owns(ptr, layout),
+            false => self.if_false.owns(ptr, layout),
+        }
+    }
+}
+
+impl<F, A, B> Trim for RouteBy<F, A, B>
+where
+    A: Trim,
+    B: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.if_true.trim();
+        self.if_false.trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn route_by() {
+    let a = Malloc.route_by(AlignAtMost::<8>, Null);
+    let _ = Box::new_in(1u64, &a);
+    a.allocate(Layout::from_size_align(16, 16).unwrap())
+        .unwrap_err();
+    let a = Null.route_by(|it: Layout| it.size() == 0, Malloc);
+    let _ = Box::new_in(1u8, &a);
+}
@@ -0,0 +1,256 @@
+use crate::prelude::*;
+use core::{cell::Cell, fmt, mem::ManuallyDrop};
+
+/// Escapes are reported with a [`LeakCheck`] in debug builds with `std`.
+#[cfg(all(feature = "std", debug_assertions))]
+type Checked<'a, A> = LeakCheck<&'a A>;
+#[cfg(not(all(feature = "std", debug_assertions)))]
+type Checked<'a, A> = &'a A;
+
+#[cfg(all(feature = "std", debug_assertions))]
+fn checked<A>(inner: &A) -> Checked<'_, A> {
+    let mut it = LeakCheck::new(inner);
+    it.on_drop = OnDrop::Ignore;
+    it
+}
+#[cfg(not(all(feature = "std", debug_assertions)))]
+fn checked<A>(inner: &A) -> Checked<'_, A> {
+    inner
+}
+
+/// A bump allocator for temporary allocations,
+/// which are made through a [`Scope`] and all freed when it is dropped.
+///
+/// Scopes may be nested, and only the innermost may allocate.
+///
+/// `A` is only reachable through a scope,
+/// since anything allocated from it directly would be freed by the next rewind:
+/// ```compile_fail
+/// # use composable_allocators::*;
+/// let scratch = Malloc.arena(64).scratch();
+/// let _ = Box::new_in(1u64, &scratch.inner);
+/// ```
+/// ```
+/// # use composable_allocators::*;
+/// let scratch = Malloc.arena(4096).scratch();
+/// for line in ["a b", "c d e"] {
+///     let scope = scratch.scope();
+///     let mut words = allocator_api2::vec::Vec::new_in(&scope);
+///     words.extend(line.split(' '));
+///     assert!(words.len() >= 2);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Scratch<A> {
+    inner: A,
+    depth: Cell<usize>,
+}
+
+impl<A> Scratch<A> {
+    pub const fn new(inner: A) -> Self {
+        Self {
+            inner,
+            depth: Cell::new(0),
+        }
+    }
+    /// Exclusive access to the bump allocator, while no scope is alive.
+    pub fn inner_mut(&mut self) -> &mut A {
+        &mut self.inner
+    }
+    pub fn into_inner(self) -> A {
+        self.inner
+    }
+}
+
+impl<A> Scratch<A>
+where
+    A: Rewind,
+{
+    /// Begin a [`Scope`], which must be dropped before any scope outside it.
+    pub fn scope(&self) -> Scope<'_, A> {
+        let depth = self.depth.get() + 1;
+        self.depth.set(depth);
+        Scope {
+            scratch: self,
+            inner: checked(&self.inner),
+            checkpoint: ManuallyDrop::new(self.inner.checkpoint()),
+            depth,
+            live: Cell::new(0),
+        }
+    }
+}
+
+/// An [`Allocator`] which allocates from a [`Scratch`],
+/// [rewinding](Rewind) it when dropped.
+///
+/// Allocations fail while a nested scope is alive.
+///
+/// Dropping a scope with allocations still live,
+/// e.g because a container was [forgotten](core::mem::forget),
+/// fails a debug assertion, which lists them with `LeakCheck` if `std` is enabled.
+pub struct Scope<'a, A: Rewind> {
+    scratch: &'a Scratch<A>,
+    inner: Checked<'a, A>,
+    checkpoint: ManuallyDrop<A::Checkpoint>,
+    depth: usize,
+    live: Cell<usize>,
+}
+
+impl<A> Scope<'_, A>
+where
+    A: Rewind,
+{
+    /// The number of allocations made through this scope which haven't been freed.
+    pub fn live(&self) -> usize {
+        self.live.get()
+    }
+    #[inline(always)]
+    fn innermost(&self) -> Result<(), AllocError> {
+        match self.scratch.depth.get() == self.depth {
+            true => Ok(()),
+            false => Err(AllocError),
+        }
+    }
+    #[inline(always)]
+    fn allocated(
+        &self,
+        res: Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if res.is_ok() {
+            self.live.set(self.live.get() + 1)
+        }
+        res
+    }
+}
+
+impl<A> Drop for Scope<'_, A>
+where
+    A: Rewind,
+{
+    fn drop(&mut self) {
+        assert_eq!(
+            self.scratch.depth.get(),
+            self.depth,
+            "scratch scopes must be dropped innermost first"
+        );
+        #[cfg(all(feature = "std", debug_assertions))]
+        if self.live.get() != 0 {
+            panic!(
+                "allocations escaped the scratch scope: {}",
+                crate::leak::Report(&self.inner.leaks())
+            )
+        }
+        debug_assert_eq!(self.live.get(), 0, "allocations escaped the scratch scope");
+        self.scratch.depth.set(self.depth - 1);
+        let checkpoint = unsafe { ManuallyDrop::take(&mut self.checkpoint) };
+        unsafe { self.scratch.inner.rewind(checkpoint) }
+    }
+}
+
+unsafe impl<A> Allocator for Scope<'_, A>
+where
+    A: Allocator + Rewind,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.innermost()?;
+        self.allocated(self.inner.allocate(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.live.set(self.live.get() - 1);
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.innermost()?;
+        self.allocated(self.inner.allocate_zeroed(layout))
+    }
+    // resizing may move the allocation past a nested scope's checkpoint
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.innermost()?;
+        self.inner.grow(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.innermost()?;
+        self.inner.grow_zeroed(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.innermost()?;
+        self.inner.shrink(ptr, old_layout, new_layout)
+    }
+}
+
+impl<A> fmt::Debug for Scope<'_, A>
+where
+    A: Rewind + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scope")
+            .field("scratch", &self.scratch)
+            .field("depth", &self.depth)
+            .field("live", &self.live)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn scratch() {
+    let stats = Malloc.stats();
+    let scratch = (&stats).arena(64).scratch();
+    let chunks = || {
+        let it = stats.snapshot();
+        it.allocations - it.deallocations
+    };
+    let outer = scratch.scope();
+    let kept = Box::new_in(1u64, &outer);
+    {
+        let inner = scratch.scope();
+        let _big = Box::new_in([0u8; 256], &inner);
+        Box::try_new_in(1u8, &outer).unwrap_err();
+        assert_eq!(chunks(), 2);
+    }
+    assert_eq!(chunks(), 1);
+    assert_eq!(*kept, 1);
+    drop(kept);
+    assert_eq!(outer.live(), 0);
+    drop(outer);
+    assert_eq!(chunks(), 0);
+}
+
+#[cfg(all(feature = "malloc", debug_assertions))]
+#[test]
+#[should_panic = "allocations escaped the scratch scope"]
+fn escaped() {
+    let scratch = Malloc.arena(64).scratch();
+    let scope = scratch.scope();
+    core::mem::forget(Box::new_in(1u8, &scope));
+}
+
+#[cfg(all(feature = "malloc", feature = "std", debug_assertions))]
+#[test]
+#[should_panic = "1 allocation(s) leaked"]
+fn escaped_report() {
+    let scratch = Malloc.arena(64).scratch();
+    let scope = scratch.scope();
+    core::mem::forget(Box::new_in(1u8, &scope));
+}
@@ -0,0 +1,184 @@
+use crate::prelude::*;
+use core::{cmp, ptr};
+
+/// Move an allocation from `from` to `to`.
+///
+/// # Safety
+/// - As for [`Allocator::grow`] or [`Allocator::shrink`] on `from`.
+#[inline(always)]
+pub(crate) unsafe fn relocate<From, To>(
+    from: &From,
+    to: &To,
+    ptr: NonNull<u8>,
+    old_layout: Layout,
+    new_layout: Layout,
+    zeroed: bool,
+) -> Result<NonNull<[u8]>, AllocError>
+where
+    From: Allocator + ?Sized,
+    To: Allocator + ?Sized,
+{
+    let new = match zeroed {
+        true => to.allocate_zeroed(new_layout)?,
+        false => to.allocate(new_layout)?,
+    };
+    ptr::copy_nonoverlapping(
+        ptr.as_ptr(),
+        new.cast::<u8>().as_ptr(),
+        cmp::min(old_layout.size(), new_layout.size()),
+    );
+    from.deallocate(ptr, old_layout);
+    Ok(new)
+}
+
+/// An [`Allocator`] which sends allocations of at most [`Self::threshold`] bytes to `SmallT`,
+/// and larger allocations to `LargeT`.
+///
+/// Since the same rule is applied on deallocation, neither needs to implement [`Owns`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Segregate<SmallT, LargeT> {
+    pub small: SmallT,
+    pub large: LargeT,
+    pub threshold: usize,
+}
+
+impl<SmallT, LargeT> Segregate<SmallT, LargeT> {
+    pub const fn new(threshold: usize, small: SmallT, large: LargeT) -> Self {
+        Self {
+            small,
+            large,
+            threshold,
+        }
+    }
+    #[inline(always)]
+    fn is_small(&self, layout: Layout) -> bool {
+        layout.size() <= self.threshold
+    }
+}
+
+unsafe impl<SmallT, LargeT> Allocator for Segregate<SmallT, LargeT>
+where
+    SmallT: Allocator,
+    LargeT: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        match self.is_small(layout) {
+            true => self.small.allocate(layout),
+            false => self.large.allocate(layout),
+        }
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        match self.is_small(layout) {
+            true => self.small.deallocate(ptr, layout),
+            false => self.large.deallocate(ptr, layout),
+        }
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        match self.is_small(layout) {
+            true => self.small.allocate_zeroed(layout),
+            false => self.large.allocate_zeroed(layout),
+        }
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        match (self.is_small(old_layout), self.is_small(new_layout)) {
+            (true, true) => self.small.grow(ptr, old_layout, new_layout),
+            (false, false) => self.large.grow(ptr, old_layout, new_layout),
+            (true, false) => relocate(&self.small, &self.large, ptr, old_layout, new_layout, false),
+            (false, true) => relocate(&self.large, &self.small, ptr, old_layout, new_layout, false),
+        }
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        match (self.is_small(old_layout), self.is_small(new_layout)) {
+            (true, true) => self.small.grow_zeroed(ptr, old_layout, new_layout),
+            (false, false) => self.large.grow_zeroed(ptr, old_layout, new_layout),
+            (true, false) => relocate(&self.small, &self.large, ptr, old_layout, new_layout, true),
+            (false, true) => relocate(&self.large, &self.small, ptr, old_layout, new_layout, true),
+        }
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        match (self.is_small(old_layout), self.is_small(new_layout)) {
+            (true, true) => self.small.shrink(ptr, old_layout, new_layout),
+            (false, false) => self.large.shrink(ptr, old_layout, new_layout),
+            (true, false) => relocate(&self.small, &self.large, ptr, old_layout, new_layout, false),
+            (false, true) => relocate(&self.large, &self.small, ptr, old_layout, new_layout, false),
+        }
+    }
+}
+
+unsafe impl<SmallT, LargeT> AllocatesZeroed for Segregate<SmallT, LargeT>
+where
+    SmallT: AllocatesZeroed,
+    LargeT: AllocatesZeroed,
+{
+}
+
+impl<SmallT, LargeT> DeallocateAll for Segregate<SmallT, LargeT>
+where
+    SmallT: DeallocateAll,
+    LargeT: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.small.deallocate_all();
+        self.large.deallocate_all()
+    }
+}
+
+unsafe impl<SmallT, LargeT> Owns for Segregate<SmallT, LargeT>
+where
+    SmallT: Owns,
+    LargeT: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        match self.is_small(layout) {
+            true => self.small.owns(ptr, layout),
+            false => self.large.owns(ptr, layout),
+        }
+    }
+}
+
+impl<SmallT, LargeT> Trim for Segregate<SmallT, LargeT>
+where
+    SmallT: Trim,
+    LargeT: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.small.trim();
+        self.large.trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn segregate() {
+    let a = Malloc.limit_count(2).segregate(4, Malloc);
+    let _small = Box::new_in(1u32, &a);
+    let _large = Box::new_in(1u64, &a);
+    let mut v = allocator_api2::vec::Vec::<u8, _>::with_capacity_in(4, &a);
+    Box::try_new_in(1u32, &a).unwrap_err();
+    v.extend_from_slice(&[1u8; 16]);
+    let _ = Box::new_in(1u32, &a);
+}
@@ -0,0 +1,350 @@
+use crate::{pages::page_size, prelude::*};
+use core::{
+    ffi::{c_int, CStr},
+    fmt, hash,
+    marker::PhantomData,
+    mem, ptr,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+/// A failed system call, with its error number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OsError(pub c_int);
+
+impl fmt::Display for OsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "system call failed with error {}", self.0)
+    }
+}
+
+impl OsError {
+    pub(crate) fn last() -> Self {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        use libc::__errno_location as location;
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        use libc::__error as location;
+        Self(unsafe { *location() })
+    }
+}
+
+/// Checks that a segment was created by [`SharedMem::create`].
+const MAGIC: u64 = u64::from_le_bytes(*b"cashmem1");
+
+/// At the start of every segment.
+#[repr(C)]
+struct Header {
+    magic: AtomicU64,
+    /// The offset of the first free byte.
+    cursor: AtomicUsize,
+}
+
+/// An [`Allocator`] which bump-allocates out of a named POSIX shared memory segment,
+/// from `shm_open` and `mmap`.
+///
+/// The segment may be mapped at different addresses in each process,
+/// so all bookkeeping is by offset from its start,
+/// and pointers should be passed between processes as [`Offset`]s:
+/// ```
+/// # use composable_allocators::*;
+/// let name = c"/composable-allocators-doctest";
+/// # let _ = SharedMem::unlink(name);
+/// let here = SharedMem::create(name, 4096).unwrap();
+/// let there = SharedMem::open(name).unwrap();
+/// SharedMem::unlink(name).unwrap();
+/// let it = allocator_api2::boxed::Box::new_in(7u32, &here);
+/// let offset = here.offset_of(core::ptr::NonNull::from(&*it)).unwrap();
+/// assert_eq!(unsafe { *there.resolve(offset).unwrap().as_ptr() }, 7);
+/// ```
+///
+/// Like [`Region`], deallocating the most recent allocation reuses its space.
+/// Alignments larger than the page size are not supported,
+/// since they might not hold in every mapping.
+#[derive(Debug)]
+pub struct SharedMem {
+    base: NonNull<u8>,
+    len: usize,
+    fd: c_int,
+}
+
+unsafe impl Send for SharedMem {}
+unsafe impl Sync for SharedMem {}
+
+impl SharedMem {
+    /// Create and map a new segment called `name` of `len` bytes, including a small header.
+    ///
+    /// Fails with `EEXIST` if it already exists.
+    pub fn create(name: &CStr, len: usize) -> Result<Self, OsError> {
+        let fd = unsafe {
+            libc::shm_open(
+                name.as_ptr(),
+                libc::O_RDWR | libc::O_CREAT | libc::O_EXCL,
+                0o600,
+            )
+        };
+        if fd < 0 {
+            return Err(OsError::last());
+        }
+        unsafe { Self::init(fd, len) }.inspect_err(|_| unsafe {
+            libc::shm_unlink(name.as_ptr());
+        })
+    }
+    /// Map an existing segment called `name`, created by [`Self::create`].
+    ///
+    /// Fails with `EINVAL` if it wasn't.
+    pub fn open(name: &CStr) -> Result<Self, OsError> {
+        let fd = unsafe { libc::shm_open(name.as_ptr(), libc::O_RDWR, 0) };
+        if fd < 0 {
+            return Err(OsError::last());
+        }
+        unsafe { Self::attach(fd) }
+    }
+    /// Remove the segment called `name`.
+    ///
+    /// Existing mappings stay valid until they are dropped.
+    pub fn unlink(name: &CStr) -> Result<(), OsError> {
+        match unsafe { libc::shm_unlink(name.as_ptr()) } {
+            0 => Ok(()),
+            _ => Err(OsError::last()),
+        }
+    }
+    /// Resize the empty file `fd` to `len` bytes, map it, and write the header.
+    ///
+    /// # Safety
+    /// - `fd` must be an open, writable file, which is owned by the result,
+    ///   or closed on failure.
+    pub(crate) unsafe fn init(fd: c_int, len: usize) -> Result<Self, OsError> {
+        let this = libc::off_t::try_from(len)
+            .map_err(|_| OsError(libc::EINVAL))
+            .and_then(|off| match libc::ftruncate(fd, off) {
+                0 => Self::map(fd, len),
+                _ => Err(OsError::last()),
+            })
+            .inspect_err(|_| {
+                libc::close(fd);
+            })?;
+        let header = this.header();
+        header
+            .cursor
+            .store(mem::size_of::<Header>(), Ordering::Release);
+        header.magic.store(MAGIC, Ordering::Release);
+        Ok(this)
+    }
+    /// Map the file `fd`, which must already have a header written by [`Self::init`].
+    ///
+    /// # Safety
+    /// - As for [`Self::init`].
+    pub(crate) unsafe fn attach(fd: c_int) -> Result<Self, OsError> {
+        let mut stat = mem::MaybeUninit::<libc::stat>::uninit();
+        let this = match libc::fstat(fd, stat.as_mut_ptr()) {
+            0 => match usize::try_from(stat.assume_init().st_size) {
+                Ok(len) => Self::map(fd, len),
+                Err(_) => Err(OsError(libc::EINVAL)),
+            },
+            _ => Err(OsError::last()),
+        }
+        .inspect_err(|_| {
+            libc::close(fd);
+        })?;
+        // dropping `this` unmaps and closes it
+        match this.header().magic.load(Ordering::Acquire) == MAGIC {
+            true => Ok(this),
+            false => Err(OsError(libc::EINVAL)),
+        }
+    }
+    /// Unmap the segment, returning its file descriptor.
+    pub(crate) fn into_fd(self) -> c_int {
+        let this = mem::ManuallyDrop::new(self);
+        unsafe { libc::munmap(this.base.as_ptr().cast(), this.len) };
+        this.fd
+    }
+    /// # Safety
+    /// - `fd` must be a shared memory object of at least `len` bytes.
+    unsafe fn map(fd: c_int, len: usize) -> Result<Self, OsError> {
+        if len < mem::size_of::<Header>() {
+            return Err(OsError(libc::EINVAL));
+        }
+        match libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd,
+            0,
+        ) {
+            libc::MAP_FAILED => Err(OsError::last()),
+            it => Ok(Self {
+                base: NonNull::new_unchecked(it.cast()),
+                len,
+                fd,
+            }),
+        }
+    }
+    #[inline(always)]
+    fn header(&self) -> &Header {
+        unsafe { &*self.base.as_ptr().cast::<Header>() }
+    }
+    /// The size of the segment, including the header.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// The file descriptor of the segment.
+    pub fn fd(&self) -> c_int {
+        self.fd
+    }
+    /// Where the segment is mapped in this process.
+    pub fn base(&self) -> NonNull<u8> {
+        self.base
+    }
+    /// The offset of `ptr` in this segment,
+    /// or [`None`] if it isn't in this mapping.
+    pub fn offset_of<T>(&self, ptr: NonNull<T>) -> Option<Offset<T>> {
+        let offset = (ptr.as_ptr() as usize).checked_sub(self.base.as_ptr() as usize)?;
+        (offset.checked_add(mem::size_of::<T>())? <= self.len).then_some(Offset::new(offset))
+    }
+    /// A pointer to `offset` in this mapping,
+    /// or [`None`] if it is out of bounds.
+    ///
+    /// The pointer is only valid to dereference if an allocation was made there,
+    /// in any mapping of the segment.
+    pub fn resolve<T>(&self, offset: Offset<T>) -> Option<NonNull<T>> {
+        (offset.get().checked_add(mem::size_of::<T>())? <= self.len)
+            .then(|| unsafe { NonNull::new_unchecked(self.base.as_ptr().add(offset.get()).cast()) })
+    }
+}
+
+impl Drop for SharedMem {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.base.as_ptr().cast(), self.len);
+            libc::close(self.fd);
+        }
+    }
+}
+
+unsafe impl Allocator for SharedMem {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if layout.align() > page_size() {
+            return Err(AllocError);
+        }
+        let mut offset = 0;
+        self.header()
+            .cursor
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |cursor| {
+                // the base is page-aligned in every mapping
+                offset = cursor.checked_next_multiple_of(layout.align())?;
+                let end = offset.checked_add(layout.size())?;
+                (end <= self.len).then_some(end)
+            })
+            .map_err(|_| AllocError)?;
+        let ptr = unsafe { NonNull::new_unchecked(self.base.as_ptr().add(offset)) };
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let offset = ptr.as_ptr() as usize - self.base.as_ptr() as usize;
+        let _ = self.header().cursor.compare_exchange(
+            offset + layout.size(),
+            offset,
+            Ordering::AcqRel,
+            Ordering::Relaxed,
+        );
+    }
+}
+
+/// This frees allocations made through every mapping of the segment.
+impl DeallocateAll for SharedMem {
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.header()
+            .cursor
+            .store(mem::size_of::<Header>(), Ordering::Release)
+    }
+}
+
+unsafe impl Owns for SharedMem {
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        let base = self.base.as_ptr() as usize;
+        let ptr = ptr.as_ptr() as usize;
+        base + mem::size_of::<Header>() <= ptr
+            && ptr.saturating_add(layout.size()) <= base + self.len
+    }
+}
+
+/// A pointer to a `T` in a [`SharedMem`] segment, as an offset from its start,
+/// so that it is meaningful in every process which maps the segment.
+///
+/// See [`SharedMem::offset_of`] and [`SharedMem::resolve`].
+#[repr(transparent)]
+pub struct Offset<T> {
+    offset: usize,
+    _pointee: PhantomData<fn() -> T>,
+}
+
+impl<T> Offset<T> {
+    pub const fn new(offset: usize) -> Self {
+        Self {
+            offset,
+            _pointee: PhantomData,
+        }
+    }
+    pub const fn get(self) -> usize {
+        self.offset
+    }
+}
+
+impl<T> Clone for Offset<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Offset<T> {}
+
+impl<T> PartialEq for Offset<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.offset == other.offset
+    }
+}
+
+impl<T> Eq for Offset<T> {}
+
+impl<T> hash::Hash for Offset<T> {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.offset.hash(state)
+    }
+}
+
+impl<T> fmt::Debug for Offset<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Offset").field(&self.offset).finish()
+    }
+}
+
+#[test]
+fn shared_mem() {
+    let name = c"/composable-allocators-test";
+    let _ = SharedMem::unlink(name);
+    let here = SharedMem::create(name, 64).unwrap();
+    assert_eq!(
+        SharedMem::create(name, 64).unwrap_err(),
+        OsError(libc::EEXIST)
+    );
+    let there = SharedMem::open(name).unwrap();
+    SharedMem::unlink(name).unwrap();
+    assert_ne!(here.base(), there.base());
+    let first = Box::new_in(1u64, &here);
+    let second = Box::new_in(2u64, &there);
+    let offset = here.offset_of(NonNull::from(&*first)).unwrap();
+    assert_eq!(unsafe { *there.resolve(offset).unwrap().as_ptr() }, 1);
+    assert!(here.owns(NonNull::from(&*first).cast(), Layout::new::<u64>()));
+    assert!(!here.owns(NonNull::from(&*second).cast(), Layout::new::<u64>()));
+    assert!(there.resolve(Offset::<u64>::new(60)).is_none());
+    Box::try_new_in([0u8; 64], &here).unwrap_err();
+    drop((first, second));
+}
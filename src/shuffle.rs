@@ -0,0 +1,189 @@
+use crate::{prelude::*, rng::SplitMix, segregate::relocate};
+use core::{cmp, mem};
+
+/// An [`Allocator`] which places each allocation at a random offset within a larger block from `A`,
+/// frustrating exploits which depend on the layout of the heap.
+///
+/// Each allocation is offset by a random number of steps, from zero to [`Self::slots`] inclusive,
+/// where a step is the larger of its alignment and a `usize`.
+/// The offset is stored immediately before the allocation, so it can be recovered on deallocation.
+///
+/// Resizing always moves the allocation to a new random offset.
+#[derive(Debug)]
+pub struct Shuffle<A> {
+    pub inner: A,
+    slots: usize,
+    rng: SplitMix,
+}
+
+impl<A> Shuffle<A> {
+    pub const fn new(inner: A, seed: u64, slots: usize) -> Self {
+        Self {
+            inner,
+            slots,
+            rng: SplitMix::new(seed),
+        }
+    }
+    /// Fixed at construction, since deallocation recomputes the size of the outer block from it.
+    pub const fn slots(&self) -> usize {
+        self.slots
+    }
+    /// Returns `(outer, step)`.
+    #[inline(always)]
+    fn outer(&self, layout: Layout) -> Option<(Layout, usize)> {
+        let step = cmp::max(layout.align(), mem::size_of::<usize>());
+        let size = step
+            .checked_mul(self.slots.checked_add(1)?)?
+            .checked_add(layout.size())?;
+        Some((Layout::from_size_align(size, step).ok()?, step))
+    }
+    #[inline(always)]
+    unsafe fn header(body: NonNull<u8>) -> NonNull<usize> {
+        body.sub(mem::size_of::<usize>()).cast()
+    }
+}
+
+impl<A> Shuffle<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate_with(&self, layout: Layout, zeroed: bool) -> Result<NonNull<[u8]>, AllocError> {
+        let (outer, step) = self.outer(layout).ok_or(AllocError)?;
+        let start = match zeroed {
+            true => self.inner.allocate_zeroed(outer)?,
+            false => self.inner.allocate(outer)?,
+        };
+        let slot = match self.slots {
+            0 => 0,
+            slots => (self.rng.next() % (slots as u64 + 1)) as usize,
+        };
+        let body_offset = step * (slot + 1);
+        unsafe {
+            let body = start.cast::<u8>().add(body_offset);
+            Self::header(body).write(body_offset);
+            Ok(NonNull::slice_from_raw_parts(body, layout.size()))
+        }
+    }
+}
+
+unsafe impl<A> Allocator for Shuffle<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocate_with(layout, false)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let (outer, _) = self.outer(layout).unwrap_unchecked();
+        let body_offset = Self::header(ptr).read();
+        self.inner.deallocate(ptr.sub(body_offset), outer)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocate_with(layout, true)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        relocate(self, self, ptr, old_layout, new_layout, false)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        relocate(self, self, ptr, old_layout, new_layout, true)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        relocate(self, self, ptr, old_layout, new_layout, false)
+    }
+}
+
+impl<A> DeallocateAll for Shuffle<A>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all()
+    }
+}
+
+/// Each possible offset is tried in turn,
+/// so this costs up to [`Shuffle::slots`] + 1 calls to `A`.
+///
+/// The header is only read if `A` [owns](Owns::owns) the whole allocation.
+unsafe impl<A> Owns for Shuffle<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        let Some((outer, step)) = self.outer(layout) else {
+            return false;
+        };
+        (1..=self.slots + 1).any(|slot| {
+            let body_offset = step * slot;
+            // `ptr` may not be ours, so don't assume it's in bounds
+            match NonNull::new(ptr.as_ptr().wrapping_byte_sub(body_offset)) {
+                Some(start) => {
+                    self.inner.owns(start, outer)
+                        && unsafe { Self::header(ptr).read() } == body_offset
+                }
+                None => false,
+            }
+        })
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn shuffle() {
+    let a = Malloc.shuffle(0xDEADBEEF, 7);
+    let layout = Layout::from_size_align(24, 32).unwrap();
+    let mut offsets = [false; 8];
+    assert_eq!(a.slots() + 1, offsets.len());
+    for _ in 0..256 {
+        let body = a.allocate(layout).unwrap().cast::<u8>();
+        assert_eq!(body.as_ptr() as usize % 32, 0);
+        let body_offset = unsafe { Shuffle::<Malloc>::header(body).read() };
+        offsets[body_offset / 32 - 1] = true;
+        unsafe { a.deallocate(body, layout) };
+    }
+    assert_eq!(offsets, [true; 8]);
+    let mut v = allocator_api2::vec::Vec::<u8, _>::new_in(&a);
+    v.extend(0..=255);
+    v.truncate(3);
+    v.shrink_to_fit();
+    assert_eq!(v, [0, 1, 2]);
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn owns() {
+    let a = Malloc.tracked().shuffle(0xDEADBEEF, 3).or(Malloc);
+    let layout = Layout::new::<u64>();
+    let ours = [(); 8].map(|_| a.primary.allocate(layout).unwrap().cast());
+    let theirs = a.fallback.allocate(layout).unwrap().cast();
+    assert!(ours.iter().all(|it| a.primary.owns(*it, layout)));
+    assert!(!a.primary.owns(theirs, layout));
+    unsafe {
+        ours.iter().for_each(|it| a.deallocate(*it, layout));
+        a.deallocate(theirs, layout);
+    }
+}
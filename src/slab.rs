@@ -0,0 +1,239 @@
+use crate::prelude::*;
+use core::{fmt, mem};
+
+/// No more vacant entries.
+const END: usize = usize::MAX;
+
+enum Entry<T> {
+    /// Holds the key of the next vacant entry.
+    Vacant(usize),
+    Occupied(T),
+}
+
+/// A collection of values of type `T`, keyed by small integers,
+/// stored in chunks of [`chunk_len`](Self::chunk_len) entries requested from `A` as it grows.
+///
+/// Values never move, and the keys of removed values are reused,
+/// most recently removed first, so keys stay dense, e.g for connection tables.
+/// Use a [`TypedPool`] for handles which are never reused.
+/// ```
+/// # use composable_allocators::*;
+/// let mut connections = Malloc.stats().slab::<&str>(64);
+/// let a = connections.insert("10.0.0.1").unwrap();
+/// let b = connections.insert("10.0.0.2").unwrap();
+/// assert_eq!((a, b), (0, 1));
+/// assert_eq!(connections.remove(a), Some("10.0.0.1"));
+/// assert_eq!(connections.insert("10.0.0.3"), Ok(a));
+/// assert_eq!(connections.get(b), Some(&"10.0.0.2"));
+/// // one chunk, and the table of chunks
+/// assert_eq!(connections.inner().snapshot().allocations, 2);
+/// ```
+pub struct Slab<T, A: Allocator> {
+    inner: A,
+    chunk_len: usize,
+    /// A table of [`Self::chunks`] pointers, with room for [`Self::table_len`].
+    table: NonNull<NonNull<Entry<T>>>,
+    table_len: usize,
+    chunks: usize,
+    /// Entries past this key have never been occupied.
+    fresh: usize,
+    vacant: usize,
+    len: usize,
+}
+
+unsafe impl<T: Send, A: Allocator + Send> Send for Slab<T, A> {}
+unsafe impl<T: Sync, A: Allocator + Sync> Sync for Slab<T, A> {}
+
+impl<T, A: Allocator> Slab<T, A> {
+    pub const fn new(inner: A, chunk_len: usize) -> Self {
+        Self {
+            inner,
+            chunk_len,
+            table: NonNull::dangling(),
+            table_len: 0,
+            chunks: 0,
+            fresh: 0,
+            vacant: END,
+            len: 0,
+        }
+    }
+    /// The number of entries requested from [`Self::inner`] at a time.
+    pub fn chunk_len(&self) -> usize {
+        self.chunk_len
+    }
+    /// The number of values.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+    #[inline(always)]
+    fn chunk_layout(&self) -> Layout {
+        // checked when the first chunk is allocated
+        unsafe { Layout::array::<Entry<T>>(self.chunk_len).unwrap_unchecked() }
+    }
+    /// # Safety
+    /// - `key` must be less than [`Self::fresh`].
+    #[inline(always)]
+    unsafe fn entry(&self, key: usize) -> NonNull<Entry<T>> {
+        let chunk = self.table.add(key / self.chunk_len).read();
+        chunk.add(key % self.chunk_len)
+    }
+    /// Request another chunk from [`Self::inner`], growing the table if it's full.
+    fn add_chunk(&mut self) -> Result<(), AllocError> {
+        let chunk_layout = Layout::array::<Entry<T>>(self.chunk_len).map_err(|_| AllocError)?;
+        if chunk_layout.size() == 0 {
+            return Err(AllocError);
+        }
+        if self.chunks == self.table_len {
+            let new_len = match self.table_len {
+                0 => 4,
+                len => len.checked_mul(2).ok_or(AllocError)?,
+            };
+            let new_layout = Layout::array::<NonNull<Entry<T>>>(new_len).map_err(|_| AllocError)?;
+            let table = match self.table_len {
+                0 => self.inner.allocate(new_layout)?,
+                len => unsafe {
+                    let old_layout = Layout::array::<NonNull<Entry<T>>>(len).unwrap_unchecked();
+                    self.inner.grow(self.table.cast(), old_layout, new_layout)?
+                },
+            };
+            self.table = table.cast();
+            self.table_len = new_len;
+        }
+        let chunk = self.inner.allocate(chunk_layout)?.cast();
+        unsafe { self.table.add(self.chunks).write(chunk) };
+        self.chunks += 1;
+        Ok(())
+    }
+    /// Store `value`, returning its key,
+    /// or returning `value` if a chunk couldn't be allocated.
+    pub fn insert(&mut self, value: T) -> Result<usize, T> {
+        let key = match self.vacant {
+            END => {
+                if self.fresh == self.chunks * self.chunk_len && self.add_chunk().is_err() {
+                    return Err(value);
+                }
+                self.fresh += 1;
+                self.fresh - 1
+            }
+            key => {
+                let Entry::Vacant(next) = (unsafe { self.entry(key).read() }) else {
+                    unreachable!()
+                };
+                self.vacant = next;
+                key
+            }
+        };
+        unsafe { self.entry(key).write(Entry::Occupied(value)) };
+        self.len += 1;
+        Ok(key)
+    }
+    pub fn contains(&self, key: usize) -> bool {
+        self.get(key).is_some()
+    }
+    pub fn get(&self, key: usize) -> Option<&T> {
+        if key >= self.fresh {
+            return None;
+        }
+        match unsafe { &*self.entry(key).as_ptr() } {
+            Entry::Occupied(it) => Some(it),
+            Entry::Vacant(_) => None,
+        }
+    }
+    pub fn get_mut(&mut self, key: usize) -> Option<&mut T> {
+        if key >= self.fresh {
+            return None;
+        }
+        match unsafe { &mut *self.entry(key).as_ptr() } {
+            Entry::Occupied(it) => Some(it),
+            Entry::Vacant(_) => None,
+        }
+    }
+    /// Remove a value, so that its key may be reused.
+    pub fn remove(&mut self, key: usize) -> Option<T> {
+        if key >= self.fresh {
+            return None;
+        }
+        let entry = unsafe { &mut *self.entry(key).as_ptr() };
+        match mem::replace(entry, Entry::Vacant(self.vacant)) {
+            Entry::Occupied(it) => {
+                self.vacant = key;
+                self.len -= 1;
+                Some(it)
+            }
+            vacant => {
+                *entry = vacant;
+                None
+            }
+        }
+    }
+}
+
+impl<T, A: Allocator> Drop for Slab<T, A> {
+    fn drop(&mut self) {
+        unsafe {
+            for key in 0..self.fresh {
+                self.entry(key).drop_in_place()
+            }
+            for ix in 0..self.chunks {
+                let chunk = self.table.add(ix).read();
+                self.inner.deallocate(chunk.cast(), self.chunk_layout())
+            }
+            if self.table_len != 0 {
+                let layout = Layout::array::<NonNull<Entry<T>>>(self.table_len).unwrap_unchecked();
+                self.inner.deallocate(self.table.cast(), layout)
+            }
+        }
+    }
+}
+
+impl<T, A> fmt::Debug for Slab<T, A>
+where
+    A: Allocator + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Slab")
+            .field("inner", &self.inner)
+            .field("chunk_len", &self.chunk_len)
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn slab() {
+    let mut slab = Malloc.stats().slab::<u64>(2);
+    for it in 0..10 {
+        assert_eq!(slab.insert(it), Ok(it as usize));
+    }
+    let first = slab.get(0).unwrap() as *const u64;
+    assert_eq!(slab.remove(3), Some(3));
+    assert_eq!(slab.remove(3), None);
+    assert_eq!(slab.remove(7), Some(7));
+    assert!(!slab.contains(7));
+    assert_eq!(slab.insert(70), Ok(7));
+    assert_eq!(slab.insert(30), Ok(3));
+    assert_eq!(slab.insert(10), Ok(10));
+    *slab.get_mut(10).unwrap() += 1;
+    assert_eq!(slab.get(10), Some(&11));
+    assert_eq!(slab.get(12), None);
+    assert_eq!(slab.len(), 11);
+    assert_eq!(slab.get(0).unwrap() as *const u64, first);
+    // six chunks, and a table which grew once
+    let snapshot = slab.inner().snapshot();
+    assert_eq!((snapshot.allocations, snapshot.reallocations), (7, 1));
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn slab_oom() {
+    let mut slab = Malloc.limit_count(2).slab::<u8>(1);
+    assert_eq!(slab.insert(1), Ok(0));
+    assert_eq!(slab.insert(2), Err(2));
+}
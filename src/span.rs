@@ -0,0 +1,132 @@
+use crate::prelude::*;
+
+/// An [`Allocator`] which implements [`Owns`] for an `A` which allocates from a known span,
+/// e.g a fixed heap like `talc::Talck` or `linked_list_allocator::LockedHeap` with [`FromGlobal`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InSpan<A> {
+    inner: A,
+    span: NonNull<[u8]>,
+}
+
+unsafe impl<A: Send> Send for InSpan<A> {}
+unsafe impl<A: Sync> Sync for InSpan<A> {}
+
+impl<A> InSpan<A> {
+    /// # Safety
+    /// - every allocation from `inner` must lie within `span`,
+    ///   and nothing else may be allocated within `span`.
+    pub const unsafe fn new(inner: A, span: NonNull<[u8]>) -> Self {
+        Self { inner, span }
+    }
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+    pub fn span(&self) -> NonNull<[u8]> {
+        self.span
+    }
+}
+
+unsafe impl<A> Allocator for InSpan<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.allocate(layout)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.allocate_zeroed(layout)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.grow(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.grow_zeroed(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.shrink(ptr, old_layout, new_layout)
+    }
+}
+
+impl<A> DeallocateAll for InSpan<A>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all()
+    }
+}
+
+/// Zero-sized allocations are never owned, since they may dangle anywhere.
+unsafe impl<A> Owns for InSpan<A> {
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        let base = self.span.cast::<u8>().as_ptr() as usize;
+        let start = ptr.as_ptr() as usize;
+        let Some(end) = start.checked_add(layout.size()) else {
+            return false;
+        };
+        layout.size() != 0 && base <= start && end <= base + self.span.len()
+    }
+}
+
+impl<A> UsableSize for InSpan<A>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A> Trim for InSpan<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn in_span() {
+    let inline = Inline::<64>::new();
+    let span = (&inline).allocate(Layout::new::<[u8; 64]>()).unwrap();
+    unsafe { (&inline).deallocate(span.cast(), Layout::new::<[u8; 64]>()) };
+    let a = unsafe { InSpan::new(&inline, span) }.or(Malloc);
+    let inside = Box::new_in(1u32, &a);
+    let outside = Box::new_in([1u8; 128], &a);
+    assert!(a
+        .primary
+        .owns(NonNull::from(&*inside).cast(), Layout::new::<u32>()));
+    assert!(!a
+        .primary
+        .owns(NonNull::from(&*outside).cast(), Layout::new::<[u8; 128]>()));
+}
@@ -0,0 +1,185 @@
+use crate::prelude::*;
+use core::{cell::Cell, ptr};
+
+/// A position in a [`Stack`], see [`Stack::marker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Marker(usize);
+
+/// An [`Allocator`] which allocates LIFO out of a single region of
+/// [`capacity`](Self::capacity) bytes requested from `A` on first use.
+///
+/// Deallocating the top allocation pops it,
+/// and [`Self::restore`] pops everything allocated after a [`Marker`].
+/// Other deallocations are no-ops.
+#[derive(Debug)]
+pub struct Stack<A: Allocator> {
+    inner: A,
+    capacity: usize,
+    region: Cell<Option<NonNull<u8>>>,
+    top: Cell<usize>,
+}
+
+unsafe impl<A: Allocator + Send> Send for Stack<A> {}
+
+impl<A: Allocator> Stack<A> {
+    pub const fn new(inner: A, capacity: usize) -> Self {
+        Self {
+            inner,
+            capacity,
+            region: Cell::new(None),
+            top: Cell::new(0),
+        }
+    }
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+    /// The current top of the stack.
+    pub fn marker(&self) -> Marker {
+        Marker(self.top.get())
+    }
+    /// Pop every allocation made since `marker` was taken.
+    ///
+    /// # Safety
+    /// - `marker` must be from [`Self::marker`] on this [`Stack`].
+    /// - allocations made after `marker` was taken must not be used after this call.
+    pub unsafe fn restore(&self, marker: Marker) {
+        debug_assert!(marker.0 <= self.top.get());
+        self.top.set(marker.0)
+    }
+    #[inline(always)]
+    fn layout(&self) -> Result<Layout, AllocError> {
+        Layout::from_size_align(self.capacity, 1).map_err(|_| AllocError)
+    }
+    #[inline(always)]
+    fn region(&self) -> Result<NonNull<u8>, AllocError> {
+        match self.region.get() {
+            Some(it) => Ok(it),
+            None => {
+                let it = self.inner.allocate(self.layout()?)?.cast::<u8>();
+                self.region.set(Some(it));
+                Ok(it)
+            }
+        }
+    }
+    #[inline(always)]
+    fn offset_of(&self, ptr: NonNull<u8>) -> usize {
+        match self.region.get() {
+            Some(region) => (ptr.as_ptr() as usize).wrapping_sub(region.as_ptr() as usize),
+            None => usize::MAX,
+        }
+    }
+}
+
+unsafe impl<A> Allocator for Stack<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let region = self.region()?.as_ptr();
+        let top = self.top.get();
+        let padding = region.wrapping_add(top).align_offset(layout.align());
+        let offset = top.checked_add(padding).ok_or(AllocError)?;
+        let end = offset.checked_add(layout.size()).ok_or(AllocError)?;
+        if end > self.capacity {
+            return Err(AllocError);
+        }
+        self.top.set(end);
+        let ptr = unsafe { NonNull::new_unchecked(region.add(offset)) };
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let offset = self.offset_of(ptr);
+        if offset + layout.size() == self.top.get() {
+            self.top.set(offset)
+        }
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let offset = self.offset_of(ptr);
+        if offset + old_layout.size() == self.top.get()
+            && ptr.as_ptr().align_offset(new_layout.align()) == 0
+            && offset + new_layout.size() <= self.capacity
+        {
+            self.top.set(offset + new_layout.size());
+            return Ok(NonNull::slice_from_raw_parts(ptr, new_layout.size()));
+        }
+        let new = self.allocate(new_layout)?;
+        ptr::copy_nonoverlapping(ptr.as_ptr(), new.cast::<u8>().as_ptr(), old_layout.size());
+        Ok(new)
+    }
+}
+
+impl<A> DeallocateAll for Stack<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.top.set(0)
+    }
+}
+
+impl<A> Rewind for Stack<A>
+where
+    A: Allocator,
+{
+    type Checkpoint = Marker;
+    #[inline(always)]
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.marker()
+    }
+    #[inline(always)]
+    unsafe fn rewind(&self, checkpoint: Self::Checkpoint) {
+        self.restore(checkpoint)
+    }
+}
+
+unsafe impl<A> Owns for Stack<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.region.get().is_some()
+            && self
+                .offset_of(ptr)
+                .checked_add(layout.size())
+                .is_some_and(|end| end <= self.capacity)
+    }
+}
+
+impl<A> Drop for Stack<A>
+where
+    A: Allocator,
+{
+    fn drop(&mut self) {
+        if let (Some(region), Ok(layout)) = (self.region.get(), self.layout()) {
+            unsafe { self.inner.deallocate(region, layout) }
+        }
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn stack() {
+    let a = Malloc.stack(8);
+    let marker = a.marker();
+    let first = a.allocate(Layout::new::<[u8; 4]>()).unwrap();
+    a.allocate(Layout::new::<[u8; 4]>()).unwrap();
+    Box::try_new_in(1u8, &a).unwrap_err();
+    unsafe { a.restore(marker) };
+    let second = Box::new_in([1u8; 8], &a);
+    assert_eq!(first.cast::<u8>().as_ptr(), second.as_ptr().cast_mut());
+    drop(second);
+    let _ = Box::new_in(1u8, Malloc.stack(16).guard(0xFF_u8, 0xEE_u8));
+}
@@ -0,0 +1,363 @@
+use crate::prelude::*;
+use core::{
+    panic::Location,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A point-in-time copy of the counters in [`Stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Snapshot {
+    /// Successful calls to [`Allocator::allocate`] or [`Allocator::allocate_zeroed`].
+    pub allocations: usize,
+    /// Calls to [`Allocator::deallocate`].
+    pub deallocations: usize,
+    /// Successful calls to [`Allocator::grow`], [`Allocator::grow_zeroed`] or [`Allocator::shrink`].
+    pub reallocations: usize,
+    /// Failed calls to any allocating method.
+    pub failures: usize,
+    /// Bytes currently allocated.
+    pub live: usize,
+    /// The maximum of [`Self::live`].
+    pub peak: usize,
+    /// Bytes ever allocated, including growth.
+    pub cumulative: usize,
+}
+
+impl Snapshot {
+    /// What has changed between `earlier` and this snapshot.
+    pub fn since(&self, earlier: &Self) -> Delta {
+        Delta {
+            allocations: self.allocations.wrapping_sub(earlier.allocations),
+            deallocations: self.deallocations.wrapping_sub(earlier.deallocations),
+            reallocations: self.reallocations.wrapping_sub(earlier.reallocations),
+            failures: self.failures.wrapping_sub(earlier.failures),
+            live: self.live.wrapping_sub(earlier.live) as isize,
+            cumulative: self.cumulative.wrapping_sub(earlier.cumulative),
+        }
+    }
+}
+
+/// The difference between two [`Snapshot`]s, see [`Stats::diff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Delta {
+    pub allocations: usize,
+    pub deallocations: usize,
+    pub reallocations: usize,
+    pub failures: usize,
+    /// The change in live bytes, which is negative if more were freed than allocated.
+    pub live: isize,
+    pub cumulative: usize,
+}
+
+impl Delta {
+    /// Whether every allocation made in the interval was freed, and nothing else.
+    pub fn is_balanced(&self) -> bool {
+        self.allocations == self.deallocations && self.live == 0
+    }
+}
+
+/// An [`Allocator`] which counts calls and bytes passing through to `A`.
+///
+/// See [`Self::snapshot`].
+///
+/// In tests, [`Self::balanced`] checks that a scope doesn't leak:
+/// ```
+/// # use composable_allocators::*;
+/// let a = Malloc.stats();
+/// let _guard = a.balanced();
+/// let mut v = allocator_api2::vec::Vec::<u8, _>::new_in(&a);
+/// v.extend([1, 2, 3]);
+/// drop(v);
+/// ```
+#[derive(Debug, Default)]
+pub struct Stats<A> {
+    pub inner: A,
+    pub allocations: AtomicUsize,
+    pub deallocations: AtomicUsize,
+    pub reallocations: AtomicUsize,
+    pub failures: AtomicUsize,
+    pub live: AtomicUsize,
+    pub peak: AtomicUsize,
+    pub cumulative: AtomicUsize,
+}
+
+impl<A> Stats<A> {
+    pub const fn new(inner: A) -> Self {
+        Self {
+            inner,
+            allocations: AtomicUsize::new(0),
+            deallocations: AtomicUsize::new(0),
+            reallocations: AtomicUsize::new(0),
+            failures: AtomicUsize::new(0),
+            live: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            cumulative: AtomicUsize::new(0),
+        }
+    }
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            allocations: self.allocations.load(Ordering::Acquire),
+            deallocations: self.deallocations.load(Ordering::Acquire),
+            reallocations: self.reallocations.load(Ordering::Acquire),
+            failures: self.failures.load(Ordering::Acquire),
+            live: self.live.load(Ordering::Acquire),
+            peak: self.peak.load(Ordering::Acquire),
+            cumulative: self.cumulative.load(Ordering::Acquire),
+        }
+    }
+    /// What has changed since `earlier`, which should be from [`Self::snapshot`].
+    pub fn diff(&self, earlier: &Snapshot) -> Delta {
+        self.snapshot().since(earlier)
+    }
+    /// Panic if allocations made since `earlier` haven't all been freed,
+    /// or allocations from before were freed.
+    #[track_caller]
+    pub fn assert_balanced(&self, earlier: &Snapshot) {
+        let delta = self.diff(earlier);
+        assert!(delta.is_balanced(), "unbalanced allocations: {delta:?}")
+    }
+    /// [`Self::assert_balanced`] when the guard is dropped,
+    /// against a snapshot taken now.
+    #[track_caller]
+    pub fn balanced(&self) -> BalanceGuard<'_, A> {
+        BalanceGuard {
+            stats: self,
+            earlier: self.snapshot(),
+            location: Location::caller(),
+        }
+    }
+    #[inline(always)]
+    fn charge(&self, size: usize) {
+        let live = self.live.fetch_add(size, Ordering::AcqRel) + size;
+        self.peak.fetch_max(live, Ordering::AcqRel);
+        self.cumulative.fetch_add(size, Ordering::AcqRel);
+    }
+    #[inline(always)]
+    fn refund(&self, size: usize) {
+        self.live.fetch_sub(size, Ordering::AcqRel);
+    }
+    #[inline(always)]
+    fn allocated(
+        &self,
+        layout: Layout,
+        res: Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        match res {
+            Ok(_) => {
+                self.allocations.fetch_add(1, Ordering::AcqRel);
+                self.charge(layout.size());
+            }
+            Err(_) => {
+                self.failures.fetch_add(1, Ordering::AcqRel);
+            }
+        }
+        res
+    }
+    #[inline(always)]
+    fn reallocated(
+        &self,
+        old_layout: Layout,
+        new_layout: Layout,
+        res: Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        match res {
+            Ok(_) => {
+                self.reallocations.fetch_add(1, Ordering::AcqRel);
+                match new_layout.size() >= old_layout.size() {
+                    true => self.charge(new_layout.size() - old_layout.size()),
+                    false => self.refund(old_layout.size() - new_layout.size()),
+                }
+            }
+            Err(_) => {
+                self.failures.fetch_add(1, Ordering::AcqRel);
+            }
+        }
+        res
+    }
+}
+
+/// Checks that a scope's allocations were freed, see [`Stats::balanced`].
+#[derive(Debug)]
+#[must_use = "the check happens when the guard is dropped"]
+pub struct BalanceGuard<'a, A> {
+    stats: &'a Stats<A>,
+    earlier: Snapshot,
+    location: &'static Location<'static>,
+}
+
+impl<A> BalanceGuard<'_, A> {
+    /// What has changed since the guard was created.
+    pub fn diff(&self) -> Delta {
+        self.stats.diff(&self.earlier)
+    }
+}
+
+impl<A> Drop for BalanceGuard<'_, A> {
+    fn drop(&mut self) {
+        #[cfg(feature = "std")]
+        if std::thread::panicking() {
+            return;
+        }
+        let delta = self.diff();
+        assert!(
+            delta.is_balanced(),
+            "unbalanced allocations in the scope at {}: {delta:?}",
+            self.location
+        )
+    }
+}
+
+unsafe impl<A> Allocator for Stats<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocated(layout, self.inner.allocate(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.deallocations.fetch_add(1, Ordering::AcqRel);
+        self.refund(layout.size());
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocated(layout, self.inner.allocate_zeroed(layout))
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.grow(ptr, old_layout, new_layout);
+        self.reallocated(old_layout, new_layout, res)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.grow_zeroed(ptr, old_layout, new_layout);
+        self.reallocated(old_layout, new_layout, res)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.shrink(ptr, old_layout, new_layout);
+        self.reallocated(old_layout, new_layout, res)
+    }
+}
+
+unsafe impl<A> AllocatesZeroed for Stats<A> where A: AllocatesZeroed {}
+
+impl<A> DeallocateAll for Stats<A>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all();
+        self.live.store(0, Ordering::Release)
+    }
+}
+
+/// Rewinding restores [`Stats::live`] from when the checkpoint was taken,
+/// so older allocations freed in the meantime are counted as live again,
+/// and later [`Delta::live`]s include them.
+impl<A> Rewind for Stats<A>
+where
+    A: Rewind,
+{
+    type Checkpoint = (A::Checkpoint, usize);
+    #[inline(always)]
+    fn checkpoint(&self) -> Self::Checkpoint {
+        (self.inner.checkpoint(), self.live.load(Ordering::Acquire))
+    }
+    #[inline(always)]
+    unsafe fn rewind(&self, checkpoint: Self::Checkpoint) {
+        let (inner, live) = checkpoint;
+        self.inner.rewind(inner);
+        self.live.store(live, Ordering::Release)
+    }
+}
+
+unsafe impl<A> Owns for Stats<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A> Trim for Stats<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn stats() {
+    let a = Malloc.limit_count(2).stats();
+    let first = Box::new_in(1u32, &a);
+    let second = Box::new_in(1u64, &a);
+    Box::try_new_in(1u8, &a).unwrap_err();
+    drop(first);
+    drop(second);
+    assert_eq!(
+        a.snapshot(),
+        Snapshot {
+            allocations: 2,
+            deallocations: 2,
+            reallocations: 0,
+            failures: 1,
+            live: 0,
+            peak: 12,
+            cumulative: 12,
+        }
+    );
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn balanced() {
+    let a = Malloc.stats();
+    let kept = Box::new_in(1u8, &a);
+    let before = a.snapshot();
+    {
+        let _guard = a.balanced();
+        let mut v = allocator_api2::vec::Vec::<u8, _>::new_in(&a);
+        v.extend([1, 2, 3]);
+    }
+    a.assert_balanced(&before);
+    drop(kept);
+    let delta = a.diff(&before);
+    assert_eq!((delta.deallocations, delta.live), (2, -1));
+    assert!(!delta.is_balanced());
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+#[should_panic = "unbalanced allocations"]
+fn unbalanced() {
+    let a = Malloc.stats();
+    let _guard = a.balanced();
+    core::mem::forget(Box::new_in(1u8, &a));
+}
@@ -0,0 +1,236 @@
+use crate::{prelude::*, segregate::relocate};
+use core::mem;
+
+/// An [`Allocator`] which stores each allocation's [`Layout`] in a header immediately before it,
+/// so that it may be freed with only a pointer, like C's `free`.
+///
+/// See [`Self::deallocate_untyped`].
+///
+/// ```text
+/// ┌──────────────────────────────────────┐
+/// │ outer                                │
+/// ├─────────┬────────┬───────────────────┤
+/// │ padding │ Layout │ body              │
+/// ├─────────┴────────┼───────────────────┘
+/// ├─body_offset─────►│
+/// ```
+///
+/// Unlike [`Affix`], whose prefix offset depends on the alignment of the body,
+/// the header is always at a fixed offset from the body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StoreLayout<A> {
+    pub inner: A,
+}
+
+/// Returns `(outer, body_offset)`.
+#[inline(always)]
+fn outer(body: Layout) -> Option<(Layout, usize)> {
+    let (outer, body_offset) = Layout::new::<Layout>().extend(body).ok()?;
+    Some((outer.pad_to_align(), body_offset))
+}
+
+#[inline(always)]
+unsafe fn header(body: NonNull<u8>) -> NonNull<Layout> {
+    body.sub(mem::size_of::<Layout>()).cast()
+}
+
+impl<A> StoreLayout<A> {
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+    /// Get the [`Layout`] that `body` was allocated with.
+    ///
+    /// # Safety
+    /// - `body` must be a live allocation from this [`StoreLayout`].
+    #[inline(always)]
+    pub unsafe fn layout_of(body: NonNull<u8>) -> Layout {
+        header(body).read()
+    }
+}
+
+impl<A> StoreLayout<A>
+where
+    A: Allocator,
+{
+    /// Like [`Allocator::deallocate`], but the [`Layout`] is recovered from the header.
+    ///
+    /// # Safety
+    /// - `body` must be a live allocation from this [`StoreLayout`].
+    #[inline(always)]
+    pub unsafe fn deallocate_untyped(&self, body: NonNull<u8>) {
+        self.deallocate(body, Self::layout_of(body))
+    }
+    #[inline(always)]
+    fn allocate_with(&self, layout: Layout, zeroed: bool) -> Result<NonNull<[u8]>, AllocError> {
+        let (outer, body_offset) = outer(layout).ok_or(AllocError)?;
+        let start = match zeroed {
+            true => self.inner.allocate_zeroed(outer)?,
+            false => self.inner.allocate(outer)?,
+        };
+        unsafe {
+            let body = start.cast::<u8>().add(body_offset);
+            header(body).write(layout);
+            Ok(NonNull::slice_from_raw_parts(body, layout.size()))
+        }
+    }
+    #[inline(always)]
+    unsafe fn resize(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        zeroed: bool,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let (old_outer, old_offset) = outer(old_layout).unwrap_unchecked();
+        let (new_outer, new_offset) = outer(new_layout).ok_or(AllocError)?;
+        if old_offset != new_offset {
+            return relocate(self, self, ptr, old_layout, new_layout, zeroed);
+        }
+        let start = ptr.sub(old_offset);
+        let start = match new_outer.size() >= old_outer.size() {
+            true => self.inner.grow(start, old_outer, new_outer)?,
+            false => self.inner.shrink(start, old_outer, new_outer)?,
+        };
+        let body = start.cast::<u8>().add(new_offset);
+        header(body).write(new_layout);
+        // the old outer allocation may already cover some of the new bytes
+        if zeroed {
+            body.add(old_layout.size())
+                .write_bytes(0, new_layout.size() - old_layout.size())
+        }
+        Ok(NonNull::slice_from_raw_parts(body, new_layout.size()))
+    }
+}
+
+unsafe impl<A> Allocator for StoreLayout<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocate_with(layout, false)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let (outer, body_offset) = outer(layout).unwrap_unchecked();
+        self.inner.deallocate(ptr.sub(body_offset), outer)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocate_with(layout, true)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, false)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, true)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, false)
+    }
+}
+
+impl<A> DeallocateAll for StoreLayout<A>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all()
+    }
+}
+
+/// The header is only read if `A` [owns](Owns::owns) the whole allocation,
+/// and must match `layout`.
+unsafe impl<A> Owns for StoreLayout<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        let Some((outer, body_offset)) = outer(layout) else {
+            return false;
+        };
+        // `ptr` may not be ours, so don't assume it's in bounds
+        let Some(start) = NonNull::new(ptr.as_ptr().wrapping_byte_sub(body_offset)) else {
+            return false;
+        };
+        self.inner.owns(start, outer) && unsafe { Self::layout_of(ptr) } == layout
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn store_layout() {
+    let a = Malloc.store_layout();
+    for layout in [
+        Layout::new::<u8>(),
+        Layout::new::<[u64; 3]>(),
+        Layout::from_size_align(3, 64).unwrap(),
+    ] {
+        let body = a.allocate(layout).unwrap().cast::<u8>();
+        assert_eq!(body.as_ptr() as usize % layout.align(), 0);
+        assert_eq!(unsafe { StoreLayout::<Malloc>::layout_of(body) }, layout);
+        unsafe { a.deallocate_untyped(body) };
+    }
+    let mut v = allocator_api2::vec::Vec::<u32, _>::new_in(&a);
+    v.extend(0..256);
+    v.truncate(3);
+    v.shrink_to_fit();
+    assert_eq!(v, [0, 1, 2]);
+    let body = NonNull::new(v.as_mut_ptr()).unwrap().cast();
+    assert_eq!(
+        unsafe { StoreLayout::<Malloc>::layout_of(body) },
+        Layout::new::<[u32; 3]>()
+    );
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn owns() {
+    let a = Malloc.tracked().store_layout().or(Malloc);
+    let layout = Layout::new::<u64>();
+    let ours = a.primary.allocate(layout).unwrap().cast();
+    let theirs = a.fallback.allocate(layout).unwrap().cast();
+    assert!(a.primary.owns(ours, layout));
+    assert!(!a.primary.owns(ours, Layout::new::<u32>()));
+    assert!(!a.primary.owns(theirs, layout));
+    unsafe {
+        a.deallocate(ours, layout);
+        a.deallocate(theirs, layout);
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn grow_zeroed() {
+    // the header and a byte are padded to 24 bytes, filled with 0xAA
+    let a = Malloc.fill().store_layout();
+    let old = Layout::new::<u8>();
+    let new = Layout::new::<[u8; 8]>();
+    unsafe {
+        let ptr = a.allocate(old).unwrap().cast::<u8>();
+        ptr.write(1);
+        let ptr = a.grow_zeroed(ptr, old, new).unwrap().cast::<[u8; 8]>();
+        assert_eq!(ptr.read(), [1, 0, 0, 0, 0, 0, 0, 0]);
+        a.deallocate(ptr.cast(), new);
+    }
+}
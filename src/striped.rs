@@ -0,0 +1,159 @@
+use crate::prelude::*;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// An [`Allocator`] which spreads allocations round-robin across `N` instances of `A`,
+/// trying the rest in turn if one fails.
+///
+/// Deallocations are routed back using [`Owns`].
+///
+/// Useful to reduce contention on e.g [`Locked`] pools or arenas.
+///
+/// Dropping this drops every stripe, so they release whatever memory they hold.
+#[derive(Debug)]
+pub struct Striped<A, const N: usize> {
+    pub stripes: [A; N],
+    pub next: AtomicUsize,
+}
+
+impl<A, const N: usize> Striped<A, N> {
+    pub const fn new(stripes: [A; N]) -> Self {
+        Self {
+            stripes,
+            next: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl<A, const N: usize> Striped<A, N>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owner(&self, ptr: NonNull<u8>, layout: Layout) -> Option<&A> {
+        self.stripes.iter().find(|it| it.owns(ptr, layout))
+    }
+    /// Try `f` on each stripe, starting from the next in turn.
+    #[inline(always)]
+    fn spread(
+        &self,
+        f: impl Fn(&A) -> Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        (0..N)
+            .map(|it| &self.stripes[start.wrapping_add(it) % N])
+            .find_map(|it| f(it).ok())
+            .ok_or(AllocError)
+    }
+}
+
+unsafe impl<A, const N: usize> Allocator for Striped<A, N>
+where
+    A: Allocator + Owns,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.spread(|it| it.allocate(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if let Some(it) = self.owner(ptr, layout) {
+            it.deallocate(ptr, layout)
+        }
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.spread(|it| it.allocate_zeroed(layout))
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.owner(ptr, old_layout)
+            .ok_or(AllocError)?
+            .grow(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.owner(ptr, old_layout)
+            .ok_or(AllocError)?
+            .grow_zeroed(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.owner(ptr, old_layout)
+            .ok_or(AllocError)?
+            .shrink(ptr, old_layout, new_layout)
+    }
+}
+
+impl<A, const N: usize> DeallocateAll for Striped<A, N>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        for it in &self.stripes {
+            it.deallocate_all()
+        }
+    }
+}
+
+impl<A, const N: usize> Trim for Striped<A, N>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        for it in &self.stripes {
+            it.trim()
+        }
+    }
+}
+
+unsafe impl<A, const N: usize> Owns for Striped<A, N>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.owner(ptr, layout).is_some()
+    }
+}
+
+#[test]
+fn striped() {
+    let mut buffers = [[core::mem::MaybeUninit::uninit(); 8]; 2];
+    let [first, second] = &mut buffers;
+    let a = Striped::new([Region::new(first), Region::new(second)]);
+    let first = Box::new_in(1u32, &a);
+    let second = Box::new_in(2u32, &a);
+    assert!(a.stripes[0].owns(NonNull::from(&*first).cast(), Layout::new::<u32>()));
+    assert!(a.stripes[1].owns(NonNull::from(&*second).cast(), Layout::new::<u32>()));
+    let _third = Box::new_in(3u32, &a);
+    let _fourth = Box::new_in(4u32, &a);
+    Box::try_new_in(5u32, &a).unwrap_err();
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn drop_stripes() {
+    let stats = Malloc.stats();
+    let a = Striped::new([(); 2].map(|()| (&stats).arena(64).locked()));
+    drop([1u32, 2].map(|it| Box::new_in(it, &a)));
+    assert!(stats.snapshot().live >= 128);
+    drop(a);
+    assert_eq!(stats.snapshot().live, 0);
+}
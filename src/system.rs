@@ -0,0 +1,72 @@
+use crate::{
+    global::{dangling, wrap},
+    prelude::*,
+};
+use std::alloc::GlobalAlloc as _;
+
+/// An allocator using the standard library's [`std::alloc::System`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct System;
+
+unsafe impl Allocator for System {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        match layout.size() {
+            0 => Ok(dangling(layout)),
+            size => wrap(unsafe { std::alloc::System.alloc(layout) }, size),
+        }
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            std::alloc::System.dealloc(ptr.as_ptr(), layout)
+        }
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        match layout.size() {
+            0 => Ok(dangling(layout)),
+            size => wrap(unsafe { std::alloc::System.alloc_zeroed(layout) }, size),
+        }
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        match old_layout.size() != 0 && old_layout.align() == new_layout.align() {
+            true => wrap(
+                std::alloc::System.realloc(ptr.as_ptr(), old_layout, new_layout.size()),
+                new_layout.size(),
+            ),
+            false => crate::segregate::relocate(self, self, ptr, old_layout, new_layout, false),
+        }
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        match new_layout.size() != 0 && old_layout.align() == new_layout.align() {
+            true => wrap(
+                std::alloc::System.realloc(ptr.as_ptr(), old_layout, new_layout.size()),
+                new_layout.size(),
+            ),
+            false => crate::segregate::relocate(self, self, ptr, old_layout, new_layout, false),
+        }
+    }
+}
+
+#[test]
+fn system() {
+    let _ = Box::new_in(1, System);
+    let mut v = allocator_api2::vec::Vec::new_in(System);
+    v.extend_from_slice(&[1u8; 1024]);
+    v.truncate(1);
+    v.shrink_to_fit();
+    assert_eq!(v, [1]);
+}
@@ -0,0 +1,294 @@
+use crate::{prelude::*, spin::Spin};
+use core::{cmp, fmt, marker::PhantomData};
+
+/// Statistics for the allocations under one tag, as collected by [`Tagged`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TagStats {
+    /// Allocations ever made under the tag, not counting resizes.
+    pub allocations: usize,
+    /// Bytes currently allocated under the tag.
+    pub live: usize,
+    /// The maximum of [`Self::live`].
+    pub peak: usize,
+}
+
+impl TagStats {
+    const ZERO: Self = Self {
+        allocations: 0,
+        live: 0,
+        peak: 0,
+    };
+    #[inline(always)]
+    fn charge(&mut self, size: usize) {
+        self.live += size;
+        self.peak = cmp::max(self.peak, self.live);
+    }
+}
+
+struct Table<T, const N: usize> {
+    tags: [Option<(T, TagStats)>; N],
+    untracked: TagStats,
+}
+
+impl<T, const N: usize> Table<T, N>
+where
+    T: PartialEq,
+{
+    /// Tags are never removed, so a tag which didn't fit is always untracked.
+    #[inline(always)]
+    fn entry(&mut self, tag: T) -> &mut TagStats {
+        let ix = self.tags.iter().position(|it| match it {
+            Some((it, _)) => *it == tag,
+            None => true,
+        });
+        match ix {
+            Some(ix) => &mut self.tags[ix].get_or_insert((tag, TagStats::ZERO)).1,
+            None => &mut self.untracked,
+        }
+    }
+}
+
+/// Attributes allocations in `A` to user-supplied tags of type `T`,
+/// such as small integers or `&'static str`s,
+/// keeping [`TagStats`] for up to `N` distinct tags.
+///
+/// Allocate under a tag through [`Self::with_tag`].
+/// The tag is stored in an [`Affix`] prefix,
+/// so allocations may be freed or resized through any handle.
+/// ```
+/// # use composable_allocators::*;
+/// let tagged = Malloc.tagged::<&str, 4>();
+/// let parser = tagged.with_tag("parser");
+/// let _ast = allocator_api2::boxed::Box::new_in([0u8; 64], parser);
+/// let _tokens = allocator_api2::vec::Vec::<u8, _>::with_capacity_in(16, tagged.with_tag("lexer"));
+/// assert_eq!(tagged.stats("parser").live, 64);
+/// ```
+pub struct Tagged<A, T, const N: usize> {
+    pub inner: Affix<A, T, ()>,
+    table: Spin<Table<T, N>>,
+}
+
+impl<A, T, const N: usize> Tagged<A, T, N> {
+    pub const fn new(inner: A) -> Self {
+        Self {
+            inner: Affix {
+                inner,
+                prefix: PhantomData,
+                suffix: PhantomData,
+            },
+            table: Spin::new(Table {
+                tags: [const { None }; N],
+                untracked: TagStats::ZERO,
+            }),
+        }
+    }
+    /// An [`Allocator`] which allocates under `tag`.
+    pub const fn with_tag(&self, tag: T) -> WithTag<'_, A, T, N> {
+        WithTag { tagged: self, tag }
+    }
+    /// Statistics for allocations under tags that didn't fit in the table.
+    pub fn untracked(&self) -> TagStats {
+        self.table.lock().untracked
+    }
+    /// The tag of an allocation.
+    ///
+    /// # Safety
+    /// - `body` must be from an allocation by a [`WithTag`] of this [`Tagged`], with `layout`.
+    pub unsafe fn tag_of(body: NonNull<u8>, layout: Layout) -> T
+    where
+        T: Copy,
+    {
+        Affix::<A, T, ()>::read_prefix(body, layout)
+    }
+}
+
+impl<A, T, const N: usize> Tagged<A, T, N>
+where
+    T: Copy + PartialEq,
+{
+    /// Statistics for allocations under `tag`.
+    pub fn stats(&self, tag: T) -> TagStats {
+        let table = self.table.lock();
+        table
+            .tags
+            .iter()
+            .flatten()
+            .find(|(it, _)| *it == tag)
+            .map(|(_, stats)| *stats)
+            .unwrap_or_default()
+    }
+    /// Every tag seen so far, with its statistics, in the order they were first seen.
+    pub fn tags(&self) -> [Option<(T, TagStats)>; N] {
+        self.table.lock().tags
+    }
+    #[inline(always)]
+    fn resized(&self, tag: T, old_layout: Layout, new_layout: Layout) {
+        let mut table = self.table.lock();
+        let stats = table.entry(tag);
+        match new_layout.size() >= old_layout.size() {
+            true => stats.charge(new_layout.size() - old_layout.size()),
+            false => stats.live -= old_layout.size() - new_layout.size(),
+        }
+    }
+}
+
+impl<A, T, const N: usize> fmt::Debug for Tagged<A, T, N>
+where
+    A: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tagged")
+            .field("inner", &self.inner.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An [`Allocator`] which allocates under [`Self::tag`] in a [`Tagged`].
+///
+/// See [`Tagged::with_tag`].
+#[derive(Debug)]
+pub struct WithTag<'a, A, T, const N: usize> {
+    pub tagged: &'a Tagged<A, T, N>,
+    pub tag: T,
+}
+
+impl<A, T: Clone, const N: usize> Clone for WithTag<'_, A, T, N> {
+    fn clone(&self) -> Self {
+        Self {
+            tagged: self.tagged,
+            tag: self.tag.clone(),
+        }
+    }
+}
+
+impl<A, T: Copy, const N: usize> Copy for WithTag<'_, A, T, N> {}
+
+impl<A, T, const N: usize> WithTag<'_, A, T, N>
+where
+    T: Copy + PartialEq,
+{
+    #[inline(always)]
+    #[allow(clippy::type_complexity)]
+    fn allocated(
+        &self,
+        layout: Layout,
+        res: Result<(NonNull<u8>, NonNull<[u8]>, NonNull<u8>), AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let (prefix, body, _) = res?;
+        unsafe { prefix.cast::<T>().write(self.tag) };
+        let mut table = self.tagged.table.lock();
+        let stats = table.entry(self.tag);
+        stats.allocations += 1;
+        stats.charge(layout.size());
+        Ok(body)
+    }
+    #[inline(always)]
+    unsafe fn resize(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        zeroed: bool,
+    ) -> Result<NonNull<[u8]>, AllocError>
+    where
+        A: Allocator,
+    {
+        let tag = Tagged::<A, T, N>::tag_of(ptr, old_layout);
+        let (_, body, _) = self
+            .tagged
+            .inner
+            .affix_resize(ptr, old_layout, new_layout, zeroed)?;
+        self.tagged.resized(tag, old_layout, new_layout);
+        Ok(body)
+    }
+}
+
+unsafe impl<A, T, const N: usize> Allocator for WithTag<'_, A, T, N>
+where
+    A: Allocator,
+    T: Copy + PartialEq,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocated(layout, self.tagged.inner.affix_allocate(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let tag = Tagged::<A, T, N>::tag_of(ptr, layout);
+        self.tagged.table.lock().entry(tag).live -= layout.size();
+        self.tagged.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.allocated(layout, self.tagged.inner.affix_allocate_zeroed(layout))
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, false)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, true)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, false)
+    }
+}
+
+unsafe impl<A, T, const N: usize> Owns for WithTag<'_, A, T, N>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.tagged.inner.owns(ptr, layout)
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn tagged() {
+    let tagged = Malloc.tagged::<u8, 2>();
+    let (zero, one, two) = (tagged.with_tag(0), tagged.with_tag(1), tagged.with_tag(2));
+    let first = Box::new_in(1u32, zero);
+    let mut v = allocator_api2::vec::Vec::<u8, _>::with_capacity_in(4, one);
+    v.extend([0; 12]);
+    let third = Box::new_in(1u64, two);
+    v.shrink_to(8);
+    assert_eq!(
+        tagged.stats(0),
+        TagStats {
+            allocations: 1,
+            live: 4,
+            peak: 4
+        }
+    );
+    assert_eq!(tagged.stats(1).live, v.capacity());
+    assert_eq!(tagged.stats(1).allocations, 1);
+    assert_eq!(tagged.untracked().live, 8);
+    let layout = Layout::new::<u16>();
+    let it = one.allocate(layout).unwrap().cast();
+    assert_eq!(unsafe { Tagged::<Malloc, u8, 2>::tag_of(it, layout) }, 1);
+    unsafe { zero.deallocate(it, layout) };
+    drop((first, v, third));
+    let tags = tagged.tags();
+    assert!(tags.iter().flatten().all(|(_, stats)| stats.live == 0));
+    assert_eq!(tagged.untracked().live, 0);
+}
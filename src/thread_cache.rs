@@ -0,0 +1,222 @@
+use crate::{
+    prelude::*,
+    recycle::{class_of, layout_of, Free, CLASSES},
+};
+use core::{
+    cell::RefCell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use std::{sync::Arc, vec::Vec};
+
+/// Type-erased access to the inner allocator of a [`ThreadCache`],
+/// so that thread-local caches can return their blocks on thread exit.
+trait Release: Send + Sync {
+    unsafe fn release(&self, ptr: NonNull<u8>, layout: Layout);
+}
+
+impl<A> Release for A
+where
+    A: Allocator + Send + Sync,
+{
+    #[inline(always)]
+    unsafe fn release(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.deallocate(ptr, layout)
+    }
+}
+
+/// The cache for one [`ThreadCache`] on one thread.
+struct Local {
+    id: usize,
+    owner: Arc<dyn Release>,
+    heads: [Option<NonNull<Free>>; CLASSES],
+    counts: [usize; CLASSES],
+}
+
+impl Local {
+    fn purge(&mut self) {
+        for (class, (head, count)) in self.heads.iter_mut().zip(&mut self.counts).enumerate() {
+            let mut next = head.take();
+            while let Some(it) = next {
+                next = unsafe { it.as_ptr().read() }.next;
+                unsafe { self.owner.release(it.cast(), layout_of(class)) }
+            }
+            *count = 0;
+        }
+    }
+}
+
+impl Drop for Local {
+    fn drop(&mut self) {
+        self.purge()
+    }
+}
+
+std::thread_local! {
+    static LOCALS: RefCell<Vec<Local>> = const { RefCell::new(Vec::new()) };
+}
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// An [`Allocator`] which caches up to [`capacity`](Self::capacity) freed blocks
+/// per power-of-two size class in thread-local free lists,
+/// only going to the shared `A` on a miss.
+///
+/// Each thread returns its cached blocks to `A` when it exits,
+/// so `A` is kept alive until then, even if the [`ThreadCache`] is dropped.
+///
+/// If the thread-local cache is unavailable (e.g during thread teardown,
+/// or when re-entered from the global allocator), calls go straight to `A`.
+#[derive(Debug)]
+pub struct ThreadCache<A> {
+    inner: Arc<A>,
+    id: usize,
+    capacity: usize,
+}
+
+impl<A> ThreadCache<A>
+where
+    A: Allocator + Send + Sync + 'static,
+{
+    pub fn new(inner: A, capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            capacity,
+        }
+    }
+    /// The maximum number of cached blocks per size class, per thread.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+    /// Return this thread's cached blocks to [`Self::inner`].
+    pub fn purge(&self) {
+        self.with_local(Local::purge);
+    }
+    /// Run `f` on this thread's cache, if it's available.
+    #[inline(always)]
+    fn with_local<R>(&self, f: impl FnOnce(&mut Local) -> R) -> Option<R> {
+        LOCALS
+            .try_with(|locals| {
+                let mut locals = locals.try_borrow_mut().ok()?;
+                let ix = match locals.iter().position(|it| it.id == self.id) {
+                    Some(ix) => ix,
+                    None => {
+                        locals.push(Local {
+                            id: self.id,
+                            owner: self.inner.clone(),
+                            heads: [None; CLASSES],
+                            counts: [0; CLASSES],
+                        });
+                        locals.len() - 1
+                    }
+                };
+                Some(f(&mut locals[ix]))
+            })
+            .ok()
+            .flatten()
+    }
+}
+
+unsafe impl<A> Allocator for ThreadCache<A>
+where
+    A: Allocator + Send + Sync + 'static,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let Some(class) = class_of(layout) else {
+            return self.inner.allocate(layout);
+        };
+        let cached = self.with_local(|local| {
+            let it = local.heads[class]?;
+            local.heads[class] = unsafe { it.as_ptr().read() }.next;
+            local.counts[class] -= 1;
+            Some(it)
+        });
+        match cached.flatten() {
+            Some(it) => Ok(NonNull::slice_from_raw_parts(
+                it.cast(),
+                layout_of(class).size(),
+            )),
+            None => self.inner.allocate(layout_of(class)),
+        }
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let Some(class) = class_of(layout) else {
+            return self.inner.deallocate(ptr, layout);
+        };
+        let cached = self.with_local(|local| {
+            if local.counts[class] == self.capacity {
+                return false;
+            }
+            let free = ptr.cast::<Free>();
+            free.as_ptr().write(Free {
+                next: local.heads[class],
+            });
+            local.heads[class] = Some(free);
+            local.counts[class] += 1;
+            true
+        });
+        if cached != Some(true) {
+            self.inner.deallocate(ptr, layout_of(class))
+        }
+    }
+}
+
+unsafe impl<A> Owns for ThreadCache<A>
+where
+    A: Allocator + Owns + Send + Sync + 'static,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        match class_of(layout) {
+            Some(class) => self.inner.owns(ptr, layout_of(class)),
+            None => self.inner.owns(ptr, layout),
+        }
+    }
+}
+
+/// Only the calling thread's cache is purged,
+/// since other threads' caches can't be reached safely.
+impl<A> Trim for ThreadCache<A>
+where
+    A: Allocator + Trim + Send + Sync + 'static,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.purge();
+        self.inner.trim()
+    }
+}
+
+impl<A> Drop for ThreadCache<A> {
+    fn drop(&mut self) {
+        let _ = LOCALS.try_with(|locals| {
+            if let Ok(mut locals) = locals.try_borrow_mut() {
+                locals.retain(|it| it.id != self.id)
+            }
+        });
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn thread_cache() {
+    let a = Malloc.thread_cache(1);
+    let first = NonNull::from(&*Box::new_in(1u32, &a));
+    let second = Box::new_in(2u64, &a);
+    assert_eq!(first, NonNull::from(&*second).cast());
+    drop(second);
+    let first_addr = first.as_ptr() as usize;
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            let other = Box::new_in(3u64, &a);
+            assert_ne!(first_addr, NonNull::from(&*other).as_ptr() as usize);
+        });
+    });
+    let third = Box::new_in(4u64, &a);
+    assert_eq!(first, NonNull::from(&*third).cast());
+}
@@ -0,0 +1,275 @@
+use crate::prelude::*;
+use core::{cell::Cell, marker::PhantomData, mem, mem::MaybeUninit};
+
+/// Precedes every block in the region.
+#[repr(C)]
+struct Header {
+    /// The physically preceding block.
+    prev: Option<NonNull<Header>>,
+    /// Size of the body, with [`FREE`] in the low bit.
+    size: usize,
+}
+
+/// Written into the body of each free block.
+#[repr(C)]
+struct Links {
+    next: Option<NonNull<Header>>,
+    prev: Option<NonNull<Header>>,
+}
+
+const FREE: usize = 1;
+const ALIGN: usize = mem::align_of::<usize>();
+const HEADER: usize = mem::size_of::<Header>();
+const MIN: usize = mem::size_of::<Links>();
+const SL_LOG2: u32 = 4;
+const SL_COUNT: usize = 1 << SL_LOG2;
+const FL_SHIFT: u32 = SL_LOG2 + ALIGN.trailing_zeros();
+const SMALL: usize = 1 << FL_SHIFT;
+const FL_COUNT: usize = (usize::BITS - FL_SHIFT + 1) as usize;
+
+#[inline(always)]
+unsafe fn size(block: NonNull<Header>) -> usize {
+    (*block.as_ptr()).size & !FREE
+}
+#[inline(always)]
+unsafe fn is_free(block: NonNull<Header>) -> bool {
+    (*block.as_ptr()).size & FREE == FREE
+}
+#[inline(always)]
+unsafe fn body(block: NonNull<Header>) -> NonNull<u8> {
+    NonNull::new_unchecked(block.as_ptr().cast::<u8>().add(HEADER))
+}
+#[inline(always)]
+unsafe fn links(block: NonNull<Header>) -> *mut Links {
+    body(block).as_ptr().cast::<Links>()
+}
+#[inline(always)]
+unsafe fn next_phys(block: NonNull<Header>) -> NonNull<Header> {
+    NonNull::new_unchecked(body(block).as_ptr().add(size(block)).cast::<Header>())
+}
+/// Write a header at `at`, fixing up the next block's back-pointer.
+#[inline(always)]
+unsafe fn split(at: *mut u8, prev: NonNull<Header>, size: usize) -> NonNull<Header> {
+    let block = NonNull::new_unchecked(at.cast::<Header>());
+    block.as_ptr().write(Header {
+        prev: Some(prev),
+        size: size | FREE,
+    });
+    (*next_phys(block).as_ptr()).prev = Some(block);
+    block
+}
+
+/// The free list for blocks of at least `size` bytes.
+#[inline(always)]
+fn mapping(size: usize) -> (usize, usize) {
+    match size < SMALL {
+        true => (0, size / (SMALL / SL_COUNT)),
+        false => {
+            let log2 = usize::BITS - 1 - size.leading_zeros();
+            let sl = (size >> (log2 - SL_LOG2)) ^ SL_COUNT;
+            ((log2 - FL_SHIFT + 1) as usize, sl)
+        }
+    }
+}
+
+/// The first free list where every block is at least `size` bytes.
+#[inline(always)]
+fn mapping_search(size: usize) -> Option<(usize, usize)> {
+    let size = match size < SMALL {
+        true => size,
+        false => {
+            let log2 = usize::BITS - 1 - size.leading_zeros();
+            size.checked_add((1 << (log2 - SL_LOG2)) - 1)?
+        }
+    };
+    Some(mapping(size))
+}
+
+/// A [two-level segregated fit](http://www.gii.upv.es/tlsf/) [`Allocator`]
+/// over a caller-provided region, with `O(1)` allocation and deallocation.
+#[derive(Debug)]
+pub struct Tlsf<'a> {
+    region: NonNull<u8>,
+    len: usize,
+    fl: Cell<usize>,
+    sl: [Cell<u32>; FL_COUNT],
+    heads: [[Cell<Option<NonNull<Header>>>; SL_COUNT]; FL_COUNT],
+    _region: PhantomData<&'a mut [MaybeUninit<u8>]>,
+}
+
+unsafe impl Send for Tlsf<'_> {}
+
+impl<'a> Tlsf<'a> {
+    pub fn new(region: &'a mut [MaybeUninit<u8>]) -> Self {
+        let len = region.len();
+        let region = NonNull::from(region).cast::<u8>();
+        let this = Self {
+            region,
+            len,
+            fl: Cell::new(0),
+            sl: [const { Cell::new(0) }; FL_COUNT],
+            heads: [const { [const { Cell::new(None) }; SL_COUNT] }; FL_COUNT],
+            _region: PhantomData,
+        };
+        let start = region.as_ptr().align_offset(ALIGN);
+        let end = (len.saturating_sub(start) & !(ALIGN - 1)) + start;
+        if end.saturating_sub(start) < HEADER + MIN + HEADER {
+            return this;
+        }
+        unsafe {
+            let first = NonNull::new_unchecked(region.as_ptr().add(start).cast::<Header>());
+            first.as_ptr().write(Header {
+                prev: None,
+                size: (end - start - HEADER - HEADER) | FREE,
+            });
+            next_phys(first).as_ptr().write(Header {
+                prev: Some(first),
+                size: 0,
+            });
+            this.insert(first);
+        }
+        this
+    }
+    #[inline(always)]
+    unsafe fn insert(&self, block: NonNull<Header>) {
+        let (fl, sl) = mapping(size(block));
+        let head = &self.heads[fl][sl];
+        links(block).write(Links {
+            next: head.get(),
+            prev: None,
+        });
+        if let Some(next) = head.get() {
+            (*links(next)).prev = Some(block);
+        }
+        head.set(Some(block));
+        self.sl[fl].set(self.sl[fl].get() | 1 << sl);
+        self.fl.set(self.fl.get() | 1 << fl);
+    }
+    #[inline(always)]
+    unsafe fn remove(&self, block: NonNull<Header>) {
+        let (fl, sl) = mapping(size(block));
+        let Links { next, prev } = links(block).read();
+        if let Some(next) = next {
+            (*links(next)).prev = prev;
+        }
+        match prev {
+            Some(prev) => (*links(prev)).next = next,
+            None => {
+                self.heads[fl][sl].set(next);
+                if next.is_none() {
+                    self.sl[fl].set(self.sl[fl].get() & !(1 << sl));
+                    if self.sl[fl].get() == 0 {
+                        self.fl.set(self.fl.get() & !(1 << fl));
+                    }
+                }
+            }
+        }
+    }
+    /// Find a free block of at least `size` bytes.
+    #[inline(always)]
+    fn find(&self, size: usize) -> Option<NonNull<Header>> {
+        let (fl, sl) = mapping_search(size)?;
+        let (fl, sl_map) = match self.sl.get(fl)?.get() & (!0 << sl) {
+            0 => {
+                let fl_map = self.fl.get() & (!0_usize).checked_shl(fl as u32 + 1).unwrap_or(0);
+                if fl_map == 0 {
+                    return None;
+                }
+                let fl = fl_map.trailing_zeros() as usize;
+                (fl, self.sl[fl].get())
+            }
+            sl_map => (fl, sl_map),
+        };
+        self.heads[fl][sl_map.trailing_zeros() as usize].get()
+    }
+}
+
+unsafe impl Allocator for Tlsf<'_> {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let wanted = layout
+            .size()
+            .max(MIN)
+            .checked_next_multiple_of(ALIGN)
+            .ok_or(AllocError)?;
+        let search = match layout.align() > ALIGN {
+            true => wanted
+                .checked_add(layout.align() + HEADER + MIN)
+                .ok_or(AllocError)?,
+            false => wanted,
+        };
+        let mut block = self.find(search).ok_or(AllocError)?;
+        unsafe {
+            self.remove(block);
+            let start = body(block).as_ptr();
+            let gap = match start.align_offset(layout.align()) {
+                0 => 0,
+                gap if gap < HEADER + MIN => {
+                    gap + (HEADER + MIN - gap).div_ceil(layout.align()) * layout.align()
+                }
+                gap => gap,
+            };
+            if gap != 0 {
+                let aligned = split(start.add(gap - HEADER), block, size(block) - gap);
+                (*block.as_ptr()).size = (gap - HEADER) | FREE;
+                self.insert(block);
+                block = aligned;
+            }
+            if size(block) - wanted >= HEADER + MIN {
+                let rest = split(
+                    body(block).as_ptr().add(wanted),
+                    block,
+                    size(block) - wanted - HEADER,
+                );
+                (*block.as_ptr()).size = wanted | FREE;
+                self.insert(rest);
+            }
+            (*block.as_ptr()).size &= !FREE;
+            Ok(NonNull::slice_from_raw_parts(body(block), size(block)))
+        }
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, _: Layout) {
+        let mut block = NonNull::new_unchecked(ptr.as_ptr().sub(HEADER).cast::<Header>());
+        if let Some(prev) = (*block.as_ptr()).prev.filter(|it| is_free(*it)) {
+            self.remove(prev);
+            (*prev.as_ptr()).size += HEADER + size(block);
+            block = prev;
+            (*next_phys(block).as_ptr()).prev = Some(block);
+        }
+        let next = next_phys(block);
+        if is_free(next) {
+            self.remove(next);
+            (*block.as_ptr()).size = size(block) + HEADER + size(next);
+            (*next_phys(block).as_ptr()).prev = Some(block);
+        }
+        (*block.as_ptr()).size |= FREE;
+        self.insert(block);
+    }
+}
+
+unsafe impl Owns for Tlsf<'_> {
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        let start = self.region.as_ptr() as usize;
+        let ptr = ptr.as_ptr() as usize;
+        start <= ptr && ptr.saturating_add(layout.size()) <= start + self.len
+    }
+}
+
+#[test]
+fn tlsf() {
+    let mut region = [MaybeUninit::uninit(); 1024];
+    let a = Tlsf::new(&mut region);
+    let first = Box::new_in([1u8; 100], &a);
+    let aligned = Box::new_in(Aligned([2u8; 64]), &a);
+    assert_eq!(NonNull::from(&*aligned).as_ptr() as usize % 64, 0);
+    assert_eq!(aligned.0, [2u8; 64]);
+    Box::try_new_in([1u8; 1024], &a).unwrap_err();
+    drop(first);
+    drop(aligned);
+    let _ = Box::new_in([1u8; 900], &a);
+
+    #[repr(align(64))]
+    struct Aligned([u8; 64]);
+}
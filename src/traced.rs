@@ -0,0 +1,143 @@
+use crate::prelude::*;
+
+/// An [`Allocator`] which records a [`tracing`] event for every call to `A`,
+/// with structured `layer`, `size`, `align` and `ptr` fields.
+///
+/// Successes are recorded at [`tracing::Level::TRACE`], and failures at [`tracing::Level::DEBUG`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Traced<A> {
+    pub inner: A,
+    pub name: &'static str,
+}
+
+impl<A> Traced<A> {
+    pub const fn new(inner: A, name: &'static str) -> Self {
+        Self { inner, name }
+    }
+    #[inline(always)]
+    fn trace(
+        &self,
+        method: &'static str,
+        old_layout: Option<Layout>,
+        layout: Layout,
+        res: Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let old_size = old_layout.map(|it| it.size());
+        match res {
+            Ok(ptr) => tracing::trace!(
+                layer = self.name,
+                method,
+                old_size,
+                size = layout.size(),
+                align = layout.align(),
+                ptr = ?ptr.cast::<u8>(),
+                "allocated"
+            ),
+            Err(AllocError) => tracing::debug!(
+                layer = self.name,
+                method,
+                old_size,
+                size = layout.size(),
+                align = layout.align(),
+                "allocation failed"
+            ),
+        }
+        res
+    }
+}
+
+unsafe impl<A> Allocator for Traced<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.trace("allocate", None, layout, self.inner.allocate(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        tracing::trace!(
+            layer = self.name,
+            method = "deallocate",
+            size = layout.size(),
+            align = layout.align(),
+            ptr = ?ptr,
+            "deallocated"
+        );
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.allocate_zeroed(layout);
+        self.trace("allocate_zeroed", None, layout, res)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.grow(ptr, old_layout, new_layout);
+        self.trace("grow", Some(old_layout), new_layout, res)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.grow_zeroed(ptr, old_layout, new_layout);
+        self.trace("grow_zeroed", Some(old_layout), new_layout, res)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.shrink(ptr, old_layout, new_layout);
+        self.trace("shrink", Some(old_layout), new_layout, res)
+    }
+}
+
+unsafe impl<A> AllocatesZeroed for Traced<A> where A: AllocatesZeroed {}
+
+unsafe impl<A> Owns for Traced<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A> UsableSize for Traced<A>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A> Trim for Traced<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn traced() {
+    let _ = Box::new_in(1, Malloc.traced("malloc"));
+    Box::try_new_in(1, Null.traced("null")).unwrap_err();
+}
@@ -0,0 +1,207 @@
+use crate::{prelude::*, segregate::relocate, spin::Spin};
+use core::{mem, ptr};
+
+/// Written after the body of each allocation.
+struct Node {
+    prev: Option<NonNull<Node>>,
+    next: Option<NonNull<Node>>,
+    start: NonNull<u8>,
+    size: usize,
+}
+
+/// The offset of the [`Node`] from the start of the body.
+#[inline(always)]
+fn node_offset(layout: Layout) -> Option<usize> {
+    layout
+        .size()
+        .checked_next_multiple_of(mem::align_of::<Node>())
+}
+
+/// The layout actually requested from the inner allocator.
+#[inline(always)]
+fn outer(layout: Layout) -> Option<(Layout, usize)> {
+    let offset = node_offset(layout)?;
+    let size = offset.checked_add(mem::size_of::<Node>())?;
+    let align = layout.align().max(mem::align_of::<Node>());
+    Some((Layout::from_size_align(size, align).ok()?, offset))
+}
+
+/// An [`Allocator`] which implements [`Owns`] for any `A`,
+/// by keeping a list of live allocations.
+///
+/// Each allocation carries a small trailing header,
+/// and [`Owns::owns`] walks every live allocation.
+#[derive(Debug)]
+pub struct Tracked<A> {
+    inner: A,
+    live: Spin<Option<NonNull<Node>>>,
+}
+
+unsafe impl<A: Send> Send for Tracked<A> {}
+unsafe impl<A: Sync> Sync for Tracked<A> {}
+
+impl<A> Tracked<A> {
+    pub const fn new(inner: A) -> Self {
+        Self {
+            inner,
+            live: Spin::new(None),
+        }
+    }
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+    /// Find the [`Node`] for an allocation.
+    #[inline(always)]
+    unsafe fn node(ptr: NonNull<u8>, layout: Layout) -> NonNull<Node> {
+        let offset = node_offset(layout).unwrap_unchecked();
+        NonNull::new_unchecked(ptr.as_ptr().add(offset).cast())
+    }
+    /// Start tracking a fresh allocation.
+    #[inline(always)]
+    unsafe fn track(
+        &self,
+        res: Result<NonNull<[u8]>, AllocError>,
+        layout: Layout,
+        offset: usize,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let start = res?.cast::<u8>();
+        let node = Self::node(start, layout);
+        let mut live = self.live.lock();
+        node.as_ptr().write(Node {
+            prev: None,
+            next: *live,
+            start,
+            size: offset,
+        });
+        if let Some(next) = *live {
+            (*next.as_ptr()).prev = Some(node);
+        }
+        *live = Some(node);
+        Ok(NonNull::slice_from_raw_parts(start, offset))
+    }
+    /// Stop tracking an allocation.
+    #[inline(always)]
+    unsafe fn untrack(&self, ptr: NonNull<u8>, layout: Layout) {
+        let Node { prev, next, .. } = ptr::read(Self::node(ptr, layout).as_ptr());
+        let mut live = self.live.lock();
+        match prev {
+            Some(prev) => (*prev.as_ptr()).next = next,
+            None => *live = next,
+        }
+        if let Some(next) = next {
+            (*next.as_ptr()).prev = prev;
+        }
+    }
+}
+
+unsafe impl<A> Allocator for Tracked<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let (outer, offset) = outer(layout).ok_or(AllocError)?;
+        unsafe { self.track(self.inner.allocate(outer), layout, offset) }
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.untrack(ptr, layout);
+        let (outer, _) = outer(layout).unwrap_unchecked();
+        self.inner.deallocate(ptr, outer)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let (outer, offset) = outer(layout).ok_or(AllocError)?;
+        unsafe { self.track(self.inner.allocate_zeroed(outer), layout, offset) }
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        relocate(self, self, ptr, old_layout, new_layout, false)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        relocate(self, self, ptr, old_layout, new_layout, true)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        relocate(self, self, ptr, old_layout, new_layout, false)
+    }
+}
+
+impl<A> DeallocateAll for Tracked<A>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        let mut live = self.live.lock();
+        self.inner.deallocate_all();
+        *live = None
+    }
+}
+
+impl<A> Trim for Tracked<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+unsafe impl<A> Owns for Tracked<A> {
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        let start = ptr.as_ptr() as usize;
+        let Some(end) = start.checked_add(layout.size()) else {
+            return false;
+        };
+        let live = self.live.lock();
+        let mut node = *live;
+        while let Some(it) = node {
+            let Node {
+                next,
+                start: base,
+                size,
+                ..
+            } = unsafe { ptr::read(it.as_ptr()) };
+            let base = base.as_ptr() as usize;
+            if base <= start && end <= base + size {
+                return true;
+            }
+            node = next;
+        }
+        false
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn tracked() {
+    let a = Malloc.tracked().or(Malloc);
+    let it = Box::new_in(1u64, &a);
+    assert!(a
+        .primary
+        .owns(NonNull::from(&*it).cast(), Layout::new::<u64>()));
+    let mut v = allocator_api2::vec::Vec::<u8, _>::with_capacity_in(1, &a);
+    v.extend_from_slice(&[1u8; 64]);
+    let ptr = NonNull::from(&*it).cast();
+    drop(it);
+    assert!(!a.primary.owns(ptr, Layout::new::<u64>()));
+}
@@ -0,0 +1,138 @@
+use crate::prelude::*;
+
+/// An [`Allocator`] which, when `A` fails, [trims](Trim) it and tries once more
+/// before giving up.
+///
+/// Put this at the top of a stack with caches (e.g [`Recycle`]),
+/// so that memory they hold is released under pressure:
+/// ```
+/// # use composable_allocators::*;
+/// let a = Malloc.limit_size(64).recycle(4).trim_on_fail();
+/// drop(allocator_api2::boxed::Box::new_in([0u8; 64], &a));
+/// // the freed block is cached, so only trimming makes room
+/// let _it = allocator_api2::boxed::Box::new_in([0u8; 32], &a);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct TrimOnFail<A> {
+    pub inner: A,
+}
+
+impl<A> TrimOnFail<A> {
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+impl<A> TrimOnFail<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn retry<T>(&self, f: impl Fn() -> Result<T, AllocError>) -> Result<T, AllocError> {
+        f().or_else(|_| {
+            self.inner.trim();
+            f()
+        })
+    }
+}
+
+unsafe impl<A> Allocator for TrimOnFail<A>
+where
+    A: Allocator + Trim,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.retry(|| self.inner.allocate(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.retry(|| self.inner.allocate_zeroed(layout))
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        // a failed resize leaves the old block in place
+        self.retry(|| self.inner.grow(ptr, old_layout, new_layout))
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.retry(|| self.inner.grow_zeroed(ptr, old_layout, new_layout))
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.retry(|| self.inner.shrink(ptr, old_layout, new_layout))
+    }
+}
+
+unsafe impl<A> AllocatesZeroed for TrimOnFail<A> where A: AllocatesZeroed + Trim {}
+
+impl<A> DeallocateAll for TrimOnFail<A>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all()
+    }
+}
+
+unsafe impl<A> Owns for TrimOnFail<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A> UsableSize for TrimOnFail<A>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A> Trim for TrimOnFail<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn trim_on_fail() {
+    let a = Malloc.limit_size(96).recycle(4).trim_on_fail();
+    let mut v = allocator_api2::vec::Vec::<u8, _>::with_capacity_in(32, &a);
+    drop(Box::new_in([0u8; 32], &a));
+    // the cached block must be released to make room for the copy
+    v.reserve_exact(64);
+    assert_eq!(v.capacity(), 64);
+    drop(v);
+    Box::try_new_in([0u8; 128], &a).unwrap_err();
+}
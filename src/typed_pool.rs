@@ -0,0 +1,177 @@
+use crate::prelude::*;
+use core::{fmt, marker::PhantomData, mem::MaybeUninit};
+
+/// A key for a value in a [`TypedPool`].
+///
+/// Handles to removed values are never valid again,
+/// even if their slot is reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Handle {
+    index: usize,
+    generation: usize,
+}
+
+impl Handle {
+    /// The slot in the pool, which is less than its [capacity](TypedPool::capacity).
+    ///
+    /// This may be shared with handles to removed values.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+/// A block in the [`Pool`].
+#[repr(C)]
+struct Slot<T> {
+    /// Overwritten by the [`Pool`]'s free list.
+    _link: MaybeUninit<Option<NonNull<u8>>>,
+    /// Odd while occupied.
+    generation: usize,
+    value: MaybeUninit<T>,
+}
+
+/// A collection of up to [`capacity`](Self::capacity) values of type `T`,
+/// which are stored in a [`Pool`] over `A` and found again with a [`Handle`].
+///
+/// Values never move, and their storage is requested from `A` on first use,
+/// so limits and statistics on `A` apply:
+/// ```
+/// # use composable_allocators::*;
+/// let mut entities = Malloc.stats().typed_pool::<(f32, f32)>(1024);
+/// let player = entities.insert((0.0, 0.0)).unwrap();
+/// let enemy = entities.insert((8.0, 2.0)).unwrap();
+/// entities.get_mut(player).unwrap().0 += 1.0;
+/// assert_eq!(entities.remove(enemy), Some((8.0, 2.0)));
+/// assert_eq!(entities.get(enemy), None);
+/// assert_eq!(entities.get(player), Some(&(1.0, 0.0)));
+/// assert_eq!(entities.inner().snapshot().allocations, 1);
+/// ```
+pub struct TypedPool<T, A: Allocator> {
+    pool: Pool<A>,
+    len: usize,
+    _values: PhantomData<T>,
+}
+
+impl<T, A: Allocator> TypedPool<T, A> {
+    pub const fn new(inner: A, capacity: usize) -> Self {
+        Self {
+            pool: Pool::new(inner, Layout::new::<Slot<T>>(), capacity),
+            len: 0,
+            _values: PhantomData,
+        }
+    }
+    /// The maximum number of values.
+    pub fn capacity(&self) -> usize {
+        self.pool.capacity()
+    }
+    /// The number of values.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn inner(&self) -> &A {
+        self.pool.inner()
+    }
+    /// Store `value`, returning it if the pool is full or `A` fails.
+    pub fn insert(&mut self, value: T) -> Result<Handle, T> {
+        let fresh = self.pool.fresh();
+        let Ok(block) = self.pool.allocate(Layout::new::<Slot<T>>()) else {
+            return Err(value);
+        };
+        let block = block.cast::<u8>();
+        let index = self.pool.index_of(block);
+        let slot = unsafe { &mut *block.cast::<Slot<T>>().as_ptr() };
+        slot.generation = match index < fresh {
+            true => slot.generation + 1,
+            false => 1,
+        };
+        slot.value.write(value);
+        self.len += 1;
+        Ok(Handle {
+            index,
+            generation: slot.generation,
+        })
+    }
+    #[inline(always)]
+    fn slot(&self, handle: Handle) -> Option<NonNull<Slot<T>>> {
+        let slot = self.pool.block_at(handle.index)?.cast::<Slot<T>>();
+        match unsafe { (*slot.as_ptr()).generation } == handle.generation {
+            true => Some(slot),
+            false => None,
+        }
+    }
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        let slot = self.slot(handle)?;
+        Some(unsafe { (*slot.as_ptr()).value.assume_init_ref() })
+    }
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        let slot = self.slot(handle)?;
+        Some(unsafe { (*slot.as_ptr()).value.assume_init_mut() })
+    }
+    /// Remove a value, freeing its slot for reuse.
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let slot = self.slot(handle)?;
+        let value = unsafe {
+            (*slot.as_ptr()).generation += 1;
+            (*slot.as_ptr()).value.assume_init_read()
+        };
+        unsafe { self.pool.deallocate(slot.cast(), Layout::new::<Slot<T>>()) };
+        self.len -= 1;
+        Some(value)
+    }
+}
+
+impl<T, A: Allocator> Drop for TypedPool<T, A> {
+    fn drop(&mut self) {
+        for index in 0..self.pool.fresh() {
+            let slot = self.pool.block_at(index).unwrap().cast::<Slot<T>>();
+            unsafe {
+                if (*slot.as_ptr()).generation % 2 == 1 {
+                    (*slot.as_ptr()).value.assume_init_drop()
+                }
+            }
+        }
+    }
+}
+
+impl<T, A> fmt::Debug for TypedPool<T, A>
+where
+    A: Allocator + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TypedPool")
+            .field("pool", &self.pool)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn typed_pool() {
+    use core::cell::Cell;
+    struct Counted<'a>(&'a Cell<usize>);
+    impl Drop for Counted<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1)
+        }
+    }
+    let drops = Cell::new(0);
+    let mut pool = Malloc.limit_count(1).typed_pool::<Counted>(2);
+    let first = pool.insert(Counted(&drops)).ok().unwrap();
+    let second = pool.insert(Counted(&drops)).ok().unwrap();
+    assert!(pool.insert(Counted(&drops)).is_err());
+    assert_eq!(drops.get(), 1);
+    drop(pool.remove(first));
+    assert_eq!(drops.get(), 2);
+    assert!(pool.get(first).is_none());
+    let third = pool.insert(Counted(&drops)).ok().unwrap();
+    assert_eq!(third.index(), first.index());
+    assert!(pool.remove(first).is_none());
+    assert!(pool.get(second).is_some());
+    assert_eq!(pool.len(), 2);
+    drop(pool);
+    assert_eq!(drops.get(), 4);
+}
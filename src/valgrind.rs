@@ -0,0 +1,230 @@
+use crate::prelude::*;
+use core::cmp;
+
+const RUNNING_ON_VALGRIND: usize = 0x1001;
+const MALLOCLIKE_BLOCK: usize = 0x1301;
+const FREELIKE_BLOCK: usize = 0x1302;
+const RESIZEINPLACE_BLOCK: usize = 0x130b;
+/// `VG_USERREQ_TOOL_BASE('M', 'C')`
+const MAKE_MEM_NOACCESS: usize = 0x4d43_0000;
+const MAKE_MEM_DEFINED: usize = MAKE_MEM_NOACCESS + 2;
+
+/// Issue a client request, returning `default` if not running under Valgrind.
+///
+/// See `valgrind.h`.
+#[inline(always)]
+fn client_request(default: usize, request: usize, args: [usize; 5]) -> usize {
+    let args = [request, args[0], args[1], args[2], args[3], args[4]];
+    #[allow(unused_mut)]
+    let mut result = default;
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        core::arch::asm!(
+            "rol rdi, 3",
+            "rol rdi, 13",
+            "rol rdi, 61",
+            "rol rdi, 51",
+            "xchg rbx, rbx",
+            in("rax") args.as_ptr(),
+            inout("rdx") result,
+        )
+    };
+    #[cfg(target_arch = "aarch64")]
+    unsafe {
+        core::arch::asm!(
+            "ror x12, x12, #3",
+            "ror x12, x12, #13",
+            "ror x12, x12, #51",
+            "ror x12, x12, #61",
+            "orr x10, x10, x10",
+            in("x4") args.as_ptr(),
+            inout("x3") result,
+        )
+    };
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    let _ = args;
+    result
+}
+
+/// An [`Allocator`] which describes each allocation to Valgrind's memcheck,
+/// so that allocators carving blocks out of a larger region, like [`Arena`],
+/// have their allocations checked individually.
+///
+/// Don't use this over allocators which Valgrind already understands, like [`Malloc`](crate::Malloc).
+///
+/// [`DeallocateAll`] isn't implemented, since memcheck would still consider outstanding blocks live.
+///
+/// Outside of Valgrind, each request costs a handful of instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Valgrind<A> {
+    pub inner: A,
+}
+
+impl<A> Valgrind<A> {
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+    /// Whether the program is running under Valgrind.
+    pub fn running() -> bool {
+        client_request(0, RUNNING_ON_VALGRIND, [0; 5]) != 0
+    }
+    /// Mark `len` bytes at `ptr` as inaccessible,
+    /// e.g the region an allocator hands out blocks from.
+    pub fn make_mem_noaccess(ptr: NonNull<u8>, len: usize) {
+        client_request(0, MAKE_MEM_NOACCESS, [ptr.as_ptr() as usize, len, 0, 0, 0]);
+    }
+    #[inline(always)]
+    fn make_mem_defined(ptr: NonNull<u8>, len: usize) {
+        client_request(0, MAKE_MEM_DEFINED, [ptr.as_ptr() as usize, len, 0, 0, 0]);
+    }
+    #[inline(always)]
+    fn malloclike(block: NonNull<[u8]>, layout: Layout, zeroed: bool) {
+        let addr = block.cast::<u8>().as_ptr() as usize;
+        client_request(
+            0,
+            MALLOCLIKE_BLOCK,
+            [addr, layout.size(), 0, zeroed as usize, 0],
+        );
+    }
+    #[inline(always)]
+    fn freelike(ptr: NonNull<u8>) {
+        client_request(0, FREELIKE_BLOCK, [ptr.as_ptr() as usize, 0, 0, 0, 0]);
+    }
+    #[inline(always)]
+    fn allocated(
+        layout: Layout,
+        zeroed: bool,
+        res: Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if let Ok(block) = res {
+            Self::malloclike(block, layout, zeroed)
+        }
+        res
+    }
+    #[inline(always)]
+    fn reallocated(
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        zeroed: bool,
+        res: Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let Ok(block) = res else { return res };
+        let block = block.cast::<u8>();
+        match block == ptr {
+            true => {
+                let args = [
+                    ptr.as_ptr() as usize,
+                    old_layout.size(),
+                    new_layout.size(),
+                    0,
+                    0,
+                ];
+                client_request(0, RESIZEINPLACE_BLOCK, args);
+            }
+            false => {
+                Self::freelike(ptr);
+                Self::malloclike(NonNull::slice_from_raw_parts(block, 0), new_layout, false);
+                // `A` copied the contents over
+                Self::make_mem_defined(block, cmp::min(old_layout.size(), new_layout.size()))
+            }
+        }
+        if zeroed && new_layout.size() > old_layout.size() {
+            let tail = unsafe { block.add(old_layout.size()) };
+            Self::make_mem_defined(tail, new_layout.size() - old_layout.size())
+        }
+        res
+    }
+}
+
+unsafe impl<A> Allocator for Valgrind<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        Self::allocated(layout, false, self.inner.allocate(layout))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        Self::freelike(ptr);
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        Self::allocated(layout, true, self.inner.allocate_zeroed(layout))
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.grow(ptr, old_layout, new_layout);
+        Self::reallocated(ptr, old_layout, new_layout, false, res)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.grow_zeroed(ptr, old_layout, new_layout);
+        Self::reallocated(ptr, old_layout, new_layout, true, res)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let res = self.inner.shrink(ptr, old_layout, new_layout);
+        Self::reallocated(ptr, old_layout, new_layout, false, res)
+    }
+}
+
+unsafe impl<A> AllocatesZeroed for Valgrind<A> where A: AllocatesZeroed {}
+
+unsafe impl<A> Owns for Valgrind<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A> UsableSize for Valgrind<A>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A> Trim for Valgrind<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn valgrind() {
+    assert!(!Valgrind::<Malloc>::running());
+    let a = Malloc.arena(256).valgrind();
+    let mut v = allocator_api2::vec::Vec::<u8, _>::new_in(&a);
+    v.extend(0..=64);
+    drop(v);
+    let _ = Box::new_in(1u32, &a);
+}
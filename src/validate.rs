@@ -0,0 +1,249 @@
+use crate::prelude::*;
+use core::fmt;
+
+/// Constraints on the [`Layout`]s accepted by [`ValidateLayout`].
+///
+/// The [`Default`] accepts every layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LayoutRules {
+    /// The largest permitted [`Layout::align`].
+    pub max_align: usize,
+    /// The largest permitted [`Layout::size`].
+    pub max_size: usize,
+    /// Reject zero-sized layouts.
+    pub non_zero_size: bool,
+    /// Reject layouts whose size isn't a multiple of their alignment,
+    /// i.e which haven't been [padded](Layout::pad_to_align).
+    pub padded: bool,
+}
+
+impl Default for LayoutRules {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LayoutRules {
+    /// Accept every layout.
+    pub const fn new() -> Self {
+        Self {
+            max_align: usize::MAX,
+            max_size: usize::MAX,
+            non_zero_size: false,
+            padded: false,
+        }
+    }
+    /// The first rule that `layout` breaks, if any.
+    pub const fn check(&self, layout: Layout) -> Result<(), Violation> {
+        if layout.align() > self.max_align {
+            return Err(Violation::Align);
+        }
+        if layout.size() > self.max_size {
+            return Err(Violation::Size);
+        }
+        if self.non_zero_size && layout.size() == 0 {
+            return Err(Violation::ZeroSize);
+        }
+        if self.padded && !layout.size().is_multiple_of(layout.align()) {
+            return Err(Violation::Unpadded);
+        }
+        Ok(())
+    }
+}
+
+/// A rule in [`LayoutRules`] which a [`Layout`] broke.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Violation {
+    /// See [`LayoutRules::max_align`].
+    Align,
+    /// See [`LayoutRules::max_size`].
+    Size,
+    /// See [`LayoutRules::non_zero_size`].
+    ZeroSize,
+    /// See [`LayoutRules::padded`].
+    Unpadded,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Violation::Align => "alignment is too large",
+            Violation::Size => "size is too large",
+            Violation::ZeroSize => "size is zero",
+            Violation::Unpadded => "size is not a multiple of alignment",
+        })
+    }
+}
+
+/// An [`Allocator`] which checks every [`Layout`] against [`Self::rules`] before passing it to `A`.
+///
+/// In debug builds a violation [`panic`]s, so that container bugs are caught early.
+/// In release builds allocating methods fail with [`AllocError`] instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ValidateLayout<A> {
+    pub inner: A,
+    pub rules: LayoutRules,
+}
+
+impl<A> ValidateLayout<A> {
+    pub const fn new(inner: A, rules: LayoutRules) -> Self {
+        Self { inner, rules }
+    }
+    #[inline(always)]
+    #[track_caller]
+    fn check(&self, layout: Layout) -> Result<(), AllocError> {
+        match self.rules.check(layout) {
+            Ok(()) => Ok(()),
+            Err(violation) if cfg!(debug_assertions) => {
+                panic!("invalid layout {layout:?}: {violation}")
+            }
+            Err(_) => Err(AllocError),
+        }
+    }
+}
+
+unsafe impl<A> Allocator for ValidateLayout<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    #[track_caller]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.check(layout)?;
+        self.inner.allocate(layout)
+    }
+    /// Only checked in debug builds, since deallocation can't fail.
+    #[inline(always)]
+    #[track_caller]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if cfg!(debug_assertions) {
+            let _ = self.check(layout);
+        }
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    #[track_caller]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.check(layout)?;
+        self.inner.allocate_zeroed(layout)
+    }
+    #[inline(always)]
+    #[track_caller]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.check(new_layout)?;
+        self.inner.grow(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    #[track_caller]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.check(new_layout)?;
+        self.inner.grow_zeroed(ptr, old_layout, new_layout)
+    }
+    #[inline(always)]
+    #[track_caller]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.check(new_layout)?;
+        self.inner.shrink(ptr, old_layout, new_layout)
+    }
+}
+
+unsafe impl<A> AllocatesZeroed for ValidateLayout<A> where A: AllocatesZeroed {}
+
+impl<A> DeallocateAll for ValidateLayout<A>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all()
+    }
+}
+
+impl<A> Rewind for ValidateLayout<A>
+where
+    A: Rewind,
+{
+    type Checkpoint = A::Checkpoint;
+    #[inline(always)]
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.inner.checkpoint()
+    }
+    #[inline(always)]
+    unsafe fn rewind(&self, checkpoint: Self::Checkpoint) {
+        self.inner.rewind(checkpoint)
+    }
+}
+
+unsafe impl<A> Owns for ValidateLayout<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A> UsableSize for ValidateLayout<A>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A> Trim for ValidateLayout<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[test]
+fn rules() {
+    let rules = LayoutRules {
+        max_align: 8,
+        non_zero_size: true,
+        padded: true,
+        ..LayoutRules::new()
+    };
+    let layout = |size, align| Layout::from_size_align(size, align).unwrap();
+    assert_eq!(rules.check(layout(8, 8)), Ok(()));
+    assert_eq!(rules.check(layout(16, 16)), Err(Violation::Align));
+    assert_eq!(rules.check(layout(0, 1)), Err(Violation::ZeroSize));
+    assert_eq!(rules.check(layout(3, 2)), Err(Violation::Unpadded));
+    assert_eq!(LayoutRules::default().check(layout(0, 4096)), Ok(()));
+}
+
+#[cfg(all(feature = "malloc", debug_assertions))]
+#[test]
+#[should_panic = "invalid layout"]
+fn validate_layout() {
+    let a = Malloc.validate_layout(LayoutRules {
+        non_zero_size: true,
+        ..LayoutRules::new()
+    });
+    let _ = Box::new_in(1u8, &a);
+    let _ = a.allocate(Layout::new::<()>());
+}
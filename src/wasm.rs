@@ -0,0 +1,71 @@
+use crate::{prelude::*, spin::Spin};
+use core::arch::wasm32;
+
+const PAGE_SIZE: usize = 64 * 1024;
+
+/// An allocator which bump-allocates out of linear memory,
+/// growing it with [`memory.grow`](wasm32::memory_grow) as required.
+///
+/// Deallocating the most recent allocation reuses its space,
+/// other deallocations leak.
+/// Use this as the upstream for [`Arena`], [`Pool`] etc.
+#[derive(Debug, Default)]
+pub struct WasmPages {
+    /// `(cursor, end)`
+    state: Spin<(usize, usize)>,
+}
+
+impl WasmPages {
+    pub const fn new() -> Self {
+        Self {
+            state: Spin::new((0, 0)),
+        }
+    }
+}
+
+unsafe impl Allocator for WasmPages {
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let mut state = self.state.lock();
+        let (cursor, end) = *state;
+        let mut start = cursor
+            .checked_next_multiple_of(layout.align())
+            .ok_or(AllocError)?;
+        if start.checked_add(layout.size()).ok_or(AllocError)? > end || start == 0 {
+            let wanted = layout
+                .size()
+                .checked_add(layout.align())
+                .ok_or(AllocError)?;
+            let pages = wanted.div_ceil(PAGE_SIZE);
+            let previous = wasm32::memory_grow(0, pages);
+            if previous == usize::MAX {
+                return Err(AllocError);
+            }
+            let base = previous * PAGE_SIZE;
+            let cursor = match base == end {
+                // contiguous with our last region
+                true => cursor,
+                false => base,
+            };
+            *state = (cursor, base + pages * PAGE_SIZE);
+            start = cursor.next_multiple_of(layout.align());
+        }
+        state.0 = start + layout.size();
+        let ptr = unsafe { NonNull::new_unchecked(start as *mut u8) };
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let mut state = self.state.lock();
+        if ptr.as_ptr() as usize + layout.size() == state.0 {
+            state.0 = ptr.as_ptr() as usize
+        }
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.allocate(layout)?;
+        // memory may be reused after deallocate
+        unsafe { ptr.cast::<u8>().as_ptr().write_bytes(0, ptr.len()) };
+        Ok(ptr)
+    }
+}
@@ -0,0 +1,148 @@
+use crate::prelude::*;
+use core::{
+    ptr,
+    sync::atomic::{compiler_fence, Ordering},
+};
+
+/// Zero `len` bytes at `ptr` in a way the optimizer can't elide,
+/// even though the memory is about to be freed.
+#[inline(always)]
+unsafe fn wipe(ptr: *mut u8, len: usize) {
+    for i in 0..len {
+        ptr::write_volatile(ptr.add(i), 0)
+    }
+    compiler_fence(Ordering::SeqCst)
+}
+
+/// An [`Allocator`] which zeroes memory before it is returned to `A`,
+/// so secrets like key material don't linger in freed memory.
+///
+/// Resizing always moves the allocation, since `A` could otherwise free the old block
+/// without it being wiped.
+///
+/// See [`Zero`] to also zero memory on allocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct WipeOnFree<A> {
+    pub inner: A,
+}
+
+impl<A> WipeOnFree<A> {
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+unsafe impl<A> Allocator for WipeOnFree<A>
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.allocate(layout)
+    }
+    #[inline(always)]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        wipe(ptr.as_ptr(), layout.size());
+        self.inner.deallocate(ptr, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.inner.allocate_zeroed(layout)
+    }
+    #[inline(always)]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        crate::segregate::relocate(self, &self.inner, ptr, old_layout, new_layout, false)
+    }
+    #[inline(always)]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        crate::segregate::relocate(self, &self.inner, ptr, old_layout, new_layout, true)
+    }
+    #[inline(always)]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        crate::segregate::relocate(self, &self.inner, ptr, old_layout, new_layout, false)
+    }
+}
+
+unsafe impl<A> AllocatesZeroed for WipeOnFree<A> where A: AllocatesZeroed {}
+
+impl<A> DeallocateAll for WipeOnFree<A>
+where
+    A: DeallocateAll,
+{
+    /// Outstanding allocations are not wiped, since their layouts aren't known.
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all()
+    }
+}
+
+unsafe impl<A> Owns for WipeOnFree<A>
+where
+    A: Owns,
+{
+    #[inline(always)]
+    fn owns(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        self.inner.owns(ptr, layout)
+    }
+}
+
+impl<A> UsableSize for WipeOnFree<A>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A> Trim for WipeOnFree<A>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[test]
+fn wipe_on_free() {
+    let inline = Inline::<64>::new();
+    // read the block just before it's freed
+    let wiped = core::cell::Cell::new(0);
+    let a = (&inline)
+        .hooked(|event| {
+            if let Event::Deallocated { ptr, layout } = event {
+                let block = unsafe { core::slice::from_raw_parts(ptr.as_ptr(), layout.size()) };
+                assert!(block.iter().all(|it| *it == 0));
+                wiped.set(wiped.get() + 1);
+            }
+        })
+        .wipe_on_free();
+    let layout = Layout::new::<[u8; 8]>();
+    let ptr = a.allocate(layout).unwrap().cast::<u8>();
+    unsafe { ptr.as_ptr().write_bytes(0xAB, 8) };
+    unsafe { a.deallocate(ptr, layout) };
+    assert_eq!(wiped.get(), 1);
+    let mut v = allocator_api2::vec::Vec::<u8, _>::new_in(&a);
+    v.extend(1..=16);
+    v.truncate(2);
+    v.shrink_to_fit();
+    assert_eq!(v, [1, 2]);
+}
@@ -1,17 +1,89 @@
 use crate::prelude::*;
+use core::marker::PhantomData;
 
-/// An [`Allocator`] which always calls [`Allocator::allocate_zeroed`] on the inner allocator.
+/// How [`Zero`] gets zeroed memory from its inner allocator `A`.
+///
+/// # Safety
+/// - [`Self::allocate`] must return zeroed memory.
+pub unsafe trait Zeroing<A: Allocator> {
+    fn allocate(inner: &A, layout: Layout) -> Result<NonNull<[u8]>, AllocError>;
+}
+
+/// A [`Zeroing`] which calls [`Allocator::allocate_zeroed`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Clear;
+
+unsafe impl<A> Zeroing<A> for Clear
+where
+    A: Allocator,
+{
+    #[inline(always)]
+    fn allocate(inner: &A, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        inner.allocate_zeroed(layout)
+    }
+}
+
+/// A [`Zeroing`] for [`AllocatesZeroed`] allocators,
+/// which calls [`Allocator::allocate`],
+/// so memory is never cleared, even if `A` doesn't override [`Allocator::allocate_zeroed`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Fresh;
+
+unsafe impl<A> Zeroing<A> for Fresh
+where
+    A: AllocatesZeroed,
+{
+    #[inline(always)]
+    fn allocate(inner: &A, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        inner.allocate(layout)
+    }
+}
+
+/// An [`Allocator`] which always returns zeroed memory from [`Allocator::allocate`].
+///
+/// By default this calls [`Allocator::allocate_zeroed`] on the inner allocator,
+/// which is cheap over allocators which get zeroed memory from the OS,
+/// like [`Malloc`], which uses `calloc`.
+/// Over [`AllocatesZeroed`] allocators, use [`Zero::fresh`] to skip zeroing entirely.
 #[derive(Debug)]
-pub struct Zero<A> {
+pub struct Zero<A, Z = Clear> {
     pub inner: A,
+    pub zeroing: PhantomData<fn() -> Z>,
 }
-unsafe impl<A> Allocator for Zero<A>
+
+impl<A> Zero<A> {
+    pub const fn new(inner: A) -> Self {
+        Self {
+            inner,
+            zeroing: PhantomData,
+        }
+    }
+}
+
+impl<A> Zero<A, Fresh>
+where
+    A: AllocatesZeroed,
+{
+    pub const fn fresh(inner: A) -> Self {
+        Self {
+            inner,
+            zeroing: PhantomData,
+        }
+    }
+}
+
+unsafe impl<A, Z> Allocator for Zero<A, Z>
 where
     A: Allocator,
+    Z: Zeroing<A>,
 {
     #[inline(always)]
     fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
-        self.inner.allocate_zeroed(layout)
+        Z::allocate(&self.inner, layout)
+    }
+    #[inline(always)]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        Z::allocate(&self.inner, layout)
     }
     #[inline(always)]
     unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
@@ -45,7 +117,40 @@ where
         self.inner.shrink(ptr, old_layout, new_layout)
     }
 }
-unsafe impl<A> Owns for Zero<A>
+
+unsafe impl<A, Z> AllocatesZeroed for Zero<A, Z>
+where
+    A: Allocator,
+    Z: Zeroing<A>,
+{
+}
+
+impl<A, Z> DeallocateAll for Zero<A, Z>
+where
+    A: DeallocateAll,
+{
+    #[inline(always)]
+    unsafe fn deallocate_all(&self) {
+        self.inner.deallocate_all()
+    }
+}
+
+impl<A, Z> Rewind for Zero<A, Z>
+where
+    A: Rewind,
+{
+    type Checkpoint = A::Checkpoint;
+    #[inline(always)]
+    fn checkpoint(&self) -> Self::Checkpoint {
+        self.inner.checkpoint()
+    }
+    #[inline(always)]
+    unsafe fn rewind(&self, checkpoint: Self::Checkpoint) {
+        self.inner.rewind(checkpoint)
+    }
+}
+
+unsafe impl<A, Z> Owns for Zero<A, Z>
 where
     A: Owns,
 {
@@ -54,3 +159,54 @@ where
         self.inner.owns(ptr, layout)
     }
 }
+
+impl<A, Z> UsableSize for Zero<A, Z>
+where
+    A: UsableSize,
+{
+    #[inline(always)]
+    unsafe fn usable_size(&self, ptr: NonNull<u8>, layout: Layout) -> usize {
+        self.inner.usable_size(ptr, layout)
+    }
+}
+
+impl<A, Z> Trim for Zero<A, Z>
+where
+    A: Trim,
+{
+    #[inline(always)]
+    fn trim(&self) {
+        self.inner.trim()
+    }
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn zero() {
+    let a = Malloc.zero();
+    let it = Box::<[u64; 512], _>::new_uninit_in(&a);
+    assert!(unsafe { it.assume_init() }.iter().all(|it| *it == 0));
+    let it = Box::<[u8; 3], _>::new_uninit_in(&a);
+    assert_eq!(*unsafe { it.assume_init() }, [0; 3]);
+}
+
+#[cfg(feature = "malloc")]
+#[test]
+fn fresh() {
+    struct Calloc;
+    unsafe impl Allocator for Calloc {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            Malloc.allocate_zeroed(layout)
+        }
+        fn allocate_zeroed(&self, _: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            panic!("cleared twice")
+        }
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            Malloc.deallocate(ptr, layout)
+        }
+    }
+    unsafe impl AllocatesZeroed for Calloc {}
+    let a = Zero::fresh(Calloc);
+    let it = Box::<[u64; 512], _>::new_zeroed_in(&a);
+    assert!(unsafe { it.assume_init() }.iter().all(|it| *it == 0));
+}